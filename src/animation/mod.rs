@@ -39,6 +39,27 @@ impl AnimationConfig {
     fn timer_from_fps(fps: u8) -> Timer {
         Timer::new(Duration::from_secs_f32(1.0 / (fps as f32)), TimerMode::Once)
     }
+
+    /// Advances the animation by `delta`, moving `cur_index` to the next frame when it is time.
+    pub fn tick(&mut self, delta: Duration) {
+        self.timer.tick(delta);
+
+        if !self.timer.just_finished() {
+            return;
+        }
+
+        if self.cur_index == self.last_index {
+            if self.repeat {
+                self.cur_index = self.first_index;
+                self.timer = AnimationConfig::timer_from_fps(self.fps);
+            } else {
+                self.finished = true;
+            }
+        } else {
+            self.cur_index += 1;
+            self.timer = AnimationConfig::timer_from_fps(self.fps);
+        }
+    }
 }
 
 fn play_animations(time: Res<Time>, mut query: Query<(&mut AnimationConfig, &mut Sprite)>) {
@@ -52,23 +73,7 @@ fn play_animations(time: Res<Time>, mut query: Query<(&mut AnimationConfig, &mut
             atlas.index = config.cur_index;
         }
 
-        config.timer.tick(time.delta());
-
-        if !config.timer.just_finished() {
-            continue;
-        }
-
-        if atlas.index == config.last_index {
-            if config.repeat {
-                atlas.index = config.first_index;
-                config.timer = AnimationConfig::timer_from_fps(config.fps);
-            } else {
-                config.finished = true;
-            }
-        } else {
-            atlas.index += 1;
-            config.timer = AnimationConfig::timer_from_fps(config.fps);
-        }
-        config.cur_index = atlas.index;
+        config.tick(time.delta());
+        atlas.index = config.cur_index;
     }
 }
@@ -15,10 +15,16 @@ use bevy::{
 use bevy_rapier2d::plugin::PhysicsSet;
 
 use crate::{
-    level::{switch_level, CurrentLevel, LevelSystems},
+    config::Config,
+    input::CursorWorldCoords,
+    level::{
+        first_visit::{send_first_visit, FirstVisitEvent},
+        room::{camera_box_at, CameraRoom},
+        switch_level, CurrentLevel, LevelSystems,
+    },
     lighting::AmbientLight2d,
-    player::PlayerMarker,
-    shared::GameState,
+    player::{light::AngleMarker, PlayerMarker},
+    shared::{GameState, ResetLevel},
 };
 
 /// The [`Plugin`] responsible for handling anything Camera related.
@@ -29,7 +35,9 @@ impl Plugin for CameraPlugin {
         app.add_event::<CameraMoveEvent>()
             .add_event::<CameraZoomEvent>()
             .add_event::<CameraTransitionEvent>()
+            .add_event::<CameraShakeEvent>()
             .add_systems(Startup, setup_camera)
+            .add_systems(PreUpdate, remove_camera_shake_offset)
             .add_systems(
                 FixedUpdate,
                 move_camera
@@ -43,12 +51,17 @@ impl Plugin for CameraPlugin {
                 (
                     (
                         (handle_zoom_camera, handle_move_camera),
+                        apply_camera_shake,
                         apply_camera_snapping,
                     )
                         .chain()
                         .after(move_camera)
                         .after(switch_level),
                     handle_transition_camera,
+                    start_intro_zoom
+                        .after(send_first_visit)
+                        .before(handle_zoom_camera)
+                        .in_set(LevelSystems::Reset),
                 ),
             );
     }
@@ -71,6 +84,8 @@ pub struct BackgroundCamera;
 pub const CAMERA_WIDTH: u32 = 320;
 pub const CAMERA_HEIGHT: u32 = 180;
 pub const CAMERA_ANIMATION_SECS: f32 = 0.4;
+/// Intensity of the ambient light on the terrain camera in regular levels
+pub const AMBIENT_LIGHT_INTENSITY: f32 = 0.4;
 
 pub const TERRAIN_LAYER: RenderLayers = RenderLayers::layer(0);
 pub const HIGHRES_LAYER: RenderLayers = RenderLayers::layer(2);
@@ -198,7 +213,7 @@ pub fn setup_camera(
                 Camera2d,
                 // MatchMainCameraTransform::Nearest,
                 AmbientLight2d {
-                    color: Vec4::new(1.0, 1.0, 1.0, 0.4),
+                    color: Vec4::new(1.0, 1.0, 1.0, AMBIENT_LIGHT_INTENSITY),
                 },
                 Camera {
                     hdr: true,
@@ -235,6 +250,8 @@ pub struct CameraTransitionEvent {
     pub ease_fn: EaseFunction,
     pub callback: Option<SystemId>,
     pub effect: CameraTransition,
+    /// The color of the screen covering the camera, usually [`Color::BLACK`]
+    pub color: Color,
 }
 
 #[derive(Debug)]
@@ -245,6 +262,11 @@ pub enum CameraControlType {
         callback: Option<SystemId>,
     },
     Instant,
+    /// Keeps easing toward the target every frame until another event comes in, catching up
+    /// faster the higher the `stiffness` is. Zooming doesn't support it, and treats it as instant
+    Smooth {
+        stiffness: f32,
+    },
 }
 
 #[derive(Event, Debug)]
@@ -262,6 +284,24 @@ pub struct CameraAnimationInfo<LERP: Sized> {
     callback: Option<SystemId>,
 }
 
+/// [`Event`] that shakes the main camera, starting `amplitude` pixels away from where it would be
+/// and dying down to nothing over `duration`.
+#[derive(Event, Debug)]
+pub struct CameraShakeEvent {
+    pub amplitude: f32,
+    pub duration: Duration,
+}
+
+/// [`Component`] on the [`MainCamera`] while it is shaking. The shake offset is added on top of the
+/// camera's position after it has been moved for the frame, and taken off again before anything
+/// else moves it, so following the player and [`CameraMoveEvent`]s never see it.
+#[derive(Component, Debug)]
+pub struct CameraShake {
+    amplitude: f32,
+    timer: Timer,
+    offset: Vec2,
+}
+
 #[derive(Event, Debug)]
 pub struct CameraZoomEvent {
     pub scale: f32,
@@ -270,16 +310,23 @@ pub struct CameraZoomEvent {
 
 pub fn handle_transition_camera(
     mut commands: Commands,
-    mut q_transition_mesh: Query<&mut Transform, With<TransitionMeshMarker>>,
+    mut q_transition_mesh: Query<
+        (&mut Transform, &MeshMaterial2d<ColorMaterial>),
+        With<TransitionMeshMarker>,
+    >,
     mut ev_transition_camera: EventReader<CameraTransitionEvent>,
     mut animation: Local<Option<CameraAnimationInfo<Vec3>>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
     time: Res<Time>,
 ) {
-    let Ok(mut mesh_transform) = q_transition_mesh.get_single_mut() else {
+    let Ok((mut mesh_transform, mesh_material)) = q_transition_mesh.get_single_mut() else {
         return;
     };
 
     for event in ev_transition_camera.read() {
+        if let Some(material) = materials.get_mut(mesh_material) {
+            material.color = event.color;
+        }
         let anim = match event.effect {
             CameraTransition::SlideFromBlack => CameraAnimationInfo {
                 progress: Timer::new(event.duration, TimerMode::Once),
@@ -305,7 +352,8 @@ pub fn handle_transition_camera(
 
     anim.progress.tick(time.delta());
 
-    let percent = anim.progress.elapsed_secs() / anim.progress.duration().as_secs_f32();
+    // fraction is 1 for zero length animations, which finish on their first tick
+    let percent = anim.progress.fraction();
 
     mesh_transform.translation = anim
         .start
@@ -319,6 +367,42 @@ pub fn handle_transition_camera(
     }
 }
 
+/// [`System`] that zooms the camera in on the player when a level is entered, and then back out
+/// over the configured duration. Since the camera keeps following the player and clamping to the
+/// level at the normal scale, the zoomed in view never shows anything outside the level.
+pub fn start_intro_zoom(
+    mut ev_reset_level: EventReader<ResetLevel>,
+    mut ev_first_visit: EventReader<FirstVisitEvent>,
+    mut ev_zoom_camera: EventWriter<CameraZoomEvent>,
+    current_level: Res<CurrentLevel>,
+    config: Res<Config>,
+) {
+    let camera_config = &config.camera_config;
+    let first_visit = ev_first_visit
+        .read()
+        .any(|ev| ev.level_iid == current_level.level_iid);
+    if !ev_reset_level.read().any(|ev| *ev == ResetLevel::Switching)
+        || !camera_config.intro_zoom
+        || camera_config.disable_transitions
+        || camera_config.intro_zoom_secs <= 0.
+        || (camera_config.intro_zoom_first_visit_only && !first_visit)
+    {
+        return;
+    }
+    ev_zoom_camera.send(CameraZoomEvent {
+        scale: camera_config.intro_zoom_scale.clamp(0.1, 1.0),
+        variant: CameraControlType::Instant,
+    });
+    ev_zoom_camera.send(CameraZoomEvent {
+        scale: 1.,
+        variant: CameraControlType::Animated {
+            duration: Duration::from_secs_f32(camera_config.intro_zoom_secs),
+            ease_fn: EaseFunction::SineInOut,
+            callback: None,
+        },
+    });
+}
+
 pub fn handle_zoom_camera(
     mut commands: Commands,
     mut q_camera: Query<&mut OrthographicProjection, With<MainCamera>>,
@@ -346,8 +430,15 @@ pub fn handle_zoom_camera(
                 };
                 *animation = Some(anim);
             }
-            CameraControlType::Instant => {
+            CameraControlType::Instant | CameraControlType::Smooth { .. } => {
                 camera_projection.scale = event.scale;
+                // animations with a callback are left alone, so their callback still runs
+                if animation
+                    .as_ref()
+                    .is_some_and(|anim| anim.callback.is_none())
+                {
+                    *animation = None;
+                }
             }
         }
     }
@@ -360,7 +451,7 @@ pub fn handle_zoom_camera(
 
     anim.progress.tick(time.delta());
 
-    let percent = anim.progress.elapsed_secs() / anim.progress.duration().as_secs_f32();
+    let percent = anim.progress.fraction();
     camera_projection.scale = anim
         .start
         .lerp(anim.end, anim.curve.sample_clamped(percent));
@@ -373,11 +464,21 @@ pub fn handle_zoom_camera(
     }
 }
 
+/// How far the camera gets toward a [`CameraControlType::Smooth`] target in `delta_secs`, as a
+/// fraction of the distance left. This doesn't depend on the frame rate.
+fn smooth_follow_fraction(stiffness: f32, delta_secs: f32) -> f32 {
+    if stiffness <= 0.0 {
+        return 1.0;
+    }
+    1.0 - (-stiffness * delta_secs).exp()
+}
+
 pub fn handle_move_camera(
     mut commands: Commands,
     mut q_camera: Query<&mut Transform, With<MainCamera>>,
     mut ev_move_camera: EventReader<CameraMoveEvent>,
     mut animation: Local<Option<CameraAnimationInfo<Vec3>>>,
+    mut follow: Local<Option<(Vec2, f32)>>,
     time: Res<Time>,
 ) {
     let Ok(mut camera_transform) = q_camera.get_single_mut() else {
@@ -399,9 +500,21 @@ pub fn handle_move_camera(
                     callback,
                 };
                 *animation = Some(anim);
+                *follow = None;
             }
             CameraControlType::Instant => {
                 camera_transform.translation = event.to.extend(camera_transform.translation.z);
+                // animations with a callback are left alone, so their callback still runs
+                if animation
+                    .as_ref()
+                    .is_some_and(|anim| anim.callback.is_none())
+                {
+                    *animation = None;
+                }
+                *follow = None;
+            }
+            CameraControlType::Smooth { stiffness } => {
+                *follow = Some((event.to, stiffness));
             }
         }
     }
@@ -409,12 +522,21 @@ pub fn handle_move_camera(
     // This is a reborrow, something that treats Bevy's "smart pointers" as actual Rust references,
     // which allows you to do the things you are supposed to (like pattern match on them).
     let Some(anim) = &mut *animation else {
+        // both the camera and the target are clamped to the level, so everything in between is too
+        if let Some((target, stiffness)) = *follow {
+            let fraction = smooth_follow_fraction(stiffness, time.delta_secs());
+            camera_transform.translation = camera_transform
+                .translation
+                .xy()
+                .lerp(target, fraction)
+                .extend(camera_transform.translation.z);
+        }
         return;
     };
 
     anim.progress.tick(time.delta());
 
-    let percent = anim.progress.elapsed_secs() / anim.progress.duration().as_secs_f32();
+    let percent = anim.progress.fraction();
     camera_transform.translation = anim
         .start
         .lerp(anim.end, anim.curve.sample_clamped(percent));
@@ -427,6 +549,57 @@ pub fn handle_move_camera(
     }
 }
 
+/// [`System`] that takes the shake offset added by [`apply_camera_shake`] last frame back off the
+/// camera, leaving its base position.
+pub fn remove_camera_shake_offset(mut q_camera: Query<(&mut Transform, &mut CameraShake)>) {
+    for (mut transform, mut shake) in q_camera.iter_mut() {
+        transform.translation -= shake.offset.extend(0.);
+        shake.offset = Vec2::ZERO;
+    }
+}
+
+/// [`System`] that starts shaking the camera on a [`CameraShakeEvent`], and offsets it from its
+/// base position while it shakes. A shake started during another keeps the stronger amplitude.
+pub fn apply_camera_shake(
+    mut commands: Commands,
+    mut q_camera: Query<(Entity, &mut Transform, Option<&mut CameraShake>), With<MainCamera>>,
+    mut ev_camera_shake: EventReader<CameraShakeEvent>,
+    time: Res<Time>,
+) {
+    let Ok((camera_entity, mut transform, shake)) = q_camera.get_single_mut() else {
+        return;
+    };
+    let started = ev_camera_shake
+        .read()
+        .max_by(|a, b| a.amplitude.total_cmp(&b.amplitude));
+
+    let Some(mut shake) = shake else {
+        if let Some(event) = started {
+            commands.entity(camera_entity).insert(CameraShake {
+                amplitude: event.amplitude,
+                timer: Timer::new(event.duration, TimerMode::Once),
+                offset: Vec2::ZERO,
+            });
+        }
+        return;
+    };
+    if let Some(event) = started {
+        if event.amplitude >= shake.amplitude * shake.timer.fraction_remaining() {
+            shake.amplitude = event.amplitude;
+            shake.timer = Timer::new(event.duration, TimerMode::Once);
+        }
+    }
+
+    shake.timer.tick(time.delta());
+    if shake.timer.finished() {
+        commands.entity(camera_entity).remove::<CameraShake>();
+        return;
+    }
+    let amplitude = shake.amplitude * shake.timer.fraction_remaining();
+    shake.offset = Vec2::from_angle(rand::random_range(0.0..std::f32::consts::TAU)) * amplitude;
+    transform.translation += shake.offset.extend(0.);
+}
+
 pub fn camera_position_from_level_with_scale(
     level_box: Rect,
     player_pos: Vec2,
@@ -451,11 +624,21 @@ pub fn camera_position_from_level(level_box: Rect, player_pos: Vec2) -> Vec2 {
     camera_position_from_level_with_scale(level_box, player_pos, 1.)
 }
 
-/// [`System`] that moves camera to player's position and constrains it to the [`CurrentLevel`]'s `world_box`.
+/// [`System`] that moves camera to player's position and constrains it to the [`CurrentLevel`]'s
+/// `world_box`, or the [`CameraRoom`] the player is in. The camera follows with
+/// [`CameraControlType::Smooth`], so crossing into another room pans the camera over.
+///
+/// While the player is aiming, the camera is biased toward the cursor by the configured
+/// `aim_bias`. This system doesn't run while animating, so respawns always center on the player.
+#[allow(clippy::too_many_arguments)]
 pub fn move_camera(
     current_level: Res<CurrentLevel>,
     q_player: Query<&Transform, With<PlayerMarker>>,
     q_camera: Query<&Transform, With<MainCamera>>,
+    q_angle: Query<(), With<AngleMarker>>,
+    q_cursor: Query<&CursorWorldCoords>,
+    q_rooms: Query<&CameraRoom>,
+    config: Res<Config>,
     mut ev_move_camera: EventWriter<CameraMoveEvent>,
 ) {
     let Ok(player_transform) = q_player.get_single() else {
@@ -465,10 +648,37 @@ pub fn move_camera(
         return;
     };
 
-    let camera_pos =
-        camera_position_from_level(current_level.level_box, player_transform.translation.xy());
+    let player_pos = player_transform.translation.xy();
+    let camera_box = camera_box_at(q_rooms.iter(), current_level.level_box, player_pos);
+
+    let mut target = player_pos;
+    let aim_bias = config.camera_config.aim_bias.clamp(0.0, 1.0);
+    if let Ok(cursor) = q_cursor.get_single() {
+        if aim_bias > 0.0 && !q_angle.is_empty() {
+            // measure from the camera rather than the player, otherwise moving the camera would
+            // move the cursor and feed back into itself
+            target += (cursor.pos - camera_transform.translation.xy()) * aim_bias;
+        }
+    }
+
     ev_move_camera.send(CameraMoveEvent {
-        to: camera_transform.translation.xy().lerp(camera_pos, 0.2),
-        variant: CameraControlType::Instant,
+        to: camera_position_from_level(camera_box, target),
+        variant: CameraControlType::Smooth {
+            stiffness: config.camera_config.follow_stiffness,
+        },
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smooth_follow_ignores_frame_rate() {
+        let stiffness = 14.0;
+        let left_after_one_frame = 1.0 - smooth_follow_fraction(stiffness, 1.0 / 30.0);
+        let left_after_two_frames = (1.0 - smooth_follow_fraction(stiffness, 1.0 / 60.0)).powi(2);
+        assert!((left_after_one_frame - left_after_two_frames).abs() < 1e-5);
+        assert_eq!(smooth_follow_fraction(0.0, 1.0 / 60.0), 1.0);
+    }
+}
@@ -2,6 +2,12 @@ use bevy::prelude::KeyCode;
 use bevy::prelude::*;
 use serde::Deserialize;
 
+use crate::light::{palette::PaletteMode, LightColor};
+use crate::player::movement::{
+    COYOTE_TIME_TICKS, PLAYER_GRAVITY, PLAYER_JUMP_VEL, PLAYER_MAX_H_VEL, PLAYER_MAX_Y_VEL,
+    PLAYER_MOVE_VEL, SHOULD_JUMP_TICKS,
+};
+
 pub struct ConfigPlugin;
 
 impl Plugin for ConfigPlugin {
@@ -19,6 +25,38 @@ pub struct Config {
     pub level_config: LevelConfig,
     pub debug_config: DebugConfig,
     pub controls_config: ControlsConfig,
+    #[serde(default)]
+    pub player_config: PlayerConfig,
+    #[serde(default)]
+    pub camera_config: CameraConfig,
+    #[serde(default)]
+    pub assist_config: AssistConfig,
+    #[serde(default)]
+    pub darkness_config: DarknessConfig,
+    #[serde(default)]
+    pub hardcore_config: HardcoreConfig,
+    #[serde(default)]
+    pub combo_config: ComboConfig,
+    #[serde(default)]
+    pub tutorial_config: TutorialConfig,
+    #[serde(default)]
+    pub telemetry_config: TelemetryConfig,
+    #[serde(default)]
+    pub kill_transition_config: KillTransitionConfig,
+    #[serde(default)]
+    pub repeat_death_config: RepeatDeathConfig,
+    #[serde(default)]
+    pub hud_config: HudConfig,
+    #[serde(default)]
+    pub hazard_config: HazardConfig,
+    #[serde(default)]
+    pub light_config: LightConfig,
+    #[serde(default)]
+    pub gameplay_config: GameplayConfig,
+    #[serde(default)]
+    pub audio_config: AudioConfig,
+    #[serde(default)]
+    pub movement_config: MovementConfig,
 }
 
 impl Default for Config {
@@ -26,6 +64,14 @@ impl Default for Config {
         Config {
             level_config: LevelConfig {
                 level_path: "levels/lightborne.ldtk".into(),
+                out_of_bounds_grace_ticks: default_out_of_bounds_grace_ticks(),
+                kill_during_animation: KillDuringAnimation::default(),
+                death_penalty_threshold: 0,
+                tint_affects_light: false,
+                duplicate_shard: DuplicateShardBehavior::default(),
+                hurt_shard_overlap: HurtShardOverlap::default(),
+                shard_cutscene: default_shard_cutscene(),
+                completion_summary: false,
             },
             debug_config: DebugConfig::default(),
             controls_config: ControlsConfig {
@@ -35,20 +81,204 @@ impl Default for Config {
                 key_left: KeyCode::KeyA,
                 key_right: KeyCode::KeyD,
                 key_jump: KeyCode::Space,
+                // Quicksaving
+                key_quicksave: default_key_quicksave(),
+                key_quickload: default_key_quickload(),
+                key_reset: default_key_reset(),
+                reset_grace_millis: default_reset_grace_millis(),
+                key_undo_color: default_key_undo_color(),
+                key_drop_through: default_key_drop_through(),
+                // Gamepad
+                gamepad_jump: default_gamepad_jump(),
+                gamepad_reset: default_gamepad_reset(),
+                gamepad_shoot: default_gamepad_shoot(),
+                gamepad_cancel_shoot: default_gamepad_cancel_shoot(),
+                gamepad_deadzone: default_gamepad_deadzone(),
             },
+            player_config: PlayerConfig::default(),
+            camera_config: CameraConfig::default(),
+            assist_config: AssistConfig::default(),
+            darkness_config: DarknessConfig::default(),
+            hardcore_config: HardcoreConfig::default(),
+            combo_config: ComboConfig::default(),
+            tutorial_config: TutorialConfig::default(),
+            telemetry_config: TelemetryConfig::default(),
+            kill_transition_config: KillTransitionConfig::default(),
+            repeat_death_config: RepeatDeathConfig::default(),
+            hud_config: HudConfig::default(),
+            hazard_config: HazardConfig::default(),
+            light_config: LightConfig::default(),
+            gameplay_config: GameplayConfig::default(),
+            audio_config: AudioConfig::default(),
+            movement_config: MovementConfig::default(),
         }
     }
 }
 
-#[derive(Deserialize, Default)]
+#[derive(Deserialize)]
+#[serde(default)]
 pub struct DebugConfig {
+    /// Master switch for all debug overlays. Each overlay also has to be turned on individually,
+    /// though they all default to on.
+    pub enabled: bool,
+    /// Old name for `enabled`, kept so existing configs keep working
     pub ui: bool,
+    /// Shows the egui inspector window
+    pub inspector: bool,
+    /// Draws the raycast path of every light beam
+    pub light_beams: bool,
+    /// Lets F5 snapshot the current level and F9 restore the snapshot
+    pub snapshots: bool,
+    /// Lets F7 instantly collect every shard in the current level
+    pub collect_shards: bool,
     pub unlock_levels: bool,
+    /// Shows a window with how many light beams are active and how much they cost to simulate
+    pub light_stats: bool,
+    /// Shows a window with the player's current color and the colors available in the level
+    pub light_inventory: bool,
+    /// Lets F4 toggle flying around freely, through terrain and hazards
+    pub fly: bool,
+    /// Shows a window with a button to warp to the start of each level
+    pub warp: bool,
+}
+
+impl Default for DebugConfig {
+    fn default() -> Self {
+        DebugConfig {
+            enabled: false,
+            ui: false,
+            inspector: true,
+            light_beams: true,
+            snapshots: true,
+            collect_shards: true,
+            unlock_levels: false,
+            light_stats: true,
+            light_inventory: true,
+            fly: true,
+            warp: true,
+        }
+    }
+}
+
+impl DebugConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled || self.ui
+    }
+
+    pub fn show_inspector(&self) -> bool {
+        self.is_enabled() && self.inspector
+    }
+
+    pub fn show_light_beams(&self) -> bool {
+        self.is_enabled() && self.light_beams
+    }
+
+    pub fn allow_snapshots(&self) -> bool {
+        self.is_enabled() && self.snapshots
+    }
+
+    pub fn allow_collect_shards(&self) -> bool {
+        self.is_enabled() && self.collect_shards
+    }
+
+    pub fn show_light_stats(&self) -> bool {
+        self.is_enabled() && self.light_stats
+    }
+
+    pub fn show_light_inventory(&self) -> bool {
+        self.is_enabled() && self.light_inventory
+    }
+
+    pub fn allow_fly(&self) -> bool {
+        self.is_enabled() && self.fly
+    }
+
+    pub fn allow_warp(&self) -> bool {
+        self.is_enabled() && self.warp
+    }
 }
 
 #[derive(Deserialize)]
 pub struct LevelConfig {
     pub level_path: String,
+    /// How many [`FixedUpdate`] ticks the player has to stay out of bounds before they are killed
+    #[serde(default = "default_out_of_bounds_grace_ticks")]
+    pub out_of_bounds_grace_ticks: u32,
+    /// What happens to kills while an animation is playing
+    #[serde(default)]
+    pub kill_during_animation: KillDuringAnimation,
+    /// After this many deaths in one attempt at a level, the whole level is reset as a penalty. 0
+    /// disables the penalty
+    #[serde(default)]
+    pub death_penalty_threshold: u32,
+    /// Whether a level's ambient tint also dims light beams of other colors and slows down the
+    /// sensors they charge, instead of only coloring the scene
+    #[serde(default)]
+    pub tint_affects_light: bool,
+    /// What happens when the player touches a shard whose color another shard in the level already
+    /// granted
+    #[serde(default)]
+    pub duplicate_shard: DuplicateShardBehavior,
+    /// What happens when the player is killed by a hazard in the same tick they touch a shard
+    #[serde(default)]
+    pub hurt_shard_overlap: HurtShardOverlap,
+    /// Whether collecting a shard plays its cutscene. Speedrunners can turn it off to have the
+    /// shard's color granted without stopping
+    #[serde(default = "default_shard_cutscene")]
+    pub shard_cutscene: bool,
+    /// Whether completing a level pauses the game on a summary screen until a key is pressed,
+    /// instead of briefly showing the stats. Levels can opt out with the `SkipCompletionSummary`
+    /// level field
+    #[serde(default)]
+    pub completion_summary: bool,
+}
+
+fn default_out_of_bounds_grace_ticks() -> u32 {
+    10
+}
+
+fn default_shard_cutscene() -> bool {
+    true
+}
+
+/// What to do with a shard whose color was already granted by another shard in the same level.
+/// Shards of a color the level allows on its own aren't duplicates, and are always collected.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateShardBehavior {
+    /// Collect it with the full shard animation, as if it was the first
+    #[default]
+    Collect,
+    /// Collect it without the animation, so it still counts towards the collected shards
+    CollectQuietly,
+    /// Leave it where it is, until the player dies and the first shard's color is taken away
+    Ignore,
+}
+
+/// Which wins when the player touches a hazard and a shard at the same time, like a shard placed
+/// inside spikes.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum HurtShardOverlap {
+    /// The player dies without collecting the shard
+    #[default]
+    KillFirst,
+    /// The shard is collected first, and the kill is handled like any other kill during the shard
+    /// animation, see [`KillDuringAnimation`]
+    CollectFirst,
+}
+
+/// What to do when the player is killed while an animation is playing. Kills from before the
+/// player respawns are always dropped, since they are already dying.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum KillDuringAnimation {
+    /// Kill the player once the animation finishes, so touching a hazard during the shard
+    /// animation still counts
+    #[default]
+    Queue,
+    /// Drop the kill
+    Ignore,
 }
 
 #[derive(Deserialize)]
@@ -59,5 +289,709 @@ pub struct ControlsConfig {
     pub key_right: KeyCode,
     pub key_left: KeyCode,
     pub key_jump: KeyCode,
+    // Quicksaving
+    #[serde(default = "default_key_quicksave")]
+    pub key_quicksave: KeyCode,
+    #[serde(default = "default_key_quickload")]
+    pub key_quickload: KeyCode,
+    /// Kills the player to restart from the last checkpoint
+    #[serde(default = "default_key_reset")]
+    pub key_reset: KeyCode,
+    /// How long after respawning the reset key is ignored, so a rapid retry doesn't immediately
+    /// kill the player again
+    #[serde(default = "default_reset_grace_millis")]
+    pub reset_grace_millis: u64,
+    /// Switches back to the color held before the last color switch
+    #[serde(default = "default_key_undo_color")]
+    pub key_undo_color: KeyCode,
+    /// Held while standing on a one way platform to drop through it
+    #[serde(default = "default_key_drop_through")]
+    pub key_drop_through: KeyCode,
+    // Gamepad, which works alongside the keyboard. The left stick and d-pad move, and the right
+    // stick aims
+    #[serde(default = "default_gamepad_jump")]
+    pub gamepad_jump: GamepadButton,
+    #[serde(default = "default_gamepad_reset")]
+    pub gamepad_reset: GamepadButton,
+    /// Held to aim like the left mouse button, and released to shoot
+    #[serde(default = "default_gamepad_shoot")]
+    pub gamepad_shoot: GamepadButton,
+    /// Stops aiming without shooting, like the right mouse button
+    #[serde(default = "default_gamepad_cancel_shoot")]
+    pub gamepad_cancel_shoot: GamepadButton,
+    /// How far the sticks have to be pushed before they count, from 0 to 1
+    #[serde(default = "default_gamepad_deadzone")]
+    pub gamepad_deadzone: f32,
+}
+
+fn default_key_quicksave() -> KeyCode {
+    KeyCode::F6
+}
+
+fn default_key_quickload() -> KeyCode {
+    KeyCode::F8
+}
+
+fn default_key_reset() -> KeyCode {
+    KeyCode::KeyR
+}
+
+fn default_reset_grace_millis() -> u64 {
+    150
+}
+
+fn default_key_undo_color() -> KeyCode {
+    KeyCode::KeyQ
+}
+
+fn default_key_drop_through() -> KeyCode {
+    KeyCode::KeyS
+}
+
+fn default_gamepad_jump() -> GamepadButton {
+    GamepadButton::South
+}
+
+fn default_gamepad_reset() -> GamepadButton {
+    GamepadButton::Select
+}
+
+fn default_gamepad_shoot() -> GamepadButton {
+    GamepadButton::RightTrigger2
+}
+
+fn default_gamepad_cancel_shoot() -> GamepadButton {
+    GamepadButton::LeftTrigger2
+}
+
+fn default_gamepad_deadzone() -> f32 {
+    0.3
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct PlayerConfig {
+    /// Whether the player sprite turns to face the direction it is moving in
+    pub flip_with_movement: bool,
+    /// How fast the player has to move horizontally before the sprite turns around, so that tiny
+    /// movements don't make it flicker
+    pub facing_deadzone: f32,
+    /// How many seconds Lyra takes to materialize at the spawn point after respawning, during which
+    /// input is held, or 0 to respawn without it. Jump skips the rest of it
+    pub spawn_in_secs: f32,
+    /// What happens to the selected light color once it stops being allowed
+    pub disallowed_color: DisallowedColorBehavior,
+    /// How much of the player's horizontal speed is lost every tick while no direction is held,
+    /// from 0 (sliding forever) to 1 (stopping instantly)
+    pub friction: f32,
+    /// How much of the player's speed is kept when bouncing off a wall, where 0 doesn't bounce
+    pub wall_restitution: f32,
+    /// How much of the player's falling speed is kept when bouncing off the floor, where 0 doesn't
+    /// bounce
+    pub floor_restitution: f32,
+    /// How holding each color changes the player's movement
+    pub green_modifier: MovementModifier,
+    pub purple_modifier: MovementModifier,
+    pub white_modifier: MovementModifier,
+    pub blue_modifier: MovementModifier,
+    pub black_modifier: MovementModifier,
+    /// The sprite sheet the player is drawn with
+    pub skin: PlayerSkinConfig,
+    /// How many beams the player can have active at once, or 0 for no limit. Shooting past the
+    /// limit drops the oldest beam, which gives its color back
+    pub max_active_beams: usize,
+    /// How many ticks movement input is ignored for once the death transition ends, so a direction
+    /// held through the death doesn't walk the player back into the hazard. 0 doesn't ignore any
+    pub respawn_input_lockout_ticks: u32,
+}
+
+impl PlayerConfig {
+    pub fn movement_modifier(&self, color: LightColor) -> MovementModifier {
+        match color {
+            LightColor::Green => self.green_modifier,
+            LightColor::Purple => self.purple_modifier,
+            LightColor::White => self.white_modifier,
+            LightColor::Blue => self.blue_modifier,
+            LightColor::Black => self.black_modifier,
+        }
+    }
+}
+
+/// Multipliers applied to the player's movement while holding a color, where 1 leaves it unchanged
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct MovementModifier {
+    /// Multiplier for the jump velocity
+    pub jump: f32,
+    /// Multiplier for the horizontal acceleration and top speed
+    pub run: f32,
+    /// Multiplier for gravity while falling
+    pub fall: f32,
+}
+
+impl Default for MovementModifier {
+    fn default() -> Self {
+        MovementModifier {
+            jump: 1.0,
+            run: 1.0,
+            fall: 1.0,
+        }
+    }
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        PlayerConfig {
+            flip_with_movement: true,
+            facing_deadzone: 0.01,
+            spawn_in_secs: 0.0,
+            disallowed_color: DisallowedColorBehavior::default(),
+            friction: 0.4,
+            wall_restitution: 0.0,
+            floor_restitution: 0.0,
+            green_modifier: MovementModifier::default(),
+            purple_modifier: MovementModifier::default(),
+            white_modifier: MovementModifier::default(),
+            blue_modifier: MovementModifier::default(),
+            black_modifier: MovementModifier::default(),
+            skin: PlayerSkinConfig::default(),
+            max_active_beams: 0,
+            respawn_input_lockout_ticks: 2,
+        }
+    }
+}
+
+/// A sprite sheet for the player, laid out as a single row of frames. The animations and the hair
+/// and cloth strands are tied to the frames of the default sheet, so a skin has to have the same
+/// frames in the same order.
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct PlayerSkinConfig {
+    /// Path of the sheet in the assets folder
+    pub path: String,
+    pub frame_width: u32,
+    pub frame_height: u32,
+    pub frames: u32,
+}
+
+impl Default for PlayerSkinConfig {
+    fn default() -> Self {
+        PlayerSkinConfig {
+            path: "lyra_sheet.png".into(),
+            frame_width: 15,
+            frame_height: 20,
+            frames: 29,
+        }
+    }
+}
+
+/// What to do when the player's selected light color is no longer allowed, e.g. after the shard
+/// that granted it is reset on death. If no colors are allowed at all, the player is always left
+/// without a color and can keep moving, but can't shoot.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DisallowedColorBehavior {
+    /// Switch to the first color that is still allowed
+    #[default]
+    Fallback,
+    /// Deselect the color, so the player has to pick a new one
+    Deselect,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct CameraConfig {
+    /// How far the camera leans toward the cursor while aiming, as a fraction of the distance
+    /// from the center of the screen to the cursor. Set to 0 to disable.
+    pub aim_bias: f32,
+    /// Makes respawns and level switches instant instead of fading or panning the camera, for
+    /// recording clean footage
+    pub disable_transitions: bool,
+    /// How opaque the screen flashes in a shard's color when it is collected, between 0 for no
+    /// flash and 1. Lower this, or turn on `reduce_flashing` in the assist config, if flashing
+    /// lights bother you.
+    pub shard_flash_intensity: f32,
+    /// Starts every newly entered level zoomed in on the player, then zooms out to the normal
+    /// framing. Respawns aren't affected
+    pub intro_zoom: bool,
+    /// The camera scale the intro zoom starts at, where lower is more zoomed in
+    pub intro_zoom_scale: f32,
+    pub intro_zoom_secs: f32,
+    /// Only plays the intro zoom the first time a level is ever entered, see
+    /// [`FirstVisitEvent`](crate::level::first_visit::FirstVisitEvent)
+    pub intro_zoom_first_visit_only: bool,
+    /// How far, in pixels, the camera shakes when the player dies. Set to 0 to disable
+    pub death_shake_amplitude: f32,
+    /// How long the death shake takes to die down
+    pub death_shake_secs: f32,
+    /// Slowly zooms in on where the player died while the screen fades out, before cutting to the
+    /// respawn. Levels that keep the camera on respawn don't zoom
+    pub death_zoom: bool,
+    /// The camera scale the death zoom ends at, where lower is more zoomed in
+    pub death_zoom_scale: f32,
+    /// How quickly the camera catches up with the player during normal play, where higher is
+    /// stiffer. Set to 0 to follow the player without any smoothing
+    pub follow_stiffness: f32,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        CameraConfig {
+            aim_bias: 0.0,
+            disable_transitions: false,
+            shard_flash_intensity: 0.35,
+            intro_zoom: false,
+            intro_zoom_scale: 0.6,
+            intro_zoom_secs: 1.5,
+            intro_zoom_first_visit_only: false,
+            death_shake_amplitude: 2.0,
+            death_shake_secs: 0.25,
+            death_zoom: false,
+            death_zoom_scale: 0.8,
+            follow_stiffness: 14.0,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct AssistConfig {
+    /// How fast time passes while aiming with the aim slowdown setting on, where 0 freezes time
+    /// completely.
+    pub aim_time_scale: f32,
+    /// Multiplier for how fast hazards go: how fast moving platforms move, and how soon hazards
+    /// with `contact_ticks`, like electricity, kill
+    pub hazard_speed: f32,
+    /// Whether falling out of the level right after walking off a ledge puts the player back on
+    /// the ledge instead of killing them
+    pub edge_assist: bool,
+    /// How many [`FixedUpdate`] ticks after leaving the ground the edge assist still applies
+    pub edge_assist_grace_ticks: u32,
+    /// Whether the player is nudged up onto ledges they barely miss while jumping
+    pub ledge_assist: bool,
+    /// How far above the player's feet, in pixels, the top of a ledge can be for the ledge assist
+    /// to apply
+    pub ledge_assist_tolerance: f32,
+    /// Whether the player is nudged onto platforms whose edge they barely miss while falling
+    pub landing_assist: bool,
+    /// How far past the edge of a platform, in pixels, the player can fall for the landing assist
+    /// to apply
+    pub landing_assist_tolerance: f32,
+    /// Single switch mode for movement only, where the player runs on their own and turns around
+    /// at walls, and the jump key jumps while on the ground and turns around in the air. The game
+    /// has nothing to interact with, and aiming and shooting light aren't mapped to the switch and
+    /// still need the mouse or a gamepad. Levels that need light, or precise tricks like stopping
+    /// mid-air, can't be finished with the switch alone
+    pub one_button: bool,
+    /// The palette the light colors are drawn with, either Standard or Colorblind, which changes
+    /// their hues and draws a symbol on each shard
+    pub color_palette: PaletteMode,
+    /// Whether flashing effects, like the screen flash when collecting a shard, are turned off
+    pub reduce_flashing: bool,
+}
+
+impl Default for AssistConfig {
+    fn default() -> Self {
+        AssistConfig {
+            aim_time_scale: 0.25,
+            hazard_speed: 1.0,
+            edge_assist: false,
+            edge_assist_grace_ticks: 20,
+            ledge_assist: false,
+            ledge_assist_tolerance: 4.0,
+            landing_assist: false,
+            landing_assist_tolerance: 2.0,
+            one_button: false,
+            color_palette: PaletteMode::Standard,
+            reduce_flashing: false,
+        }
+    }
+}
+
+/// How each kind of hazard hurts the player.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct HazardConfig {
+    pub spike: HazardParams,
+    pub lava: HazardParams,
+    pub crusher: HazardParams,
+    pub electric: HazardParams,
+}
+
+impl Default for HazardConfig {
+    fn default() -> Self {
+        HazardConfig {
+            spike: HazardParams::default(),
+            lava: HazardParams::default(),
+            crusher: HazardParams::default(),
+            electric: HazardParams {
+                // electricity arcs, so brushing past it is survivable
+                contact_ticks: 4,
+                ..default()
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct HazardParams {
+    /// The sound played when the hazard kills the player
+    pub death_sound: String,
+    /// How many [`FixedUpdate`] ticks the player can touch the hazard for before dying, where 0
+    /// kills them on contact
+    pub contact_ticks: u32,
+}
+
+impl Default for HazardParams {
+    fn default() -> Self {
+        HazardParams {
+            death_sound: "sfx/death.wav".into(),
+            contact_ticks: 0,
+        }
+    }
 }
 
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct DarknessConfig {
+    /// Brightness of the ambient light in levels with the `Darkness` field set, where the default
+    /// in other levels is 0.4
+    pub ambient_intensity: f32,
+    /// How far around the player is revealed in dark levels while holding each color, in pixels
+    pub green_radius: f32,
+    pub purple_radius: f32,
+    pub white_radius: f32,
+    pub blue_radius: f32,
+    pub black_radius: f32,
+    /// How far around the player is revealed in dark levels while not holding any color
+    pub no_color_radius: f32,
+}
+
+impl DarknessConfig {
+    pub fn reveal_radius(&self, color: Option<LightColor>) -> f32 {
+        match color {
+            Some(LightColor::Green) => self.green_radius,
+            Some(LightColor::Purple) => self.purple_radius,
+            Some(LightColor::White) => self.white_radius,
+            Some(LightColor::Blue) => self.blue_radius,
+            Some(LightColor::Black) => self.black_radius,
+            None => self.no_color_radius,
+        }
+    }
+}
+
+impl Default for DarknessConfig {
+    fn default() -> Self {
+        DarknessConfig {
+            ambient_intensity: 0.05,
+            green_radius: 40.0,
+            purple_radius: 40.0,
+            white_radius: 56.0,
+            blue_radius: 40.0,
+            black_radius: 20.0,
+            no_color_radius: 24.0,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct HardcoreConfig {
+    /// Whether running out of lives sends the player back to the start menu, ending the run
+    pub enabled: bool,
+    /// How many deaths the player can afford in a run, where 1 means a single death ends it
+    pub lives: u32,
+}
+
+impl Default for HardcoreConfig {
+    fn default() -> Self {
+        HardcoreConfig {
+            enabled: false,
+            lives: 1,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct ComboConfig {
+    /// Whether collecting shards and completing levels without dying builds up a score
+    pub enabled: bool,
+    /// Points for each shard collected, before the multiplier
+    pub shard_points: u32,
+    /// Points for each level completed, before the multiplier
+    pub level_points: u32,
+    /// The combo needed for each step of the multiplier, in increasing order. The multiplier
+    /// starts at 1 and goes up by 1 for every threshold reached.
+    pub multiplier_thresholds: Vec<u32>,
+}
+
+impl Default for ComboConfig {
+    fn default() -> Self {
+        ComboConfig {
+            enabled: false,
+            shard_points: 100,
+            level_points: 500,
+            multiplier_thresholds: vec![3, 6, 10],
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct TutorialConfig {
+    /// Whether tutorial zones show their tutorial the first time the player enters them
+    pub enabled: bool,
+    /// How fast time passes while a tutorial is shown, where 0 pauses the game until it is
+    /// dismissed
+    pub time_scale: f32,
+}
+
+impl Default for TutorialConfig {
+    fn default() -> Self {
+        TutorialConfig {
+            enabled: true,
+            time_scale: 0.0,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    /// Whether deaths, collected shards and level completions are logged for playtesting
+    pub enabled: bool,
+    /// The CSV file rows are appended to
+    pub path: String,
+    /// How often buffered rows are written to disk, in seconds
+    pub flush_secs: f32,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        TelemetryConfig {
+            enabled: false,
+            path: "lightborne_telemetry.csv".into(),
+            flush_secs: 10.0,
+        }
+    }
+}
+
+/// The curve the death transition slides the screen along, see [`EaseFunction`]
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionEase {
+    Linear,
+    SineIn,
+    SineOut,
+    #[default]
+    SineInOut,
+    QuadraticInOut,
+    CubicInOut,
+    ExponentialInOut,
+}
+
+impl From<TransitionEase> for EaseFunction {
+    fn from(ease: TransitionEase) -> Self {
+        match ease {
+            TransitionEase::Linear => EaseFunction::Linear,
+            TransitionEase::SineIn => EaseFunction::SineIn,
+            TransitionEase::SineOut => EaseFunction::SineOut,
+            TransitionEase::SineInOut => EaseFunction::SineInOut,
+            TransitionEase::QuadraticInOut => EaseFunction::QuadraticInOut,
+            TransitionEase::CubicInOut => EaseFunction::CubicInOut,
+            TransitionEase::ExponentialInOut => EaseFunction::ExponentialInOut,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct KillTransitionConfig {
+    /// How long, in milliseconds, the screen takes to slide over the player when they die. 0
+    /// covers it right away
+    pub slide_to_black_ms: u64,
+    /// How long, in milliseconds, the screen takes to slide away again once the player has
+    /// respawned. 0 uncovers it right away
+    pub slide_from_black_ms: u64,
+    /// The curve both halves of the transition follow
+    pub ease: TransitionEase,
+}
+
+impl Default for KillTransitionConfig {
+    fn default() -> Self {
+        KillTransitionConfig {
+            slide_to_black_ms: 400,
+            slide_from_black_ms: 400,
+            ease: TransitionEase::default(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct RepeatDeathConfig {
+    /// Whether dying again close to where the player last died uses a shorter death transition
+    pub enabled: bool,
+    /// How close, in pixels, a death has to be to the last one to count as a repeat
+    pub radius: f32,
+    /// How soon, in seconds, a death has to come after the last one to count as a repeat
+    pub window_secs: f32,
+    /// How long each half of the transition takes for repeated deaths, where 0 respawns instantly
+    pub transition_millis: u64,
+}
+
+impl Default for RepeatDeathConfig {
+    fn default() -> Self {
+        RepeatDeathConfig {
+            enabled: false,
+            radius: 24.0,
+            window_secs: 10.0,
+            transition_millis: 150,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct HudConfig {
+    /// Whether the HUD with the current color and the deaths in the current level is shown. This
+    /// is separate from the debug ui
+    pub hud: bool,
+    /// Hides the HUD, or shows it again
+    pub key_toggle: KeyCode,
+    /// How many seconds a level's intro title is shown for, including fading it in and out, or 0
+    /// to never show them
+    pub intro_secs: f32,
+    /// Clears the level's intro title right away
+    pub key_skip_intro: KeyCode,
+}
+
+impl Default for HudConfig {
+    fn default() -> Self {
+        HudConfig {
+            hud: false,
+            key_toggle: KeyCode::KeyH,
+            intro_secs: 3.0,
+            key_skip_intro: KeyCode::Enter,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct LightConfig {
+    /// How many segments a light beam can have at most, counting the bounces off mirrors that
+    /// don't use up one of the beam's own bounces. This stops two mirrors facing each other from
+    /// reflecting a beam forever
+    pub max_reflection_depth: usize,
+}
+
+impl Default for LightConfig {
+    fn default() -> Self {
+        LightConfig {
+            max_reflection_depth: 15,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct GameplayConfig {
+    /// How many [`FixedUpdate`] ticks in a row the player can touch any hurt box before dying.
+    /// Hazards with a longer `contact_ticks` keep theirs
+    pub hurt_grace_ticks: u32,
+}
+
+impl Default for GameplayConfig {
+    fn default() -> Self {
+        GameplayConfig {
+            hurt_grace_ticks: 2,
+        }
+    }
+}
+
+/// The volumes the game starts with, from 0 (muted) to 1. See
+/// [`AudioSettings`](crate::sound::AudioSettings)
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    /// Scales every sound, and is what the volume setting changes
+    pub master: f32,
+    /// Scales sound effects, like the death sound
+    pub sfx: f32,
+    /// Scales the background music
+    pub music: f32,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        AudioConfig {
+            master: 1.0,
+            sfx: 1.0,
+            music: 1.0,
+        }
+    }
+}
+
+/// Tuning for how the player moves, in pixels per [`FixedUpdate`] tick. Values out of their range
+/// are clamped into it when read, so a typo can't make the game unplayable.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct MovementConfig {
+    /// Multiplies how fast the player accelerates downwards, from 0.1 to 4
+    pub gravity_scale: f32,
+    /// The fastest the player can fall, from 1 to 15
+    pub max_fall_speed: f32,
+    /// The upwards velocity the player is given while jumping, which sets how high they jump, from
+    /// 0.5 to 5
+    pub jump_impulse: f32,
+    /// The horizontal velocity added every tick a direction is held, from 0.05 to 1.5
+    pub horizontal_acceleration: f32,
+    /// How many ticks after walking off a ledge the player can still jump, up to 30
+    pub coyote_time_ticks: u32,
+    /// How many ticks a jump pressed in the air is remembered, so it still goes through if the
+    /// player lands in time, up to 30
+    pub jump_buffer_ticks: u32,
+}
+
+impl Default for MovementConfig {
+    fn default() -> Self {
+        MovementConfig {
+            gravity_scale: 1.0,
+            max_fall_speed: PLAYER_MAX_Y_VEL,
+            jump_impulse: PLAYER_JUMP_VEL,
+            horizontal_acceleration: PLAYER_MOVE_VEL,
+            coyote_time_ticks: COYOTE_TIME_TICKS,
+            jump_buffer_ticks: SHOULD_JUMP_TICKS,
+        }
+    }
+}
+
+impl MovementConfig {
+    /// The y velocity subtracted from the player every tick due to gravity.
+    pub fn gravity(&self) -> f32 {
+        PLAYER_GRAVITY * self.gravity_scale.clamp(0.1, 4.0)
+    }
+
+    pub fn max_fall_speed(&self) -> f32 {
+        self.max_fall_speed.clamp(1.0, 15.0)
+    }
+
+    pub fn jump_impulse(&self) -> f32 {
+        self.jump_impulse.clamp(0.5, PLAYER_MAX_Y_VEL)
+    }
+
+    pub fn horizontal_acceleration(&self) -> f32 {
+        self.horizontal_acceleration.clamp(0.05, PLAYER_MAX_H_VEL)
+    }
+
+    pub fn coyote_time_ticks(&self) -> isize {
+        self.coyote_time_ticks.min(30) as isize
+    }
+
+    pub fn jump_buffer_ticks(&self) -> isize {
+        self.jump_buffer_ticks.min(30) as isize
+    }
+}
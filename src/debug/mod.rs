@@ -4,14 +4,39 @@ use bevy::{
     prelude::*,
     window::PrimaryWindow,
 };
-use bevy_ecs_ldtk::LevelIid;
+use bevy_ecs_ldtk::{prelude::*, LevelIid};
 use bevy_inspector_egui::{
     bevy_egui::{EguiContext, EguiPlugin},
     egui,
 };
+use bevy_rapier2d::prelude::*;
 use bevy_rapier2d::render::RapierDebugRenderPlugin;
 
-use crate::config::Config;
+use crate::{
+    camera::{camera_position_from_level, CameraControlType, CameraMoveEvent},
+    config::Config,
+    level::{
+        get_ldtk_level_data,
+        lens::Lens,
+        level_box_from_level,
+        mirror::Mirror,
+        secret_wall::eject_direction,
+        shard::{collect_shard, CrystalShard, CrystalShardMods, ShardCollectedEvent},
+        snapshot::LevelSnapshot,
+        start_flag::start_flag_pos,
+        CurrentLevel,
+    },
+    light::{
+        segments::{play_light_beam, LightBeamStats},
+        BlackRayComponent, LightBeamSource,
+    },
+    player::{
+        light::PlayerLightInventory, movement::PlayerMovement, PlayerMarker,
+        PLAYER_COLLIDER_OFFSET, PLAYER_HALF_EXTENT,
+    },
+    save::SaveData,
+    shared::{GameState, GroupLabel, ResetLevel, LYRA_RESPAWN_EPSILON},
+};
 
 pub struct DebugPlugin {
     pub physics: bool,
@@ -25,9 +50,27 @@ impl Plugin for DebugPlugin {
         if self.ui {
             app.add_plugins(EguiPlugin)
                 .add_plugins(bevy_inspector_egui::DefaultInspectorConfigPlugin)
-                .add_systems(Last, debug_ui);
+                .add_systems(
+                    Last,
+                    (
+                        debug_ui,
+                        debug_light_stats,
+                        debug_light_inventory,
+                        debug_level_warp_ui,
+                    ),
+                );
         }
 
+        app.init_resource::<DebugFly>()
+            .add_event::<DebugWarpEvent>()
+            .add_systems(Update, debug_level_warp.run_if(on_event::<DebugWarpEvent>))
+            .add_systems(PostUpdate, debug_light_beams)
+            .add_systems(Update, (debug_snapshots, debug_collect_shards))
+            .add_systems(
+                Update,
+                (toggle_debug_fly, debug_fly.run_if(is_debug_flying)).chain(),
+            );
+
         if self.physics {
             app.add_plugins(RapierDebugRenderPlugin::default());
         }
@@ -88,7 +131,7 @@ impl Default for DebugPlugin {
 
 pub fn debug_ui(world: &mut World) {
     let config = world.resource::<Config>();
-    if !config.debug_config.ui {
+    if !config.debug_config.show_inspector() {
         return;
     }
 
@@ -119,3 +162,440 @@ pub fn debug_ui(world: &mut World) {
         });
     });
 }
+
+/// [`System`] that shows how many light beams are active and how much they cost, to catch runaway
+/// reflections like two mirrors facing each other.
+pub fn debug_light_stats(
+    mut q_egui: Query<&mut EguiContext, With<PrimaryWindow>>,
+    stats: Res<LightBeamStats>,
+    config: Res<Config>,
+) {
+    if !config.debug_config.show_light_stats() {
+        return;
+    }
+    let Ok(mut egui_context) = q_egui.get_single_mut() else {
+        return;
+    };
+    let max_depth = config.light_config.max_reflection_depth;
+
+    egui::Window::new("Light").show(egui_context.get_mut(), |ui| {
+        ui.label(format!("Active beams: {}", stats.beams));
+        ui.label(format!("Raycasts per tick: {}", stats.raycasts));
+        ui.label(format!(
+            "Reflection depth: {} / {max_depth}",
+            stats.max_depth
+        ));
+        if stats.depth_limit_hits > 0 {
+            ui.colored_label(
+                egui::Color32::YELLOW,
+                format!(
+                    "{} beams hit the max reflection depth",
+                    stats.depth_limit_hits
+                ),
+            );
+        }
+    });
+}
+
+/// [`System`] that shows the player's current color and which colors the level allows, marking
+/// the ones that only come from a shard collected in the level.
+pub fn debug_light_inventory(
+    mut q_egui: Query<&mut EguiContext, With<PrimaryWindow>>,
+    q_player: Query<&PlayerLightInventory, With<PlayerMarker>>,
+    q_light_sources: Query<(), With<LightBeamSource>>,
+    current_level: Res<CurrentLevel>,
+    shard_mods: Res<CrystalShardMods>,
+    save_data: Res<SaveData>,
+    config: Res<Config>,
+) {
+    if !config.debug_config.show_light_inventory() {
+        return;
+    }
+    let Ok(mut egui_context) = q_egui.get_single_mut() else {
+        return;
+    };
+    let current_color = q_player.get_single().ok().and_then(|i| i.current_color);
+
+    egui::Window::new("Light Inventory").show(egui_context.get_mut(), |ui| {
+        match current_color {
+            Some(color) => ui.label(format!("Current color: {color:?}")),
+            None => ui.colored_label(egui::Color32::GRAY, "Current color: none"),
+        };
+        ui.label(format!("Active beams: {}", q_light_sources.iter().count()));
+        ui.separator();
+        for (color, allowed) in current_level.allowed_colors.iter() {
+            let (text, label_color) = if !*allowed {
+                (format!("{color:?}"), egui::Color32::DARK_GRAY)
+            } else if shard_mods.0[color] {
+                (format!("{color:?} (shard)"), egui::Color32::YELLOW)
+            } else if save_data.permanent_colors.contains(&color) {
+                (format!("{color:?} (permanent)"), egui::Color32::LIGHT_BLUE)
+            } else {
+                (format!("{color:?} (level)"), egui::Color32::WHITE)
+            };
+            ui.colored_label(label_color, text);
+        }
+    });
+}
+
+/// [`Event`] sent by the level warp window to move the player to the start flag of the level with
+/// the given `level_iid`.
+#[derive(Event)]
+pub struct DebugWarpEvent(pub LevelIid);
+
+/// [`System`] that shows a window listing every level in the Ldtk project, with a button to warp to
+/// each. The buttons are disabled while not playing, so a warp can't interrupt a transition.
+pub fn debug_level_warp_ui(
+    mut q_egui: Query<&mut EguiContext, With<PrimaryWindow>>,
+    ldtk_projects: Query<&LdtkProjectHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    current_level: Res<CurrentLevel>,
+    game_state: Res<State<GameState>>,
+    config: Res<Config>,
+    mut ev_warp: EventWriter<DebugWarpEvent>,
+) {
+    if !config.debug_config.allow_warp() {
+        return;
+    }
+    let Ok(mut egui_context) = q_egui.get_single_mut() else {
+        return;
+    };
+    let Ok(ldtk_handle) = ldtk_projects.get_single() else {
+        return;
+    };
+    let Ok(levels) = get_ldtk_level_data(ldtk_project_assets.into_inner(), ldtk_handle) else {
+        return;
+    };
+    let can_warp = *game_state == GameState::Playing;
+
+    egui::Window::new("Level Warp").show(egui_context.get_mut(), |ui| {
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for level in levels {
+                let text = match level.get_string_field("LevelId") {
+                    Ok(level_id) => format!("{level_id} ({})", level.iid),
+                    Err(_) => level.iid.clone(),
+                };
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(can_warp, egui::Button::new("Warp"))
+                        .clicked()
+                    {
+                        ev_warp.send(DebugWarpEvent(LevelIid::new(level.iid.clone())));
+                    }
+                    match level.iid == current_level.level_iid.as_str() {
+                        true => ui.colored_label(egui::Color32::YELLOW, text),
+                        false => ui.label(text),
+                    };
+                });
+            }
+        });
+    });
+}
+
+/// [`System`] that moves the player to the start flag of the level they warp to, and selects the
+/// level so Ldtk spawns it. The switch itself is left to
+/// [`switch_level`](crate::level::switch_level), so the player and the level are reset like for
+/// any other switch. Warping to the current level resets it the same way. Warps sent while not
+/// playing are ignored, since moving the player during an animation would desync it.
+#[allow(clippy::too_many_arguments)]
+pub fn debug_level_warp(
+    mut ev_warp: EventReader<DebugWarpEvent>,
+    ldtk_projects: Query<&LdtkProjectHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    mut q_player: Query<(&mut Transform, &mut PlayerMovement), With<PlayerMarker>>,
+    current_level: Res<CurrentLevel>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut ev_move_camera: EventWriter<CameraMoveEvent>,
+    mut ev_reset_level: EventWriter<ResetLevel>,
+    game_state: Res<State<GameState>>,
+) {
+    let Some(DebugWarpEvent(level_iid)) = ev_warp.read().last() else {
+        return;
+    };
+    if *game_state != GameState::Playing {
+        return;
+    }
+    let Ok((mut transform, mut movement)) = q_player.get_single_mut() else {
+        return;
+    };
+    let Ok(ldtk_handle) = ldtk_projects.get_single() else {
+        return;
+    };
+    let Ok(levels) = get_ldtk_level_data(ldtk_project_assets.into_inner(), ldtk_handle) else {
+        return;
+    };
+    let Some((level, start_pos)) = levels
+        .iter()
+        .find(|level| level.iid == level_iid.as_str())
+        .and_then(|level| Some((level, start_flag_pos(level)?)))
+    else {
+        warn!(
+            "Couldn't find the start flag of level {} to warp to",
+            level_iid.as_str()
+        );
+        return;
+    };
+    let pos = start_pos + Vec2::Y * LYRA_RESPAWN_EPSILON;
+    transform.translation = pos.extend(transform.translation.z);
+    movement.velocity = Vec2::ZERO;
+    ev_move_camera.send(CameraMoveEvent {
+        to: camera_position_from_level(level_box_from_level(level), pos),
+        variant: CameraControlType::Instant,
+    });
+    if *level_iid == current_level.level_iid {
+        // switch_level only switches to other levels
+        ev_reset_level.send(ResetLevel::Switching);
+    } else {
+        *level_selection = LevelSelection::iid(level_iid.clone());
+    }
+}
+
+/// [`System`] that draws the raycast path of every active light beam, including where it bounces
+/// and where it stops, using the same simulation as the beams themselves.
+pub fn debug_light_beams(
+    mut q_rapier: Query<&mut RapierContext>,
+    q_light_sources: Query<&LightBeamSource>,
+    q_black_ray: Query<(Entity, &BlackRayComponent)>,
+    q_mirrors: Query<&Mirror>,
+    q_lenses: Query<&Lens>,
+    config: Res<Config>,
+    mut gizmos: Gizmos,
+) {
+    if !config.debug_config.show_light_beams() {
+        return;
+    }
+    let Ok(rapier_context) = q_rapier.get_single_mut() else {
+        return;
+    };
+    let rapier_context = rapier_context.into_inner();
+
+    for source in q_light_sources.iter() {
+        let color = source.color.light_beam_color();
+        let playback = play_light_beam(
+            rapier_context,
+            source,
+            &q_black_ray,
+            &q_mirrors,
+            &q_lenses,
+            config.light_config.max_reflection_depth,
+        );
+        let points: Vec<Vec2> = playback.iter_points(source).collect();
+        for pair in points.windows(2) {
+            gizmos.line_2d(pair[0], pair[1], color);
+        }
+        for intersection in playback.intersections.iter() {
+            gizmos.circle_2d(Isometry2d::from_translation(intersection.point), 2.0, color);
+        }
+        if let Some(end_point) = playback.end_point {
+            gizmos.cross_2d(Isometry2d::from_translation(end_point), 3.0, color);
+        }
+    }
+}
+
+/// [`Resource`] for whether the player is flying around freely, toggled with F4. While flying, the
+/// player ignores gravity and terrain, and doesn't touch hazards or shards.
+#[derive(Resource, Default)]
+pub struct DebugFly(pub bool);
+
+/// Run condition for the systems that shouldn't run while flying, like the hurt box check. Apps
+/// without the [`DebugPlugin`] never fly.
+pub fn not_debug_flying(debug_fly: Option<Res<DebugFly>>) -> bool {
+    !is_debug_flying(debug_fly)
+}
+
+fn is_debug_flying(debug_fly: Option<Res<DebugFly>>) -> bool {
+    debug_fly.is_some_and(|debug_fly| debug_fly.0)
+}
+
+/// How fast the player flies, in pixels per second. Holding shift flies three times as fast.
+const DEBUG_FLY_SPEED: f32 = 120.0;
+
+/// How many times the player is pushed out of terrain when they stop flying, before giving up
+const DEBUG_FLY_MAX_EJECTS: usize = 4;
+
+/// [`System`] that toggles flying on F4. The player's collider is disabled while flying, and the
+/// player is let go of with no velocity, so they fall from wherever they stopped flying. If they
+/// stop flying inside terrain, they are pushed out of it like out of a closing secret wall, and
+/// keep flying if that doesn't free them.
+#[allow(clippy::too_many_arguments)]
+pub fn toggle_debug_fly(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    config: Res<Config>,
+    game_state: Res<State<GameState>>,
+    mut debug_fly: ResMut<DebugFly>,
+    rapier_context: Query<&RapierContext>,
+    q_terrain: Query<(&Collider, &GlobalTransform)>,
+    mut q_player: Query<(Entity, &mut PlayerMovement, &mut Transform), With<PlayerMarker>>,
+) {
+    // debug being turned off lands the player
+    let toggle = keys.just_pressed(KeyCode::F4) && *game_state == GameState::Playing;
+    if !(toggle && config.debug_config.allow_fly()
+        || debug_fly.0 && !config.debug_config.allow_fly())
+    {
+        return;
+    }
+    let (Ok(rapier_context), Ok((player, mut movement, mut transform))) =
+        (rapier_context.get_single(), q_player.get_single_mut())
+    else {
+        return;
+    };
+    if !debug_fly.0 {
+        debug_fly.0 = true;
+        movement.velocity = Vec2::ZERO;
+        commands.entity(player).insert(ColliderDisabled);
+        return;
+    }
+    let Some(position) = eject_from_terrain(
+        rapier_context,
+        &q_terrain,
+        player,
+        transform.translation.xy(),
+    ) else {
+        // stay flying rather than land the player inside a wall
+        if toggle {
+            warn!("Can't stop flying inside terrain");
+        }
+        return;
+    };
+    transform.translation = position.extend(transform.translation.z);
+    debug_fly.0 = false;
+    movement.velocity = Vec2::ZERO;
+    commands.entity(player).remove::<ColliderDisabled>();
+}
+
+/// Returns where the player at `position` should be so their collider is out of the terrain, or
+/// `None` if pushing them out of one collider keeps landing them in another.
+fn eject_from_terrain(
+    rapier_context: &RapierContext,
+    q_terrain: &Query<(&Collider, &GlobalTransform)>,
+    player: Entity,
+    mut position: Vec2,
+) -> Option<Vec2> {
+    let filter = QueryFilter::new()
+        .exclude_sensors()
+        .exclude_collider(player)
+        .groups(CollisionGroups::new(
+            GroupLabel::PLAYER_COLLIDER,
+            GroupLabel::TERRAIN,
+        ));
+    let shape = Collider::cuboid(PLAYER_HALF_EXTENT.x, PLAYER_HALF_EXTENT.y);
+    for _ in 0..=DEBUG_FLY_MAX_EJECTS {
+        let mut overlapping = None;
+        rapier_context.intersections_with_shape(
+            position + PLAYER_COLLIDER_OFFSET,
+            0.0,
+            &shape,
+            filter,
+            |entity| {
+                overlapping = Some(entity);
+                false
+            },
+        );
+        let Some(entity) = overlapping else {
+            return Some(position);
+        };
+        // only boxes can be ejected from, like secret walls
+        let (collider, transform) = q_terrain.get(entity).ok()?;
+        let cuboid = collider.as_cuboid()?;
+        let terrain_box =
+            Rect::from_center_half_size(transform.translation().xy(), cuboid.half_extents());
+        let player_box =
+            Rect::from_center_half_size(position + PLAYER_COLLIDER_OFFSET, PLAYER_HALF_EXTENT);
+        position += eject_direction(terrain_box, player_box)?;
+    }
+    None
+}
+
+/// [`System`] that moves the flying player straight from the movement keys.
+pub fn debug_fly(
+    keys: Res<ButtonInput<KeyCode>>,
+    config: Res<Config>,
+    game_state: Res<State<GameState>>,
+    time: Res<Time>,
+    mut q_player: Query<&mut Transform, With<PlayerMarker>>,
+) {
+    if *game_state != GameState::Playing {
+        return;
+    }
+    let Ok(mut transform) = q_player.get_single_mut() else {
+        return;
+    };
+    let controls = &config.controls_config;
+    let axis = |negative: KeyCode, positive: KeyCode| {
+        keys.pressed(positive) as i32 as f32 - keys.pressed(negative) as i32 as f32
+    };
+    let direction = Vec2::new(
+        axis(controls.key_left, controls.key_right),
+        axis(controls.key_down, controls.key_up),
+    )
+    .normalize_or_zero();
+    let speed = if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        DEBUG_FLY_SPEED * 3.0
+    } else {
+        DEBUG_FLY_SPEED
+    };
+    transform.translation += (direction * speed * time.delta_secs()).extend(0.0);
+}
+
+/// [`System`] that snapshots the current level on F5 and restores the snapshot on F9.
+pub fn debug_snapshots(world: &mut World, mut snapshot: Local<Option<LevelSnapshot>>) {
+    if !world.resource::<Config>().debug_config.allow_snapshots() {
+        return;
+    }
+    let keys = world.resource::<ButtonInput<KeyCode>>();
+    let (save, load) = (
+        keys.just_pressed(KeyCode::F5),
+        keys.just_pressed(KeyCode::F9),
+    );
+
+    if save {
+        *snapshot = LevelSnapshot::capture(world);
+    }
+    if load {
+        if let Some(snapshot) = snapshot.as_ref() {
+            snapshot.restore(world);
+        }
+    }
+}
+
+/// [`System`] that collects every shard in the current level on F7, without the shard animation.
+/// Each shard sends a quiet [`ShardCollectedEvent`] and is hidden like normal, so the systems
+/// reacting to collected shards see it, and its color is reset on death and level switch the same
+/// way.
+#[allow(clippy::too_many_arguments)]
+pub fn debug_collect_shards(
+    keys: Res<ButtonInput<KeyCode>>,
+    config: Res<Config>,
+    game_state: Res<State<GameState>>,
+    mut q_shards: Query<(Entity, &mut CrystalShard, &GlobalTransform, &mut Visibility)>,
+    mut q_player: Query<&mut PlayerLightInventory, With<PlayerMarker>>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut shard_mods: ResMut<CrystalShardMods>,
+    mut ev_shard_collected: EventWriter<ShardCollectedEvent>,
+) {
+    if !config.debug_config.allow_collect_shards()
+        || !keys.just_pressed(KeyCode::F7)
+        || *game_state != GameState::Playing
+    {
+        return;
+    }
+    let Ok(mut inventory) = q_player.get_single_mut() else {
+        return;
+    };
+    for (entity, mut shard, transform, mut visibility) in q_shards.iter_mut() {
+        if shard.collected
+            || !current_level
+                .level_box
+                .contains(transform.translation().xy())
+        {
+            continue;
+        }
+        let color = shard.light_color();
+        shard_mods.record_collected(color, &current_level);
+        current_level.allowed_colors[color] = true;
+        inventory.current_color = Some(color);
+        collect_shard(entity, &mut shard, true, &mut ev_shard_collected);
+        *visibility = Visibility::Hidden;
+    }
+}
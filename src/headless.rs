@@ -0,0 +1,137 @@
+use bevy::{
+    input::InputPlugin,
+    prelude::*,
+    state::app::StatesPlugin,
+    time::{Fixed, TimeUpdateStrategy},
+};
+use bevy_ecs_ldtk::prelude::LdtkProject;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    camera::{CameraMoveEvent, CameraShakeEvent, CameraTransitionEvent, CameraZoomEvent},
+    config::Config,
+    level::{
+        checkpoint::CheckpointActivated, shard::CrystalShardPlugin, shard_counter::ShardCounter,
+        speedrun::LevelTimer, LevelSystemsPlugin,
+    },
+    light::palette::ColorPalette,
+    player::{kill::PlayerKillPlugin, spawn_in::SpawnIn},
+    save::SaveData,
+    shared::{AnimationState, GameState, GameStateChanged, ResetLevel, UiState},
+    sound::AudioSettings,
+};
+
+/// Builds the game without a window, rendering or audio, for tests that drive the game directly.
+/// The app starts out in [`GameState::Playing`] with the default [`Config`], death transitions
+/// turned off, and time advancing by exactly one fixed timestep on every [`App::update`], so each
+/// update runs [`FixedUpdate`] once and tests are deterministic.
+///
+/// Only the [`PlayerKillPlugin`], the [`CrystalShardPlugin`] and the [`LevelSystemsPlugin`] are
+/// added. Ldtk isn't, so tests spawn the entities they need, like the player and a
+/// [`StartFlag`](crate::level::start_flag::StartFlag), themselves.
+pub fn headless_app() -> App {
+    let mut config = Config::default();
+    config.camera_config.disable_transitions = true;
+
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        TransformPlugin,
+        HierarchyPlugin,
+        StatesPlugin,
+        InputPlugin,
+        AssetPlugin::default(),
+        RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(8.0).in_fixed_schedule(),
+    ))
+    .insert_resource(TimeUpdateStrategy::ManualDuration(
+        Time::<Fixed>::default().timestep(),
+    ))
+    // the asset types the plugins below load, without the plugins that render or play them
+    .init_asset::<Image>()
+    .init_asset::<TextureAtlasLayout>()
+    .init_asset::<AudioSource>()
+    .init_asset::<LdtkProject>()
+    .insert_resource(config)
+    .init_resource::<ColorPalette>()
+    .init_resource::<AudioSettings>()
+    .init_resource::<SaveData>()
+    .init_resource::<ShardCounter>()
+    .init_resource::<SpawnIn>()
+    .init_resource::<LevelTimer>()
+    .insert_state(GameState::Playing)
+    .add_sub_state::<UiState>()
+    .add_sub_state::<AnimationState>()
+    .add_event::<ResetLevel>()
+    .add_event::<GameStateChanged>()
+    .add_event::<CameraMoveEvent>()
+    .add_event::<CameraZoomEvent>()
+    .add_event::<CameraTransitionEvent>()
+    .add_event::<CameraShakeEvent>()
+    .add_event::<CheckpointActivated>()
+    .add_plugins((LevelSystemsPlugin, PlayerKillPlugin, CrystalShardPlugin));
+    app
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs_ldtk::{EntityInstance, LevelIid};
+
+    use super::*;
+    use crate::{
+        level::{start_flag::StartFlag, CurrentLevel},
+        player::{
+            kill::{KillCause, KillPlayerEvent},
+            light::PlayerLightInventory,
+            movement::PlayerMovement,
+            PlayerMarker,
+        },
+        shared::LYRA_RESPAWN_EPSILON,
+    };
+
+    #[test]
+    fn killed_player_respawns_at_start_flag() {
+        let mut app = headless_app();
+        let level_iid = LevelIid::new("level");
+        app.world_mut().resource_mut::<CurrentLevel>().level_iid = level_iid.clone();
+        app.world_mut().spawn((
+            StartFlag {
+                level_iid: level_iid.clone(),
+            },
+            EntityInstance {
+                world_x: Some(40),
+                world_y: Some(80),
+                ..default()
+            },
+        ));
+        let player = app
+            .world_mut()
+            .spawn((
+                PlayerMarker,
+                PlayerMovement::default(),
+                PlayerLightInventory::new(),
+                KinematicCharacterController::default(),
+                Sprite::default(),
+                Transform::from_xyz(300.0, -20.0, 0.0),
+            ))
+            .id();
+        app.update();
+
+        app.world_mut().send_event(KillPlayerEvent {
+            cause: KillCause::Reset,
+            ..default()
+        });
+        for _ in 0..3 {
+            app.update();
+        }
+
+        let pos = app.world().get::<Transform>(player).unwrap().translation;
+        assert_eq!(pos.xy(), Vec2::new(40.0, -80.0 + LYRA_RESPAWN_EPSILON));
+        assert_eq!(app.world().resource::<CurrentLevel>().level_iid, level_iid);
+        assert!(app
+            .world()
+            .get::<PlayerLightInventory>(player)
+            .unwrap()
+            .current_color
+            .is_none());
+    }
+}
@@ -1,7 +1,7 @@
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
 
-use crate::camera::MainCamera;
+use crate::{camera::MainCamera, config::Config, player::PlayerMarker};
 
 /// [`Component`] that holds the position of the cursor, in world coordinates. You should query
 /// for this [`Component`] if you need the cursor position to do something. Note that if your
@@ -40,3 +40,120 @@ pub fn update_cursor_world_coords(
     };
     world_coords.pos = cursor_ray.origin.truncate();
 }
+
+/// How far from the player the cursor is put when aiming with the right stick
+const GAMEPAD_AIM_DISTANCE: f32 = 48.0;
+
+/// Zeroes a stick pushed less than `deadzone`, so a worn stick resting slightly off center doesn't
+/// count as input.
+fn apply_deadzone(stick: Vec2, deadzone: f32) -> Vec2 {
+    if stick.length() < deadzone {
+        Vec2::ZERO
+    } else {
+        stick
+    }
+}
+
+/// The direction held on the left stick or d-pad of any connected [`Gamepad`]. A gamepad that is
+/// disconnected loses its [`Gamepad`] component, so this falls back to no input instead of failing.
+pub fn gamepad_movement(q_gamepads: &Query<&Gamepad>, config: &Config) -> Vec2 {
+    let deadzone = config.controls_config.gamepad_deadzone;
+    q_gamepads
+        .iter()
+        .map(|gamepad| apply_deadzone(gamepad.left_stick(), deadzone) + gamepad.dpad())
+        .sum()
+}
+
+/// Whether `button` is held on any connected [`Gamepad`].
+pub fn gamepad_pressed(q_gamepads: &Query<&Gamepad>, button: GamepadButton) -> bool {
+    q_gamepads.iter().any(|gamepad| gamepad.pressed(button))
+}
+
+/// Whether `button` was just pressed on any connected [`Gamepad`].
+pub fn gamepad_just_pressed(q_gamepads: &Query<&Gamepad>, button: GamepadButton) -> bool {
+    q_gamepads
+        .iter()
+        .any(|gamepad| gamepad.just_pressed(button))
+}
+
+/// [`Update`] [`System`] that moves the [`CursorWorldCoords`] to where the right stick of a
+/// connected [`Gamepad`] points, relative to the player. Runs after
+/// [`update_cursor_world_coords`], so the stick wins over the mouse while it's pushed.
+pub fn aim_with_right_stick(
+    mut q_coords: Query<&mut CursorWorldCoords>,
+    q_player: Query<&GlobalTransform, With<PlayerMarker>>,
+    q_gamepads: Query<&Gamepad>,
+    config: Res<Config>,
+) {
+    let deadzone = config.controls_config.gamepad_deadzone;
+    let Some(aim) = q_gamepads
+        .iter()
+        .map(|gamepad| apply_deadzone(gamepad.right_stick(), deadzone))
+        .find(|aim| *aim != Vec2::ZERO)
+    else {
+        return;
+    };
+    let Ok(player_transform) = q_player.get_single() else {
+        return;
+    };
+    let Ok(mut world_coords) = q_coords.get_single_mut() else {
+        return;
+    };
+    world_coords.pos = player_transform.translation().xy() + aim.normalize() * GAMEPAD_AIM_DISTANCE;
+}
+
+/// Run condition for the shoot button, the left mouse button or `gamepad_shoot`, being held.
+pub fn shoot_pressed(
+    mouse: Res<ButtonInput<MouseButton>>,
+    q_gamepads: Query<&Gamepad>,
+    config: Res<Config>,
+) -> bool {
+    mouse.pressed(MouseButton::Left)
+        || gamepad_pressed(&q_gamepads, config.controls_config.gamepad_shoot)
+}
+
+pub fn shoot_just_pressed(
+    mouse: Res<ButtonInput<MouseButton>>,
+    q_gamepads: Query<&Gamepad>,
+    config: Res<Config>,
+) -> bool {
+    mouse.just_pressed(MouseButton::Left)
+        || gamepad_just_pressed(&q_gamepads, config.controls_config.gamepad_shoot)
+}
+
+pub fn shoot_just_released(
+    mouse: Res<ButtonInput<MouseButton>>,
+    q_gamepads: Query<&Gamepad>,
+    config: Res<Config>,
+) -> bool {
+    let button = config.controls_config.gamepad_shoot;
+    mouse.just_released(MouseButton::Left)
+        || q_gamepads
+            .iter()
+            .any(|gamepad| gamepad.just_released(button))
+}
+
+/// Run condition for the cancel shot button, the right mouse button or `gamepad_cancel_shoot`,
+/// being just pressed.
+pub fn cancel_shoot_just_pressed(
+    mouse: Res<ButtonInput<MouseButton>>,
+    q_gamepads: Query<&Gamepad>,
+    config: Res<Config>,
+) -> bool {
+    mouse.just_pressed(MouseButton::Right)
+        || gamepad_just_pressed(&q_gamepads, config.controls_config.gamepad_cancel_shoot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sticks_respect_deadzone() {
+        assert_eq!(apply_deadzone(Vec2::new(0.1, 0.2), 0.3), Vec2::ZERO);
+        assert_eq!(
+            apply_deadzone(Vec2::new(0.0, -0.5), 0.3),
+            Vec2::new(0.0, -0.5)
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use bevy::{prelude::*, time::Real};
+
+use crate::save::SaveData;
+
+use super::{
+    level_completion::LevelCompleteEvent,
+    shard_counter::ShardCounter,
+    stats::{record_level_stats, LevelAttempt},
+};
+
+/// How long an achievement toast stays on screen
+const TOAST_SECS: f32 = 3.0;
+
+/// [`Plugin`] for achievements, milestones unlocked by the existing game events and kept in the
+/// [`SaveData`]. Each achievement only unlocks once per save.
+pub struct AchievementPlugin;
+
+impl Plugin for AchievementPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AchievementUnlockedEvent>().add_systems(
+            Update,
+            (
+                (
+                    unlock_shard_achievements.run_if(resource_changed::<ShardCounter>),
+                    unlock_level_achievements
+                        .run_if(on_event::<LevelCompleteEvent>)
+                        // the attempt is reset once its stats are recorded
+                        .before(record_level_stats),
+                ),
+                show_achievement_toasts,
+                despawn_achievement_toasts,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Achievement {
+    /// Collect any shard
+    FirstShard,
+    /// Complete a level without dying
+    DeathlessLevel,
+    /// Complete a level having collected every shard in it
+    AllShardsInLevel,
+}
+
+impl Achievement {
+    /// The id the achievement is stored as in the [`SaveData`].
+    pub fn id(&self) -> &'static str {
+        match self {
+            Achievement::FirstShard => "first_shard",
+            Achievement::DeathlessLevel => "deathless_level",
+            Achievement::AllShardsInLevel => "all_shards_in_level",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Achievement::FirstShard => "First Light",
+            Achievement::DeathlessLevel => "Untouchable",
+            Achievement::AllShardsInLevel => "Collector",
+        }
+    }
+}
+
+/// [`Event`] sent when an achievement is unlocked for the first time.
+#[derive(Event, Debug)]
+pub struct AchievementUnlockedEvent {
+    pub achievement: Achievement,
+}
+
+/// Unlocks `achievement` in the `save_data`, sending an [`AchievementUnlockedEvent`] unless it was
+/// already unlocked.
+fn unlock(
+    achievement: Achievement,
+    save_data: &mut SaveData,
+    ev_achievement_unlocked: &mut EventWriter<AchievementUnlockedEvent>,
+) {
+    // checked first so the save isn't rewritten for achievements that were already unlocked
+    if save_data.achievements.contains(achievement.id()) {
+        return;
+    }
+    save_data.achievements.insert(achievement.id().to_string());
+    ev_achievement_unlocked.send(AchievementUnlockedEvent { achievement });
+}
+
+fn unlock_shard_achievements(
+    shard_counter: Res<ShardCounter>,
+    mut save_data: ResMut<SaveData>,
+    mut ev_achievement_unlocked: EventWriter<AchievementUnlockedEvent>,
+) {
+    if shard_counter.game_collected() > 0 {
+        unlock(
+            Achievement::FirstShard,
+            &mut save_data,
+            &mut ev_achievement_unlocked,
+        );
+    }
+}
+
+fn unlock_level_achievements(
+    mut ev_level_complete: EventReader<LevelCompleteEvent>,
+    attempt: Res<LevelAttempt>,
+    shard_counter: Res<ShardCounter>,
+    mut save_data: ResMut<SaveData>,
+    mut ev_achievement_unlocked: EventWriter<AchievementUnlockedEvent>,
+) {
+    for event in ev_level_complete.read() {
+        if attempt.level_iid.as_ref() == Some(&event.level_iid) && attempt.deaths == 0 {
+            unlock(
+                Achievement::DeathlessLevel,
+                &mut save_data,
+                &mut ev_achievement_unlocked,
+            );
+        }
+        let all_shards = shard_counter.level_total(&event.level_iid) > 0
+            && shard_counter
+                .iter_level_shards(&event.level_iid)
+                .all(|iid| shard_counter.collected.contains(iid));
+        if all_shards {
+            unlock(
+                Achievement::AllShardsInLevel,
+                &mut save_data,
+                &mut ev_achievement_unlocked,
+            );
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct AchievementToast(Timer);
+
+/// [`System`] that shows a toast in the top right corner for every unlocked achievement, stacked
+/// below the toasts already on screen.
+fn show_achievement_toasts(
+    mut commands: Commands,
+    mut ev_achievement_unlocked: EventReader<AchievementUnlockedEvent>,
+    q_toasts: Query<(), With<AchievementToast>>,
+    asset_server: Res<AssetServer>,
+) {
+    let shown_before = q_toasts.iter().count();
+    for (shown, event) in (shown_before..).zip(ev_achievement_unlocked.read()) {
+        let font = TextFont {
+            font: asset_server.load("fonts/Outfit-Medium.ttf"),
+            ..default()
+        };
+        commands
+            .spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(32.),
+                    top: Val::Px(32. + 56. * shown as f32),
+                    padding: UiRect::all(Val::Px(12.)),
+                    ..default()
+                },
+                BackgroundColor(Color::BLACK.with_alpha(0.8)),
+                AchievementToast(Timer::from_seconds(TOAST_SECS, TimerMode::Once)),
+            ))
+            .with_child((
+                Text::new(format!(
+                    "Achievement unlocked: {}",
+                    event.achievement.name()
+                )),
+                font.with_font_size(20.),
+            ));
+    }
+}
+
+fn despawn_achievement_toasts(
+    mut commands: Commands,
+    mut q_toasts: Query<(Entity, &mut AchievementToast)>,
+    time: Res<Time<Real>>,
+) {
+    for (entity, mut toast) in q_toasts.iter_mut() {
+        if toast.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{config::Config, player::PlayerHurtMarker, shared::GroupLabel};
+
+use super::{CurrentLevel, LevelSystems};
+
+/// [`Plugin`] for checkpoint flags, which are placed in Ldtk like the start flag. Once the player
+/// touches one, they respawn there instead of at the start flag until they leave the level.
+pub struct CheckpointPlugin;
+
+impl Plugin for CheckpointPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<CheckpointFlagBundle>("CheckpointFlag")
+            .add_event::<CheckpointActivated>()
+            .add_systems(
+                FixedUpdate,
+                activate_checkpoints.in_set(LevelSystems::Simulation),
+            );
+    }
+}
+
+/// [`Component`] for a checkpoint flag in the level.
+#[derive(Default, Component)]
+pub struct CheckpointFlag {
+    /// Where the player respawns, at the bottom of the flag like the start flag
+    pub respawn_pos: Vec2,
+}
+
+impl From<&EntityInstance> for CheckpointFlag {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        CheckpointFlag {
+            respawn_pos: Vec2::new(
+                entity_instance
+                    .world_x
+                    .expect("Lightborne uses Free world layout") as f32,
+                -entity_instance
+                    .world_y
+                    .expect("Lightborne uses Free world layout") as f32,
+            ),
+        }
+    }
+}
+
+/// [`Event`] sent when the player touches a checkpoint that isn't the active one yet.
+#[derive(Event)]
+pub struct CheckpointActivated;
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct CheckpointFlagBundle {
+    #[from_entity_instance]
+    flag: CheckpointFlag,
+    #[with(checkpoint_collider)]
+    collider: Collider,
+    sensor: Sensor,
+    #[with(checkpoint_collision_groups)]
+    collision_groups: CollisionGroups,
+}
+
+fn checkpoint_collider(entity_instance: &EntityInstance) -> Collider {
+    Collider::cuboid(
+        (entity_instance.width / 2) as f32,
+        (entity_instance.height / 2) as f32,
+    )
+}
+
+fn checkpoint_collision_groups(_: &EntityInstance) -> CollisionGroups {
+    CollisionGroups::new(GroupLabel::ALL, GroupLabel::PLAYER_SENSOR)
+}
+
+/// [`System`] that makes the last checkpoint touched by the player's hurt box the respawn point of
+/// the [`CurrentLevel`]. Checkpoints do nothing in hardcore mode, where the player always respawns
+/// at the start of the level.
+pub fn activate_checkpoints(
+    rapier_context: Query<&RapierContext>,
+    q_player: Query<Entity, With<PlayerHurtMarker>>,
+    q_checkpoints: Query<(Entity, &CheckpointFlag)>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut ev_checkpoint_activated: EventWriter<CheckpointActivated>,
+    config: Res<Config>,
+) {
+    if config.hardcore_config.enabled {
+        return;
+    }
+    let Ok(rapier_context) = rapier_context.get_single() else {
+        return;
+    };
+    let Ok(player_entity) = q_player.get_single() else {
+        return;
+    };
+    for (checkpoint_entity, checkpoint) in q_checkpoints.iter() {
+        // checkpoints of neighbouring levels can be touched right before the level switches
+        if !current_level.level_box.contains(checkpoint.respawn_pos) {
+            continue;
+        }
+        if rapier_context.intersection_pair(player_entity, checkpoint_entity) != Some(true) {
+            continue;
+        }
+        if current_level.respawn_override != Some(checkpoint.respawn_pos) {
+            current_level.respawn_override = Some(checkpoint.respawn_pos);
+            ev_checkpoint_activated.send(CheckpointActivated);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::{
+    config::Config,
+    player::kill::KillPlayerEvent,
+    shared::{GameState, ResetLevel},
+};
+
+use super::{level_completion::LevelCompleteEvent, shard::ShardCollectedEvent, LevelSystems};
+
+/// [`Plugin`] for the optional arcade scoring, where collecting shards and completing levels
+/// builds a combo that multiplies the points earned, and dying resets the combo.
+pub struct ComboPlugin;
+
+impl Plugin for ComboPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ComboState>()
+            .add_systems(
+                Update,
+                (reset_combo_on_kill, update_combo, update_combo_ui)
+                    .chain()
+                    .run_if(|config: Res<Config>| config.combo_config.enabled),
+            )
+            .add_systems(Update, reset_scored_shards.in_set(LevelSystems::Reset));
+    }
+}
+
+/// [`Resource`] that tracks the current combo and the score earned with it.
+#[derive(Resource, Default, Debug)]
+pub struct ComboState {
+    pub combo: u32,
+    pub score: u32,
+    /// Shards already scored in the current attempt at the level, since shards are shown again on
+    /// death and would otherwise score every time they are collected
+    scored_shards: HashSet<Entity>,
+}
+
+impl ComboState {
+    /// The multiplier for the current combo, which goes up by 1 for every threshold reached.
+    pub fn multiplier(&self, thresholds: &[u32]) -> u32 {
+        1 + thresholds
+            .iter()
+            .filter(|threshold| self.combo >= **threshold)
+            .count() as u32
+    }
+
+    fn add(&mut self, points: u32, thresholds: &[u32]) {
+        self.combo += 1;
+        self.score += points * self.multiplier(thresholds);
+    }
+}
+
+/// [`System`] that lets the shards score again once the player leaves the level.
+pub fn reset_scored_shards(
+    mut ev_reset_level: EventReader<ResetLevel>,
+    mut combo: ResMut<ComboState>,
+) {
+    if ev_reset_level.read().any(|ev| *ev == ResetLevel::Switching) {
+        combo.scored_shards.clear();
+    }
+}
+
+pub fn reset_combo_on_kill(
+    mut ev_kill_player: EventReader<KillPlayerEvent>,
+    mut combo: ResMut<ComboState>,
+) {
+    if ev_kill_player.read().count() > 0 && combo.combo > 0 {
+        combo.combo = 0;
+    }
+}
+
+/// [`System`] that builds the combo when a shard is collected for the first time in the current
+/// attempt, or when a level is completed.
+pub fn update_combo(
+    mut combo: ResMut<ComboState>,
+    config: Res<Config>,
+    mut ev_shard_collected: EventReader<ShardCollectedEvent>,
+    mut ev_level_complete: EventReader<LevelCompleteEvent>,
+) {
+    let combo_config = &config.combo_config;
+    for ev in ev_shard_collected.read() {
+        if combo.scored_shards.insert(ev.shard) {
+            combo.add(
+                combo_config.shard_points,
+                &combo_config.multiplier_thresholds,
+            );
+        }
+    }
+    for _ in ev_level_complete.read() {
+        combo.add(
+            combo_config.level_points,
+            &combo_config.multiplier_thresholds,
+        );
+    }
+}
+
+#[derive(Component)]
+pub struct ComboUi;
+
+pub fn update_combo_ui(
+    mut commands: Commands,
+    combo: Res<ComboState>,
+    config: Res<Config>,
+    mut q_combo_ui: Query<(&mut Text, &mut Visibility), With<ComboUi>>,
+    game_state: Res<State<GameState>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Ok((mut text, mut visibility)) = q_combo_ui.get_single_mut() else {
+        let font = TextFont {
+            font: asset_server.load("fonts/Outfit-Medium.ttf"),
+            ..default()
+        };
+        commands
+            .spawn(Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                top: Val::Px(32.),
+                justify_content: JustifyContent::Center,
+                ..default()
+            })
+            .with_child((
+                Text::new(""),
+                TextLayout::new_with_justify(JustifyText::Center),
+                font.with_font_size(24.),
+                ComboUi,
+            ));
+        return;
+    };
+
+    visibility.set_if_neq(if *game_state == GameState::Ui {
+        Visibility::Hidden
+    } else {
+        Visibility::Visible
+    });
+    if combo.is_changed() || text.0.is_empty() {
+        let multiplier = combo.multiplier(&config.combo_config.multiplier_thresholds);
+        *text = Text::new(format!(
+            "Score: {}\nCombo: {} (x{multiplier})",
+            combo.score, combo.combo
+        ));
+    }
+}
@@ -4,11 +4,15 @@ use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 use bevy_ecs_tilemap::tiles::TileTextureIndex;
 use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::{lighting::Occluder2d, shared::GroupLabel};
+use crate::{
+    lighting::Occluder2d,
+    shared::{GroupLabel, ResetLevel},
+};
 
 use super::{
-    entity::HurtMarker,
+    entity::{is_respawn_only, resets_on_respawn, HurtMarker, ResetOnRespawn},
     merge_tile::{spawn_merged_tiles, MergedTile},
     sensor::update_light_sensors,
     CurrentLevel, LevelSystems,
@@ -58,7 +62,7 @@ impl Plugin for CrystalPlugin {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CrystalColor {
     #[default]
     Pink,
@@ -92,7 +96,7 @@ impl From<&String> for CrystalColor {
 
 /// Enum that represents the crystals that a [`LightSensor`] should toggle. Differs from the
 /// LightColor in that the white color requires an ID field.
-#[derive(Debug, Default, Clone, Copy, Eq, Hash, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub struct CrystalIdent {
     pub color: CrystalColor,
     pub id: i32,
@@ -142,6 +146,7 @@ impl MergedTile for Crystal {
                 half_extent,
             },
             HurtMarker,
+            ResetOnRespawn::default(),
         ));
     }
 
@@ -158,6 +163,9 @@ pub struct CrystalBundle {
     crystal: Crystal,
     #[from_int_grid_cell]
     cell: IntGridCell,
+    // int grid cells have no fields, so crystals always reset
+    #[default]
+    reset_on_respawn: ResetOnRespawn,
 }
 
 #[derive(Component)]
@@ -359,21 +367,65 @@ fn toggle_crystal(crystal: &mut Crystal, crystal_index: &mut TileTextureIndex) {
     }
 }
 
+impl Crystal {
+    /// Whether the crystal has been toggled away from its initial state.
+    pub fn is_toggled(&self) -> bool {
+        self.active != self.init_active
+    }
+}
+
+/// Toggles the crystals of the idents in `toggled` away from their initial state, and puts the rest
+/// back into it. Used to restore a [`LevelSnapshot`](super::snapshot::LevelSnapshot).
+pub fn restore_crystals(world: &mut World, toggled: &[CrystalIdent]) {
+    let mut q_crystals = world.query::<(&mut Crystal, &mut TileTextureIndex)>();
+    for (mut crystal, mut index) in q_crystals.iter_mut(world) {
+        if crystal.is_toggled() != toggled.contains(&crystal.ident) {
+            toggle_crystal(&mut crystal, &mut index);
+        }
+    }
+
+    let groups_to_toggle: Vec<Entity> = world
+        .query::<(Entity, &CrystalGroup)>()
+        .iter(world)
+        .filter(|(_, group)| {
+            let crystal = &group.representative;
+            crystal.is_toggled() != toggled.contains(&crystal.ident)
+        })
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in groups_to_toggle {
+        let Some(mut crystal_group) = world.entity_mut(entity).take::<CrystalGroup>() else {
+            continue;
+        };
+        toggle_crystal_group(&mut world.commands(), entity, &mut crystal_group);
+        world.entity_mut(entity).insert(crystal_group);
+    }
+    world.flush();
+}
+
 /// [`System`] that listens to [`LevelSwitchEvent`]s to ensure that [`Crystal`] states are reset
-/// when switching between rooms.
+/// when switching between rooms, and when respawning for those with [`ResetOnRespawn`].
 pub fn reset_crystals(
     mut commands: Commands,
-    mut q_crystals: Query<(&mut Crystal, &mut TileTextureIndex)>,
-    mut q_crystal_groups: Query<(Entity, &mut CrystalGroup)>,
+    mut q_crystals: Query<(&mut Crystal, &mut TileTextureIndex, Option<&ResetOnRespawn>)>,
+    mut q_crystal_groups: Query<(Entity, &mut CrystalGroup, Option<&ResetOnRespawn>)>,
+    mut ev_reset_level: EventReader<ResetLevel>,
 ) {
-    for (entity, mut crystal_group) in q_crystal_groups.iter_mut() {
+    let respawn_only = is_respawn_only(&mut ev_reset_level);
+    for (entity, mut crystal_group, reset_on_respawn) in q_crystal_groups.iter_mut() {
+        if respawn_only && !resets_on_respawn(reset_on_respawn) {
+            continue;
+        }
         let crystal = &crystal_group.representative;
         if crystal.init_active != crystal.active {
             toggle_crystal_group(&mut commands, entity, &mut crystal_group);
         }
     }
 
-    for (mut crystal, mut index) in q_crystals.iter_mut() {
+    for (mut crystal, mut index, reset_on_respawn) in q_crystals.iter_mut() {
+        if respawn_only && !resets_on_respawn(reset_on_respawn) {
+            continue;
+        }
         if crystal.init_active != crystal.active {
             toggle_crystal(&mut crystal, &mut index);
         }
@@ -0,0 +1,234 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::{
+    camera::{camera_position_from_level, CameraControlType, CameraMoveEvent},
+    player::{InputLocked, PlayerMarker},
+    save::SaveData,
+    shared::{AnimationState, GameState},
+};
+
+use super::{
+    room::{camera_box_at, CameraRoom, TriggerZone},
+    CurrentLevel, LevelSystems,
+};
+
+/// How long the camera takes to pan to each waypoint, and back to the player at the end
+const CUTSCENE_PAN_SECS: f32 = 1.0;
+
+/// [`Plugin`] for camera cutscenes placed in Ldtk. Walking into a [`CutsceneTrigger`] for the
+/// first time pans the camera through the [`CutsceneWaypoint`]s with the same `cutscene_id`, in
+/// order, while the player is frozen. Space or Enter skips the rest of the cutscene.
+pub struct CutscenePlugin;
+
+impl Plugin for CutscenePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<CutsceneWaypointBundle>("CutsceneWaypoint")
+            .register_ldtk_entity::<CutsceneTriggerBundle>("CutsceneTrigger")
+            .init_resource::<ActiveCutscene>()
+            .add_systems(FixedUpdate, start_cutscene.in_set(LevelSystems::Simulation))
+            .add_systems(
+                Update,
+                play_cutscene.run_if(in_state(AnimationState::Cutscene)),
+            );
+    }
+}
+
+/// [`Component`] for a point the camera stops at during a cutscene.
+#[derive(Default, Component)]
+pub struct CutsceneWaypoint {
+    pub cutscene_id: String,
+    /// Waypoints are visited from the lowest order to the highest
+    pub order: i32,
+    /// How long the camera stays at the waypoint, in seconds
+    pub dwell_secs: f32,
+}
+
+impl From<&EntityInstance> for CutsceneWaypoint {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let cutscene_id = entity_instance
+            .get_string_field("cutscene_id")
+            .expect("Cutscene waypoints should always have a cutscene id!")
+            .clone();
+        let order = *entity_instance
+            .get_int_field("order")
+            .expect("Cutscene waypoints should always have an order!");
+        let dwell_secs = match entity_instance.get_float_field("dwell_secs") {
+            Ok(dwell_secs) => *dwell_secs,
+            Err(_) => 1.0,
+        };
+        CutsceneWaypoint {
+            cutscene_id,
+            order,
+            dwell_secs,
+        }
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct CutsceneWaypointBundle {
+    #[from_entity_instance]
+    waypoint: CutsceneWaypoint,
+}
+
+/// [`Component`] for a [`TriggerZone`] that starts a cutscene. Each cutscene only plays once,
+/// which is saved across sessions.
+#[derive(Default, Component)]
+pub struct CutsceneTrigger {
+    pub cutscene_id: String,
+}
+
+impl From<&EntityInstance> for CutsceneTrigger {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let cutscene_id = entity_instance
+            .get_string_field("cutscene_id")
+            .expect("Cutscene triggers should always have a cutscene id!")
+            .clone();
+        CutsceneTrigger { cutscene_id }
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct CutsceneTriggerBundle {
+    #[from_entity_instance]
+    zone: TriggerZone,
+    #[from_entity_instance]
+    trigger: CutsceneTrigger,
+}
+
+struct CutsceneProgress {
+    /// The camera position and dwell time of each waypoint, in order
+    waypoints: Vec<(Vec2, f32)>,
+    next: usize,
+    timer: Timer,
+}
+
+/// [`Resource`] holding the cutscene being played, if any.
+#[derive(Resource, Default)]
+pub struct ActiveCutscene(Option<CutsceneProgress>);
+
+#[allow(clippy::too_many_arguments)]
+pub fn start_cutscene(
+    mut commands: Commands,
+    q_player: Query<(Entity, &Transform), With<PlayerMarker>>,
+    q_triggers: Query<(&TriggerZone, &CutsceneTrigger)>,
+    q_waypoints: Query<(&CutsceneWaypoint, &GlobalTransform)>,
+    current_level: Res<CurrentLevel>,
+    cur_game_state: Res<State<GameState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut next_anim_state: ResMut<NextState<AnimationState>>,
+    mut save_data: ResMut<SaveData>,
+    mut active_cutscene: ResMut<ActiveCutscene>,
+) {
+    if *cur_game_state.get() == GameState::Animating {
+        return;
+    }
+    let Ok((player_entity, player_transform)) = q_player.get_single() else {
+        return;
+    };
+    let player_pos = player_transform.translation.xy();
+    let Some((_, trigger)) = q_triggers.iter().find(|(zone, trigger)| {
+        zone.trigger_box.contains(player_pos)
+            && !save_data.seen_cutscenes.contains(&trigger.cutscene_id)
+    }) else {
+        return;
+    };
+    save_data.seen_cutscenes.insert(trigger.cutscene_id.clone());
+
+    let mut waypoints = q_waypoints
+        .iter()
+        .filter(|(waypoint, _)| waypoint.cutscene_id == trigger.cutscene_id)
+        .collect::<Vec<_>>();
+    if waypoints.is_empty() {
+        warn!("Cutscene {:?} has no waypoints", trigger.cutscene_id);
+        return;
+    }
+    waypoints.sort_by_key(|(waypoint, _)| waypoint.order);
+
+    active_cutscene.0 = Some(CutsceneProgress {
+        waypoints: waypoints
+            .into_iter()
+            .map(|(waypoint, transform)| {
+                let camera_pos = camera_position_from_level(
+                    current_level.level_box,
+                    transform.translation().xy(),
+                );
+                (camera_pos, waypoint.dwell_secs)
+            })
+            .collect(),
+        next: 0,
+        // finishes on the first tick, which starts the pan to the first waypoint
+        timer: Timer::new(Duration::ZERO, TimerMode::Once),
+    });
+    commands.entity(player_entity).insert(InputLocked);
+    next_game_state.set(GameState::Animating);
+    next_anim_state.set(AnimationState::Cutscene);
+}
+
+/// [`System`] that pans the camera to each waypoint of the [`ActiveCutscene`] in turn, then back
+/// to the player, before returning control.
+#[allow(clippy::too_many_arguments)]
+pub fn play_cutscene(
+    mut commands: Commands,
+    mut active_cutscene: ResMut<ActiveCutscene>,
+    mut ev_move_camera: EventWriter<CameraMoveEvent>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    q_player: Query<(Entity, &Transform), With<PlayerMarker>>,
+    q_rooms: Query<&CameraRoom>,
+    current_level: Res<CurrentLevel>,
+    keys: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+) {
+    let Ok((player_entity, player_transform)) = q_player.get_single() else {
+        return;
+    };
+    let player_pos = player_transform.translation.xy();
+    let player_camera_pos = camera_position_from_level(
+        camera_box_at(q_rooms.iter(), current_level.level_box, player_pos),
+        player_pos,
+    );
+    let mut end_cutscene = |active_cutscene: &mut ActiveCutscene| {
+        active_cutscene.0 = None;
+        next_game_state.set(GameState::Playing);
+        commands.entity(player_entity).remove::<InputLocked>();
+    };
+
+    let Some(progress) = active_cutscene.0.as_mut() else {
+        end_cutscene(&mut active_cutscene);
+        return;
+    };
+
+    if keys.any_just_pressed([KeyCode::Space, KeyCode::Enter]) {
+        ev_move_camera.send(CameraMoveEvent {
+            to: player_camera_pos,
+            variant: CameraControlType::Instant,
+        });
+        end_cutscene(&mut active_cutscene);
+        return;
+    }
+
+    if !progress.timer.tick(time.delta()).finished() {
+        return;
+    }
+    let (to, dwell_secs) = match progress.waypoints.get(progress.next) {
+        Some(waypoint) => *waypoint,
+        // one more pan back to the player after the last waypoint
+        None if progress.next == progress.waypoints.len() => (player_camera_pos, 0.0),
+        None => {
+            end_cutscene(&mut active_cutscene);
+            return;
+        }
+    };
+    progress.next += 1;
+    progress.timer = Timer::from_seconds(CUTSCENE_PAN_SECS + dwell_secs, TimerMode::Once);
+    ev_move_camera.send(CameraMoveEvent {
+        to,
+        variant: CameraControlType::Animated {
+            duration: Duration::from_secs_f32(CUTSCENE_PAN_SECS),
+            ease_fn: EaseFunction::SineInOut,
+            callback: None,
+        },
+    });
+}
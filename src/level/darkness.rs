@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+
+use crate::{
+    camera::AMBIENT_LIGHT_INTENSITY,
+    config::Config,
+    lighting::{AmbientLight2d, LineLight2d},
+    player::{light::PlayerLightInventory, PlayerMarker},
+    shared::AnimationState,
+};
+
+use super::CurrentLevel;
+
+/// [`Plugin`] for levels with the `Darkness` field set. The ambient light is dimmed so that only
+/// the area around the player and around light beams, which already carry their own
+/// [`LineLight2d`]s, can be seen.
+pub struct DarknessPlugin;
+
+impl Plugin for DarknessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (spawn_player_reveal_light, update_darkness).chain());
+    }
+}
+
+/// [`Component`] for the light attached to the player that reveals its surroundings in dark
+/// levels.
+#[derive(Component)]
+pub struct PlayerRevealLight;
+
+pub fn spawn_player_reveal_light(
+    mut commands: Commands,
+    q_player: Query<Entity, Added<PlayerMarker>>,
+) {
+    for player in q_player.iter() {
+        commands.entity(player).with_child((
+            LineLight2d::point(Vec4::ONE, 0.0, 0.0),
+            Transform::default(),
+            Visibility::Hidden,
+            PlayerRevealLight,
+        ));
+    }
+}
+
+/// [`System`] that dims the ambient light in dark levels and sizes the player's reveal light by
+/// the color they are holding. The darkness is lifted while the respawn fade and spawn-in play.
+pub fn update_darkness(
+    current_level: Res<CurrentLevel>,
+    config: Res<Config>,
+    anim_state: Option<Res<State<AnimationState>>>,
+    q_player: Query<&PlayerLightInventory, With<PlayerMarker>>,
+    mut q_reveal_light: Query<(&mut LineLight2d, &mut Visibility), With<PlayerRevealLight>>,
+    mut q_ambient_light: Query<&mut AmbientLight2d>,
+) {
+    let respawning = anim_state
+        .is_some_and(|state| matches!(**state, AnimationState::Respawn | AnimationState::SpawnIn));
+    let dark = current_level.darkness && !respawning;
+
+    let intensity = if dark {
+        config.darkness_config.ambient_intensity
+    } else {
+        AMBIENT_LIGHT_INTENSITY
+    };
+    for mut ambient_light in q_ambient_light.iter_mut() {
+        if ambient_light.color.w != intensity {
+            ambient_light.color.w = intensity;
+        }
+    }
+
+    let Ok((mut reveal_light, mut visibility)) = q_reveal_light.get_single_mut() else {
+        return;
+    };
+    if !dark {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    }
+    visibility.set_if_neq(Visibility::Visible);
+
+    let current_color = q_player
+        .get_single()
+        .ok()
+        .and_then(|inventory| inventory.current_color);
+    let color = match current_color {
+        Some(color) => color.lighting_color().extend(1.0),
+        None => Vec4::ONE,
+    };
+    let radius = config.darkness_config.reveal_radius(current_color);
+    if reveal_light.color != color || reveal_light.radius != radius {
+        *reveal_light = LineLight2d::point(color, radius, 0.0);
+    }
+}
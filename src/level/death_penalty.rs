@@ -0,0 +1,92 @@
+use bevy::prelude::*;
+
+use crate::{config::Config, shared::ResetLevel};
+
+use super::{
+    stats::{count_attempt_deaths, LevelAttempt},
+    LevelSystems,
+};
+
+/// How long the penalty notification stays on screen
+const PENALTY_NOTICE_SECS: f32 = 3.0;
+
+/// [`Plugin`] for the optional penalty where dying too many times in one attempt at a level resets
+/// the whole level, including collected shards and switches, as if the player had just entered it.
+pub struct DeathPenaltyPlugin;
+
+impl Plugin for DeathPenaltyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            apply_death_penalty
+                .after(count_attempt_deaths)
+                .in_set(LevelSystems::Reset)
+                .run_if(|config: Res<Config>| config.level_config.death_penalty_threshold > 0),
+        )
+        .add_systems(Update, despawn_death_penalty_ui);
+    }
+}
+
+#[derive(Component)]
+pub struct DeathPenaltyUi(Timer);
+
+/// [`System`] that sends a [`ResetLevel::Switching`] every time the deaths of the current attempt
+/// reach a multiple of the threshold. Unlike a respawn, this resets every entity in the level, not
+/// only the ones with [`ResetOnRespawn`](super::entity::ResetOnRespawn).
+pub fn apply_death_penalty(
+    mut commands: Commands,
+    mut ev_reset_level: EventReader<ResetLevel>,
+    attempt: Res<LevelAttempt>,
+    config: Res<Config>,
+    q_penalty_ui: Query<Entity, With<DeathPenaltyUi>>,
+    asset_server: Res<AssetServer>,
+) {
+    if !ev_reset_level.read().any(|ev| *ev == ResetLevel::Respawn) {
+        return;
+    }
+    let threshold = config.level_config.death_penalty_threshold;
+    if attempt.level_iid.is_none()
+        || attempt.deaths == 0
+        || !attempt.deaths.is_multiple_of(threshold)
+    {
+        return;
+    }
+    // read by the reset systems next frame
+    commands.send_event(ResetLevel::Switching);
+
+    for entity in q_penalty_ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let font = TextFont {
+        font: asset_server.load("fonts/Outfit-Medium.ttf"),
+        ..default()
+    };
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                top: Val::Px(96.),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            DeathPenaltyUi(Timer::from_seconds(PENALTY_NOTICE_SECS, TimerMode::Once)),
+        ))
+        .with_child((
+            Text::new(format!("{threshold} deaths, the level has been reset")),
+            TextLayout::new_with_justify(JustifyText::Center),
+            font.with_font_size(24.),
+        ));
+}
+
+fn despawn_death_penalty_ui(
+    mut commands: Commands,
+    mut q_penalty_ui: Query<(Entity, &mut DeathPenaltyUi)>,
+    time: Res<Time<Real>>,
+) {
+    for (entity, mut penalty_ui) in q_penalty_ui.iter_mut() {
+        if penalty_ui.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -2,7 +2,7 @@ use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
 
-use crate::{player::PlayerHurtMarker, shared::GroupLabel};
+use crate::{player::PlayerHurtMarker, shared::GroupLabel, sound::AudioSettings};
 
 use super::LevelSystems;
 
@@ -35,6 +35,7 @@ pub fn on_egg(
     q_egg: Query<Entity, (With<EggEgg>, Without<PlayerHurtMarker>)>,
     egg_sounds: Local<EggSounds>,
     mut was_intersecting: Local<bool>,
+    audio_settings: Res<AudioSettings>,
 ) {
     let Ok(player_entity) = q_player.get_single() else {
         return;
@@ -47,10 +48,10 @@ pub fn on_egg(
     };
     if let Some(true) = rapier_context.intersection_pair(egg, player_entity) {
         if !*was_intersecting {
-            commands.entity(egg).with_child((
-                AudioPlayer::new(egg_sounds.0[rand::random_range(0..3)].clone()),
-                PlaybackSettings::DESPAWN,
-            ));
+            let sound = egg_sounds.0[rand::random_range(0..3)].clone();
+            if let Some(sound) = audio_settings.sfx(sound) {
+                commands.entity(egg).with_child(sound);
+            }
         }
         *was_intersecting = true;
     } else {
@@ -1,12 +1,238 @@
+use std::collections::HashMap;
+
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
 
-use crate::shared::GroupLabel;
-/// Component for things that hurt
+use crate::{
+    config::{Config, HazardParams},
+    light::LightColor,
+    shared::{GroupLabel, ResetLevel},
+};
+/// Component for things that hurt. Hurt boxes with a [`HazardType`] behave as described by the
+/// [`HazardRegistry`], the rest kill the player instantly.
 #[derive(Default, Component)]
 pub struct HurtMarker;
 
+/// The kind of a hazard, which decides how it hurts the player. Hazard entities in Ldtk can set
+/// this with a `hazard_type` enum field, while hazard tiles in the Terrain layer get it from their
+/// int cell value.
+#[derive(Default, Component, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum HazardType {
+    #[default]
+    Spike,
+    Lava,
+    Crusher,
+    Electric,
+}
+
+impl From<&String> for HazardType {
+    fn from(value: &String) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "spike" => HazardType::Spike,
+            "lava" => HazardType::Lava,
+            "crusher" => HazardType::Crusher,
+            "electric" => HazardType::Electric,
+            _ => {
+                warn!(
+                    "Unknown hazard type {value}, treating it as {:?}",
+                    HazardType::default()
+                );
+                HazardType::default()
+            }
+        }
+    }
+}
+
+impl From<IntGridCell> for HazardType {
+    fn from(cell_instance: IntGridCell) -> Self {
+        match cell_instance.value {
+            2 => HazardType::Spike,
+            11 => HazardType::Lava,
+            12 => HazardType::Crusher,
+            13 => HazardType::Electric,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl From<&EntityInstance> for HazardType {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        match entity_instance.get_enum_field("hazard_type") {
+            Ok(hazard_type) => hazard_type.into(),
+            Err(_) => HazardType::default(),
+        }
+    }
+}
+
+/// [`Resource`] mapping each [`HazardType`] to the [`HazardParams`] set in the `hazard_config`.
+/// Hurt boxes without a [`HazardType`] have no entry, and kill instantly with the default death
+/// sound.
+#[derive(Resource)]
+pub struct HazardRegistry(pub HashMap<HazardType, HazardParams>);
+
+impl FromWorld for HazardRegistry {
+    fn from_world(world: &mut World) -> Self {
+        let hazard_config = &world.resource::<Config>().hazard_config;
+        HazardRegistry(HashMap::from([
+            (HazardType::Spike, hazard_config.spike.clone()),
+            (HazardType::Lava, hazard_config.lava.clone()),
+            (HazardType::Crusher, hazard_config.crusher.clone()),
+            (HazardType::Electric, hazard_config.electric.clone()),
+        ]))
+    }
+}
+
+impl HazardRegistry {
+    pub fn get(&self, hazard_type: Option<&HazardType>) -> Option<&HazardParams> {
+        hazard_type.and_then(|hazard_type| self.0.get(hazard_type))
+    }
+}
+
+/// [`Component`] for hurt boxes that only hurt the player while they hold more than `max_allowed`
+/// colors, so they can be passed by shooting beams to shed colors first. Set by the optional
+/// `max_allowed` int field in Ldtk, without which it hurts like any other hurt box.
+#[derive(Default, Component)]
+pub struct ColorCountHazard {
+    pub max_allowed: Option<usize>,
+}
+
+impl ColorCountHazard {
+    pub fn hurts(&self, held_colors: usize) -> bool {
+        self.max_allowed
+            .is_none_or(|max_allowed| held_colors > max_allowed)
+    }
+}
+
+impl From<&EntityInstance> for ColorCountHazard {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        ColorCountHazard {
+            max_allowed: entity_instance
+                .get_int_field("max_allowed")
+                .ok()
+                .map(|max_allowed| (*max_allowed).max(0) as usize),
+        }
+    }
+}
+
+/// [`Component`] for hurt boxes that the player survives while their current color is the
+/// `immune_color`, set by the optional `immune_color` enum field in Ldtk. Without it, the hurt box
+/// hurts no matter the color.
+#[derive(Default, Component)]
+pub struct HurtImmunity {
+    pub immune_color: Option<LightColor>,
+}
+
+impl HurtImmunity {
+    pub fn hurts(&self, current_color: Option<LightColor>) -> bool {
+        self.immune_color.is_none() || self.immune_color != current_color
+    }
+}
+
+impl From<&EntityInstance> for HurtImmunity {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        HurtImmunity {
+            immune_color: entity_instance
+                .get_enum_field("immune_color")
+                .ok()
+                .map(|color| color.into()),
+        }
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct ColorCountHazardBundle {
+    #[from_entity_instance]
+    hurt_immunity: HurtImmunity,
+    #[from_entity_instance]
+    color_count_hazard: ColorCountHazard,
+    #[from_entity_instance]
+    hazard_type: HazardType,
+    hurt_marker: HurtMarker,
+    #[with(color_count_hazard_collider)]
+    collider: Collider,
+    sensor: Sensor,
+    #[with(color_count_hazard_rigid_body)]
+    rigid_body: RigidBody,
+    #[with(color_count_hazard_collision_groups)]
+    collision_groups: CollisionGroups,
+}
+
+fn color_count_hazard_collider(entity_instance: &EntityInstance) -> Collider {
+    Collider::cuboid(
+        (entity_instance.width / 2) as f32,
+        (entity_instance.height / 2) as f32,
+    )
+}
+
+fn color_count_hazard_rigid_body(_: &EntityInstance) -> RigidBody {
+    RigidBody::Fixed
+}
+
+fn color_count_hazard_collision_groups(_: &EntityInstance) -> CollisionGroups {
+    CollisionGroups::new(GroupLabel::HURT_BOX, GroupLabel::PLAYER_SENSOR)
+}
+
+/// [`Resource`] that scales the timing of hazards without affecting the player. Values below 1 give
+/// the player more time to react. Moving platforms multiply their delta time by this instead of
+/// using [`Time`] directly, and hazards that take a number of `contact_ticks` to kill, like
+/// electricity, take proportionally longer. No hazard is animated yet, so there are no hazard
+/// animations to slow down.
+#[derive(Resource)]
+pub struct HazardSpeed(pub f32);
+
+impl HazardSpeed {
+    /// How many ticks something taking `ticks` at normal speed takes at this speed. A speed of 0
+    /// makes it take forever, but something instant stays instant.
+    pub fn scale_ticks(&self, ticks: u32) -> u32 {
+        if ticks == 0 {
+            return 0;
+        }
+        // float to int casts saturate, so dividing by 0 gives u32::MAX
+        (ticks as f32 / self.0).ceil() as u32
+    }
+}
+
+impl FromWorld for HazardSpeed {
+    fn from_world(world: &mut World) -> Self {
+        let config = world.resource::<Config>();
+        HazardSpeed(config.assist_config.hazard_speed.max(0.0))
+    }
+}
+
+/// [`Component`] for dynamic level entities, like moving platforms and crystals, holding whether
+/// they go back to their initial state when the player respawns. Ldtk entities set this with their
+/// `reset_on_respawn` bool field, which defaults to true, so a level can leave some of them as they
+/// were. Entities that don't reset on respawn are only reset when switching levels.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ResetOnRespawn(pub bool);
+
+impl Default for ResetOnRespawn {
+    fn default() -> Self {
+        ResetOnRespawn(true)
+    }
+}
+
+impl From<&EntityInstance> for ResetOnRespawn {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        match entity_instance.get_bool_field("reset_on_respawn") {
+            Ok(reset_on_respawn) => ResetOnRespawn(*reset_on_respawn),
+            Err(_) => ResetOnRespawn::default(),
+        }
+    }
+}
+
+/// Whether an entity with the given [`ResetOnRespawn`] is reset when the player respawns.
+pub fn resets_on_respawn(reset_on_respawn: Option<&ResetOnRespawn>) -> bool {
+    reset_on_respawn.is_some_and(|reset_on_respawn| reset_on_respawn.0)
+}
+
+/// Returns true if all of the [`ResetLevel`] events are [`ResetLevel::Respawn`], in which case
+/// only entities with [`ResetOnRespawn`] should be reset.
+pub fn is_respawn_only(ev_reset_level: &mut EventReader<ResetLevel>) -> bool {
+    ev_reset_level.read().all(|ev| *ev == ResetLevel::Respawn)
+}
+
 /// Component for spikes
 #[derive(Default, Component)]
 pub struct Spike;
@@ -17,9 +243,21 @@ pub struct SpikeBundle {
     #[from_int_grid_cell]
     fixed_entity_bundle: FixedEntityBundle,
     hurt_marker: HurtMarker,
+    #[from_int_grid_cell]
+    hazard_type: HazardType,
     spike: Spike,
 }
 
+/// Bundle for the hazard tiles other than spikes, like lava, which fill their whole tile
+#[derive(Default, Bundle, LdtkIntCell)]
+pub struct HazardTileBundle {
+    #[from_int_grid_cell]
+    fixed_entity_bundle: FixedEntityBundle,
+    hurt_marker: HurtMarker,
+    #[from_int_grid_cell]
+    hazard_type: HazardType,
+}
+
 /// [`Bundle`] used to group together components commonly used together when initializing physics
 /// for fixed [`LdtkEntity`]s.
 #[derive(Default, Bundle)]
@@ -32,7 +270,7 @@ pub struct FixedEntityBundle {
 impl From<&EntityInstance> for FixedEntityBundle {
     fn from(entity_instance: &EntityInstance) -> Self {
         match entity_instance.identifier.as_ref() {
-            "Sensor" => FixedEntityBundle {
+            "Sensor" | "LightSwitch" => FixedEntityBundle {
                 collider: Collider::cuboid(4., 4.),
                 rigid_body: RigidBody::Fixed,
                 collision_groups: CollisionGroups::new(
@@ -68,6 +306,14 @@ impl From<IntGridCell> for FixedEntityBundle {
                     GroupLabel::ALL & !GroupLabel::PLAYER_COLLIDER,
                 ),
             },
+            11..=13 => FixedEntityBundle {
+                collider: Collider::cuboid(4., 4.),
+                rigid_body: RigidBody::Fixed,
+                collision_groups: CollisionGroups::new(
+                    GroupLabel::TERRAIN,
+                    GroupLabel::ALL & !GroupLabel::PLAYER_COLLIDER,
+                ),
+            },
             15 => FixedEntityBundle {
                 collider: Collider::cuboid(4., 1.),
                 rigid_body: RigidBody::Fixed,
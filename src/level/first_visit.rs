@@ -0,0 +1,48 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::{save::SaveData, shared::ResetLevel};
+
+use super::{CurrentLevel, LevelSystems};
+
+/// [`Plugin`] that tracks which levels the player has ever entered, so levels can run intro events
+/// only the first time.
+pub struct FirstVisitPlugin;
+
+impl Plugin for FirstVisitPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FirstVisitEvent>()
+            .add_systems(Update, send_first_visit.in_set(LevelSystems::Reset));
+    }
+}
+
+/// [`Event`] sent once when the player enters a level they have never entered before, saved
+/// across sessions. Subscribe to this for one-time intros like camera pans or hints.
+#[derive(Event)]
+pub struct FirstVisitEvent {
+    pub level_iid: LevelIid,
+}
+
+pub fn send_first_visit(
+    mut ev_reset_level: EventReader<ResetLevel>,
+    mut ev_first_visit: EventWriter<FirstVisitEvent>,
+    current_level: Res<CurrentLevel>,
+    mut save_data: ResMut<SaveData>,
+) {
+    if !ev_reset_level.read().any(|x| *x == ResetLevel::Switching) {
+        return;
+    }
+    // avoid triggering change detection, which would write the save file on every switch
+    if save_data
+        .visited_levels
+        .contains(current_level.level_iid.as_str())
+    {
+        return;
+    }
+    save_data
+        .visited_levels
+        .insert(current_level.level_iid.to_string());
+    ev_first_visit.send(FirstVisitEvent {
+        level_iid: current_level.level_iid.clone(),
+    });
+}
@@ -0,0 +1,140 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    player::{
+        movement::{move_player, PlayerMovement},
+        PlayerHurtMarker, PlayerMarker,
+    },
+    shared::GroupLabel,
+};
+
+use super::LevelSystems;
+
+/// [`Plugin`] for force zones, rectangles placed in Ldtk that push the player while they're
+/// inside, like wind or a current.
+pub struct ForceZonePlugin;
+
+impl Plugin for ForceZonePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<ForceZoneBundle>("ForceZone")
+            .add_systems(
+                FixedUpdate,
+                push_player_in_force_zones
+                    .before(move_player)
+                    .in_set(LevelSystems::Simulation),
+            );
+    }
+}
+
+/// [`Component`] for a zone that pushes the player towards its `direction` while they overlap it.
+/// The direction comes from the zone's `direction_x` and `direction_y` float fields, and how hard
+/// it pushes, in pixels per [`FixedUpdate`] tick per tick, from its `magnitude` float field.
+#[derive(Default, Component, Debug)]
+pub struct ForceZone {
+    pub direction: Vec2,
+    pub magnitude: f32,
+}
+
+impl ForceZone {
+    /// The velocity the zone adds to the player every tick.
+    pub fn force(&self) -> Vec2 {
+        self.direction.normalize_or_zero() * self.magnitude
+    }
+}
+
+impl From<&EntityInstance> for ForceZone {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let float_field = |name: &str| {
+            *entity_instance
+                .get_float_field(name)
+                .unwrap_or_else(|_| panic!("All force zones should have a {name} float field"))
+        };
+        ForceZone {
+            // Ldtk's y axis points down
+            direction: Vec2::new(float_field("direction_x"), -float_field("direction_y")),
+            magnitude: float_field("magnitude"),
+        }
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct ForceZoneBundle {
+    #[from_entity_instance]
+    zone: ForceZone,
+    #[with(force_zone_collider)]
+    collider: Collider,
+    sensor: Sensor,
+    #[with(force_zone_collision_groups)]
+    collision_groups: CollisionGroups,
+}
+
+fn force_zone_collider(entity_instance: &EntityInstance) -> Collider {
+    Collider::cuboid(
+        (entity_instance.width / 2) as f32,
+        (entity_instance.height / 2) as f32,
+    )
+}
+
+fn force_zone_collision_groups(_: &EntityInstance) -> CollisionGroups {
+    CollisionGroups::new(GroupLabel::ALL, GroupLabel::PLAYER_SENSOR)
+}
+
+/// The force on the player from every zone they're inside, which add up where zones overlap.
+fn total_force<'a>(zones: impl IntoIterator<Item = &'a ForceZone>) -> Vec2 {
+    zones.into_iter().map(ForceZone::force).sum()
+}
+
+/// [`System`] that sets the force on the player to the sum of the [`ForceZone`]s their hurt box is
+/// inside, so it stops the tick they leave the last one.
+pub fn push_player_in_force_zones(
+    rapier_context: Query<&RapierContext>,
+    q_player_hurt: Query<Entity, With<PlayerHurtMarker>>,
+    mut q_player: Query<&mut PlayerMovement, With<PlayerMarker>>,
+    q_zones: Query<(Entity, &ForceZone)>,
+) {
+    let Ok(mut movement) = q_player.get_single_mut() else {
+        return;
+    };
+    let (Ok(rapier_context), Ok(player_hurt)) =
+        (rapier_context.get_single(), q_player_hurt.get_single())
+    else {
+        movement.zone_force = Vec2::ZERO;
+        return;
+    };
+    movement.zone_force = total_force(
+        q_zones
+            .iter()
+            .filter(|(zone_entity, _)| {
+                rapier_context.intersection_pair(player_hurt, *zone_entity) == Some(true)
+            })
+            .map(|(_, zone)| zone),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping_zones_add_up() {
+        let zones = [
+            ForceZone {
+                direction: Vec2::new(3.0, 0.0),
+                magnitude: 0.5,
+            },
+            ForceZone {
+                direction: Vec2::new(0.0, 2.0),
+                magnitude: 0.25,
+            },
+            // a zone without a direction doesn't push at all
+            ForceZone {
+                direction: Vec2::ZERO,
+                magnitude: 10.0,
+            },
+        ];
+        assert_eq!(total_force(&zones), Vec2::new(0.5, 0.25));
+        assert_eq!(total_force([]), Vec2::ZERO);
+    }
+}
@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{light::LightColor, shared::GroupLabel};
+
+use super::LevelSystems;
+
+/// [`Plugin`] for lenses, which recolor the light beams passing through them. See
+/// [`play_light_beam`](crate::light::segments::play_light_beam) for how beams are split at a lens.
+pub struct LensPlugin;
+
+impl Plugin for LensPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<LensBundle>("Lens")
+            .add_systems(PreUpdate, add_lens_sprites.in_set(LevelSystems::Processing));
+    }
+}
+
+/// [`Component`] for a rectangle placed in Ldtk that light beams pass through, coming out the other
+/// side as a beam of its `color`, from the `light_color` enum field. The player walks right
+/// through it.
+#[derive(Default, Component, Debug)]
+pub struct Lens {
+    pub color: LightColor,
+    pub half_extent: Vec2,
+}
+
+impl From<&EntityInstance> for Lens {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let color = entity_instance
+            .get_enum_field("light_color")
+            .expect("All lenses should have a light_color enum field")
+            .into();
+        Lens {
+            color,
+            half_extent: Vec2::new(entity_instance.width as f32, entity_instance.height as f32)
+                / 2.0,
+        }
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct LensBundle {
+    #[from_entity_instance]
+    lens: Lens,
+    #[with(lens_collider)]
+    collider: Collider,
+    sensor: Sensor,
+    #[with(lens_collision_groups)]
+    collision_groups: CollisionGroups,
+}
+
+fn lens_collider(entity_instance: &EntityInstance) -> Collider {
+    let half_extent = Lens::from(entity_instance).half_extent;
+    Collider::cuboid(half_extent.x, half_extent.y)
+}
+
+fn lens_collision_groups(_: &EntityInstance) -> CollisionGroups {
+    CollisionGroups::new(
+        GroupLabel::LIGHT_SENSOR,
+        GroupLabel::LIGHT_RAY
+            | GroupLabel::WHITE_RAY
+            | GroupLabel::BLUE_RAY
+            | GroupLabel::BLACK_RAY,
+    )
+}
+
+pub fn add_lens_sprites(mut commands: Commands, q_lenses: Query<(Entity, &Lens), Added<Lens>>) {
+    for (entity, lens) in q_lenses.iter() {
+        commands.entity(entity).insert(Sprite::from_color(
+            lens.color.light_beam_color().with_alpha(0.4),
+            lens.half_extent * 2.0,
+        ));
+    }
+}
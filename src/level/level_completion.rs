@@ -1,10 +1,24 @@
-use bevy::prelude::*;
+use std::time::Duration;
+
+use bevy::{ecs::system::SystemId, prelude::*};
 use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
 
-use crate::{player::PlayerHurtMarker, shared::GroupLabel, ui::level_select::Levels};
+use crate::{
+    camera::{
+        camera_position_from_level, CameraControlType, CameraMoveEvent, CameraTransition,
+        CameraTransitionEvent,
+    },
+    config::Config,
+    player::{PlayerHurtMarker, PlayerMarker},
+    shared::{AnimationState, GameState, GroupLabel, LYRA_RESPAWN_EPSILON},
+    ui::level_select::Levels,
+};
 
-use super::CurrentLevel;
+use super::{
+    get_ldtk_level_data, level_box_from_level, start_flag::start_flag_pos, CurrentLevel,
+    LevelSystems,
+};
 
 pub struct LevelCompletionPlugin;
 
@@ -12,8 +26,21 @@ impl Plugin for LevelCompletionPlugin {
     fn build(&self, app: &mut App) {
         app.register_ldtk_entity::<CompletionMarkerBundle>("StartMarker")
             .register_ldtk_entity::<CompletionMarkerBundle>("EndMarker")
+            .register_ldtk_entity::<GoalFlagBundle>("Goal")
             .insert_resource(InProgressLevel(LevelIid::default()))
-            .add_systems(Update, handle_start_end_markers);
+            .init_resource::<GoalCallbacks>()
+            .init_resource::<GoalReached>()
+            .add_event::<LevelStartEvent>()
+            .add_event::<LevelCompleteEvent>()
+            .add_event::<GameCompleteEvent>()
+            .add_systems(Update, handle_start_end_markers)
+            .add_systems(Update, reset_goal_reached.in_set(LevelSystems::Reset))
+            .add_systems(
+                Update,
+                handle_goal_flags
+                    .run_if(in_state(GameState::Playing))
+                    .after(LevelSystems::Reset),
+            );
     }
 }
 
@@ -34,6 +61,78 @@ struct CompletionMarkerBundle {
 #[derive(Resource)]
 struct InProgressLevel(LevelIid);
 
+/// [`Event`] sent when the player passes the `StartMarker` of a level.
+#[derive(Event)]
+pub struct LevelStartEvent {
+    pub level_iid: LevelIid,
+}
+
+/// [`Event`] sent when the player reaches the `EndMarker` of a level they started.
+#[derive(Event)]
+pub struct LevelCompleteEvent {
+    pub level_iid: LevelIid,
+}
+
+/// [`Event`] sent when the player reaches the [`GoalFlag`] of the last level.
+#[derive(Event)]
+pub struct GameCompleteEvent {
+    pub level_iid: LevelIid,
+}
+
+/// [`Component`] for the goal of a level. Touching it completes the level and takes the player to
+/// the start flag of the next one, behind the same slide to black as the death transition.
+#[derive(Default, Component)]
+pub struct GoalFlag;
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct GoalFlagBundle {
+    goal_flag: GoalFlag,
+    #[with(goal_flag_collider)]
+    collider: Collider,
+    sensor: Sensor,
+    #[with(goal_flag_collision_groups)]
+    collision_groups: CollisionGroups,
+}
+
+fn goal_flag_collider(entity_instance: &EntityInstance) -> Collider {
+    Collider::cuboid(
+        (entity_instance.width / 2) as f32,
+        (entity_instance.height / 2) as f32,
+    )
+}
+
+fn goal_flag_collision_groups(_: &EntityInstance) -> CollisionGroups {
+    CollisionGroups::new(GroupLabel::ALL, GroupLabel::PLAYER_SENSOR)
+}
+
+/// How long each half of the transition to the next level takes
+const GOAL_TRANSITION_DURATION: Duration = Duration::from_millis(400);
+
+/// [`Resource`] holding the level the player last reached the goal of, so the goal is only reached
+/// once per visit, and the level to go to once the screen is black.
+#[derive(Resource, Default)]
+struct GoalReached {
+    level_iid: Option<LevelIid>,
+    next_level_iid: Option<LevelIid>,
+}
+
+#[derive(Resource)]
+struct GoalCallbacks {
+    // once the screen is completely black
+    cb1: SystemId,
+    // once the screen is ready for play
+    cb2: SystemId,
+}
+
+impl FromWorld for GoalCallbacks {
+    fn from_world(world: &mut World) -> Self {
+        GoalCallbacks {
+            cb1: world.register_system(after_goal_slide_to_black),
+            cb2: world.register_system(after_goal_slide_from_black),
+        }
+    }
+}
+
 impl LdtkEntity for CompletionMarkerBundle {
     fn bundle_entity(
         entity_instance: &EntityInstance,
@@ -63,6 +162,7 @@ impl LdtkEntity for CompletionMarkerBundle {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_start_end_markers(
     rapier_context: Query<&RapierContext>,
     q_player: Query<Entity, With<PlayerHurtMarker>>,
@@ -70,6 +170,8 @@ fn handle_start_end_markers(
     mut res_levels: ResMut<Levels>,
     res_current_level: Res<CurrentLevel>,
     mut res_in_progress_level: ResMut<InProgressLevel>,
+    mut ev_level_start: EventWriter<LevelStartEvent>,
+    mut ev_level_complete: EventWriter<LevelCompleteEvent>,
 ) {
     let (Ok(rapier_context), Ok(player_entity), completion_markers) = (
         rapier_context.get_single(),
@@ -84,25 +186,154 @@ fn handle_start_end_markers(
         };
         match marker_type {
             CompletionMarkerType::StartMarker => {
-                res_in_progress_level.0 = res_current_level.level_iid.clone();
+                if res_in_progress_level.0 != res_current_level.level_iid {
+                    res_in_progress_level.0 = res_current_level.level_iid.clone();
+                    ev_level_start.send(LevelStartEvent {
+                        level_iid: res_current_level.level_iid.clone(),
+                    });
+                }
             }
             CompletionMarkerType::EndMarker => {
                 let current = &res_current_level.level_iid;
                 if res_in_progress_level.0 != *current {
                     return;
                 }
-                let mut unlock_next = false;
-                for level in res_levels.0.iter_mut() {
-                    if unlock_next {
-                        level.locked = false;
-                        break;
-                    }
-                    if level.level_iid == *current {
-                        level.complete = true;
-                        unlock_next = true;
-                    }
-                }
+                // so the level is only completed once per attempt
+                res_in_progress_level.0 = LevelIid::default();
+                ev_level_complete.send(LevelCompleteEvent {
+                    level_iid: current.clone(),
+                });
+                res_levels.complete(current);
             }
         }
     }
 }
+
+fn reset_goal_reached(mut goal_reached: ResMut<GoalReached>) {
+    goal_reached.level_iid = None;
+}
+
+/// [`System`] that completes the level when the player's hurt box touches a [`GoalFlag`], and
+/// starts the transition to the next level, or sends a [`GameCompleteEvent`] if there is none.
+#[allow(clippy::too_many_arguments)]
+fn handle_goal_flags(
+    mut commands: Commands,
+    rapier_context: Query<&RapierContext>,
+    q_player: Query<Entity, With<PlayerHurtMarker>>,
+    q_goal_flags: Query<Entity, (With<GoalFlag>, Without<PlayerHurtMarker>)>,
+    (mut res_levels, current_level): (ResMut<Levels>, Res<CurrentLevel>),
+    mut goal_reached: ResMut<GoalReached>,
+    (mut ev_level_complete, mut ev_game_complete): (
+        EventWriter<LevelCompleteEvent>,
+        EventWriter<GameCompleteEvent>,
+    ),
+    mut ev_transition_camera: EventWriter<CameraTransitionEvent>,
+    (mut next_game_state, mut next_anim_state): (
+        ResMut<NextState<GameState>>,
+        ResMut<NextState<AnimationState>>,
+    ),
+    callbacks: Res<GoalCallbacks>,
+    config: Res<Config>,
+) {
+    let (Ok(rapier_context), Ok(player_entity)) =
+        (rapier_context.get_single(), q_player.get_single())
+    else {
+        return;
+    };
+    let current = &current_level.level_iid;
+    if goal_reached.level_iid.as_ref() == Some(current)
+        || !q_goal_flags.iter().any(|goal_flag| {
+            rapier_context.intersection_pair(goal_flag, player_entity) == Some(true)
+        })
+    {
+        return;
+    }
+    goal_reached.level_iid = Some(current.clone());
+
+    let Some(next_level_iid) = res_levels.complete(current) else {
+        ev_game_complete.send(GameCompleteEvent {
+            level_iid: current.clone(),
+        });
+        return;
+    };
+    ev_level_complete.send(LevelCompleteEvent {
+        level_iid: current.clone(),
+    });
+    goal_reached.next_level_iid = Some(next_level_iid);
+
+    if config.camera_config.disable_transitions {
+        commands.run_system(callbacks.cb1);
+        return;
+    }
+    ev_transition_camera.send(CameraTransitionEvent {
+        duration: GOAL_TRANSITION_DURATION,
+        ease_fn: EaseFunction::SineInOut,
+        callback: Some(callbacks.cb1),
+        effect: CameraTransition::SlideToBlack,
+        color: Color::BLACK,
+    });
+    next_game_state.set(GameState::Animating);
+    next_anim_state.set(AnimationState::Switch);
+}
+
+/// Moves the player to the start flag of the next level while the screen is covered. The
+/// [`CurrentLevel`] is cleared so [`switch_level`](super::switch_level) switches to it without
+/// its own camera animation, and sends
+/// [`ResetLevel::Switching`](crate::shared::ResetLevel::Switching) like for any other switch, so
+/// the player keeps their color if the next level allows it.
+#[allow(clippy::too_many_arguments)]
+fn after_goal_slide_to_black(
+    mut goal_reached: ResMut<GoalReached>,
+    ldtk_projects: Query<&LdtkProjectHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    mut q_player: Query<&mut Transform, With<PlayerMarker>>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut ev_move_camera: EventWriter<CameraMoveEvent>,
+    mut ev_transition_camera: EventWriter<CameraTransitionEvent>,
+    callbacks: Res<GoalCallbacks>,
+    config: Res<Config>,
+) {
+    let next_level = goal_reached
+        .next_level_iid
+        .take()
+        .and_then(|next_level_iid| {
+            let ldtk_handle = ldtk_projects.get_single().ok()?;
+            get_ldtk_level_data(ldtk_project_assets.into_inner(), ldtk_handle)
+                .ok()?
+                .iter()
+                .find(|level| level.iid == next_level_iid.as_str())
+        });
+    match (next_level, q_player.get_single_mut()) {
+        (Some(level), Ok(mut player_transform)) => match start_flag_pos(level) {
+            Some(start_pos) => {
+                player_transform.translation.x = start_pos.x;
+                player_transform.translation.y = start_pos.y + LYRA_RESPAWN_EPSILON;
+                current_level.level_iid = LevelIid::new("");
+                ev_move_camera.send(CameraMoveEvent {
+                    to: camera_position_from_level(
+                        level_box_from_level(level),
+                        player_transform.translation.xy(),
+                    ),
+                    variant: CameraControlType::Instant,
+                });
+            }
+            None => warn!("Level {} has no start flag to go to", level.iid),
+        },
+        _ => warn!("Couldn't find the next level to go to"),
+    }
+
+    if config.camera_config.disable_transitions {
+        return;
+    }
+    ev_transition_camera.send(CameraTransitionEvent {
+        duration: GOAL_TRANSITION_DURATION,
+        ease_fn: EaseFunction::SineInOut,
+        callback: Some(callbacks.cb2),
+        effect: CameraTransition::SlideFromBlack,
+        color: Color::BLACK,
+    });
+}
+
+fn after_goal_slide_from_black(mut next_game_state: ResMut<NextState<GameState>>) {
+    next_game_state.set(GameState::Playing);
+}
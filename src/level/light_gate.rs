@@ -0,0 +1,312 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+use enum_map::EnumMap;
+
+use crate::{
+    animation::AnimationConfig,
+    light::{segments::simulate_light_sources, LightColor},
+    player::{PlayerMarker, PLAYER_COLLIDER_OFFSET, PLAYER_HALF_EXTENT},
+    shared::{GroupLabel, ResetLevel},
+};
+
+use super::{
+    entity::{is_respawn_only, resets_on_respawn, ResetOnRespawn},
+    secret_wall::eject_direction,
+    LevelSystems,
+};
+
+/// Size of one frame of `light_gate_sheet.png`, which is stretched to the size of the gate
+const LIGHT_GATE_FRAME_SIZE: UVec2 = UVec2::new(16, 16);
+/// The sheet holds the closed frame, the opening frames ending on the open frame, then the same
+/// frames backwards for closing
+const LIGHT_GATE_FRAMES: u32 = 7;
+const CLOSED_FRAME: usize = 0;
+const OPEN_FRAME: usize = 3;
+const LIGHT_GATE_FPS: u8 = 16;
+
+/// [`Plugin`] for light gates, rectangles placed in Ldtk that open while a beam of their
+/// [`LightColor`] hits them.
+pub struct LightGatePlugin;
+
+impl Plugin for LightGatePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<LightGateBundle>("LightGate")
+            .add_systems(
+                PreUpdate,
+                add_light_gate_sprites.in_set(LevelSystems::Processing),
+            )
+            .add_systems(Update, reset_light_gates.in_set(LevelSystems::Reset))
+            .add_systems(
+                FixedUpdate,
+                update_light_gates
+                    .after(simulate_light_sources)
+                    .in_set(LevelSystems::Simulation),
+            );
+    }
+}
+
+/// How a [`LightGate`] wired to more than one [`LightSwitch`](super::light_switch::LightSwitch)
+/// combines them, set with the gate's `switch_logic` enum field.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SwitchLogic {
+    /// Any latched switch opens the gate
+    #[default]
+    Or,
+    /// Every switch has to be latched to open the gate
+    And,
+}
+
+impl From<&String> for SwitchLogic {
+    fn from(value: &String) -> Self {
+        match value.as_str() {
+            "Or" => SwitchLogic::Or,
+            "And" => SwitchLogic::And,
+            _ => {
+                warn!(
+                    "Unknown switch logic {value}, using {:?}",
+                    SwitchLogic::default()
+                );
+                SwitchLogic::default()
+            }
+        }
+    }
+}
+
+impl SwitchLogic {
+    /// Whether the switches wired to a gate, given by whether each is `latched`, open it. Gates
+    /// without any switches are never opened by them.
+    pub fn opens(&self, latched: impl IntoIterator<Item = bool>) -> bool {
+        let latched: Vec<bool> = latched.into_iter().collect();
+        !latched.is_empty()
+            && match self {
+                SwitchLogic::Or => latched.iter().any(|latched| *latched),
+                SwitchLogic::And => latched.iter().all(|latched| *latched),
+            }
+    }
+}
+
+/// [`Component`] for a gate that blocks the player, and opens while a light beam of its `color`
+/// hits it, or once the switches wired to it are latched. Beams of every color still stop at the
+/// gate while it's open, so the beam holding it open keeps hitting it.
+#[derive(Default, Component, Debug)]
+pub struct LightGate {
+    pub color: LightColor,
+    pub half_extent: Vec2,
+    /// Colors of light beams hitting the gate, set by
+    /// [`simulate_light_sources`]
+    pub hit_by: EnumMap<LightColor, bool>,
+    pub switch_logic: SwitchLogic,
+    /// Whether the switches wired to the gate hold it open
+    pub switched: bool,
+    pub open: bool,
+}
+
+impl From<&EntityInstance> for LightGate {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let color = entity_instance
+            .get_enum_field("light_color")
+            .expect("All light gates should have a light_color enum field")
+            .into();
+        LightGate {
+            color,
+            half_extent: Vec2::new(entity_instance.width as f32, entity_instance.height as f32)
+                / 2.0,
+            hit_by: EnumMap::default(),
+            switch_logic: entity_instance
+                .get_enum_field("switch_logic")
+                .ok()
+                .map(|logic| logic.into())
+                .unwrap_or_default(),
+            switched: false,
+            open: false,
+        }
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct LightGateBundle {
+    #[from_entity_instance]
+    gate: LightGate,
+    #[from_entity_instance]
+    reset_on_respawn: ResetOnRespawn,
+}
+
+fn closed_collision_groups() -> CollisionGroups {
+    CollisionGroups::new(
+        GroupLabel::TERRAIN | GroupLabel::LIGHT_SENSOR,
+        GroupLabel::ALL,
+    )
+}
+
+/// Open gates stop colliding with the player, but not with light beams.
+fn open_collision_groups() -> CollisionGroups {
+    CollisionGroups::new(
+        GroupLabel::LIGHT_SENSOR,
+        GroupLabel::LIGHT_RAY
+            | GroupLabel::WHITE_RAY
+            | GroupLabel::BLUE_RAY
+            | GroupLabel::BLACK_RAY,
+    )
+}
+
+fn still_animation(frame: usize) -> AnimationConfig {
+    AnimationConfig::new(frame, frame, LIGHT_GATE_FPS, false)
+}
+
+pub fn add_light_gate_sprites(
+    mut commands: Commands,
+    q_gates: Query<(Entity, &LightGate), Added<LightGate>>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    if q_gates.is_empty() {
+        return;
+    }
+    let texture_atlas_layout = texture_atlas_layouts.add(TextureAtlasLayout::from_grid(
+        LIGHT_GATE_FRAME_SIZE,
+        LIGHT_GATE_FRAMES,
+        1,
+        None,
+        None,
+    ));
+
+    for (entity, gate) in q_gates.iter() {
+        commands.entity(entity).insert((
+            RigidBody::Fixed,
+            Collider::cuboid(gate.half_extent.x, gate.half_extent.y),
+            closed_collision_groups(),
+            Sprite {
+                image: asset_server.load("light_gate_sheet.png"),
+                texture_atlas: Some(TextureAtlas {
+                    layout: texture_atlas_layout.clone(),
+                    index: CLOSED_FRAME,
+                }),
+                color: gate.color.light_beam_color(),
+                custom_size: Some(gate.half_extent * 2.0),
+                ..default()
+            },
+            still_animation(CLOSED_FRAME),
+        ));
+    }
+}
+
+/// [`System`] that opens the light gates hit by a beam of their color or held open by their
+/// switches, and closes the rest. If a
+/// gate closes on top of the player, they are pushed out of it through its nearest side.
+pub fn update_light_gates(
+    mut q_player: Query<&mut Transform, With<PlayerMarker>>,
+    mut q_gates: Query<(
+        &GlobalTransform,
+        &mut LightGate,
+        &mut CollisionGroups,
+        &mut AnimationConfig,
+    )>,
+) {
+    for (transform, mut gate, mut collision_groups, mut animation) in q_gates.iter_mut() {
+        let open = gate.hit_by[gate.color] || gate.switched;
+        if gate.open == open {
+            continue;
+        }
+        gate.open = open;
+        if open {
+            *collision_groups = open_collision_groups();
+            *animation = AnimationConfig::new(CLOSED_FRAME + 1, OPEN_FRAME, LIGHT_GATE_FPS, false);
+            continue;
+        }
+        *collision_groups = closed_collision_groups();
+        *animation = AnimationConfig::new(
+            OPEN_FRAME + 1,
+            LIGHT_GATE_FRAMES as usize - 1,
+            LIGHT_GATE_FPS,
+            false,
+        );
+
+        let Ok(mut player_transform) = q_player.get_single_mut() else {
+            continue;
+        };
+        let gate_box = Rect::from_center_half_size(transform.translation().xy(), gate.half_extent);
+        let player_box = Rect::from_center_half_size(
+            player_transform.translation.xy() + PLAYER_COLLIDER_OFFSET,
+            PLAYER_HALF_EXTENT,
+        );
+        if let Some(push) = eject_direction(gate_box, player_box) {
+            player_transform.translation += push.extend(0.0);
+        }
+    }
+}
+
+/// [`System`] that closes the light gates when the level is reset.
+pub fn reset_light_gates(
+    mut q_gates: Query<(
+        &mut LightGate,
+        &mut CollisionGroups,
+        &mut AnimationConfig,
+        Option<&ResetOnRespawn>,
+    )>,
+    mut ev_reset_level: EventReader<ResetLevel>,
+) {
+    if ev_reset_level.is_empty() {
+        return;
+    }
+    let respawn_only = is_respawn_only(&mut ev_reset_level);
+    for (mut gate, mut collision_groups, mut animation, reset_on_respawn) in q_gates.iter_mut() {
+        if respawn_only && !resets_on_respawn(reset_on_respawn) {
+            continue;
+        }
+        gate.hit_by = EnumMap::default();
+        gate.switched = false;
+        gate.open = false;
+        *collision_groups = closed_collision_groups();
+        *animation = still_animation(CLOSED_FRAME);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gate_opens_for_its_color() {
+        let mut app = App::new();
+        app.add_systems(Update, update_light_gates);
+        let gate = app
+            .world_mut()
+            .spawn((
+                LightGate {
+                    color: LightColor::Green,
+                    half_extent: Vec2::splat(8.0),
+                    ..default()
+                },
+                GlobalTransform::default(),
+                closed_collision_groups(),
+                still_animation(CLOSED_FRAME),
+            ))
+            .id();
+
+        app.world_mut().get_mut::<LightGate>(gate).unwrap().hit_by[LightColor::Blue] = true;
+        app.update();
+        assert!(!app.world().get::<LightGate>(gate).unwrap().open);
+
+        app.world_mut().get_mut::<LightGate>(gate).unwrap().hit_by[LightColor::Green] = true;
+        app.update();
+        assert!(app.world().get::<LightGate>(gate).unwrap().open);
+        assert_eq!(
+            *app.world().get::<CollisionGroups>(gate).unwrap(),
+            open_collision_groups()
+        );
+
+        app.world_mut().get_mut::<LightGate>(gate).unwrap().hit_by[LightColor::Green] = false;
+        app.update();
+        assert!(!app.world().get::<LightGate>(gate).unwrap().open);
+    }
+
+    #[test]
+    fn switch_logic_combines_switches() {
+        assert!(SwitchLogic::Or.opens([false, true]));
+        assert!(!SwitchLogic::And.opens([false, true]));
+        assert!(SwitchLogic::And.opens([true, true]));
+        assert!(!SwitchLogic::Or.opens([]));
+        assert!(!SwitchLogic::And.opens([]));
+    }
+}
@@ -0,0 +1,245 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+use enum_map::EnumMap;
+
+use crate::{
+    light::{segments::simulate_light_sources, LightColor},
+    shared::ResetLevel,
+    sound::AudioSettings,
+};
+
+use super::{
+    entity::{is_respawn_only, resets_on_respawn, FixedEntityBundle, ResetOnRespawn},
+    light_gate::{update_light_gates, LightGate},
+    LevelSystems,
+};
+
+/// [`Plugin`] for light switches, which latch once hit by a beam of their color and hold the
+/// [`LightGate`]s wired to them open until the level is reset.
+pub struct LightSwitchPlugin;
+
+impl Plugin for LightSwitchPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LightSwitchToggleEvent>()
+            .register_ldtk_entity::<LightSwitchBundle>("LightSwitch")
+            .add_systems(
+                PreUpdate,
+                add_light_switch_sprites.in_set(LevelSystems::Processing),
+            )
+            .add_systems(Update, reset_light_switches.in_set(LevelSystems::Reset))
+            .add_systems(
+                FixedUpdate,
+                (update_light_switches, open_switched_gates)
+                    .chain()
+                    .after(simulate_light_sources)
+                    .before(update_light_gates)
+                    .in_set(LevelSystems::Simulation),
+            );
+    }
+}
+
+/// [`Component`] for a switch that latches the first time a light beam of its `color` hits it, and
+/// stays latched after the beam moves away.
+#[derive(Default, Component, Debug)]
+pub struct LightSwitch {
+    pub color: LightColor,
+    /// The gates wired to the switch, from its `gates` entity ref field
+    pub gates: Vec<EntityIid>,
+    /// Colors of light beams hitting the switch, set by [`simulate_light_sources`]
+    pub hit_by: EnumMap<LightColor, bool>,
+    pub latched: bool,
+}
+
+impl From<&EntityInstance> for LightSwitch {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let color = entity_instance
+            .get_enum_field("light_color")
+            .expect("All light switches should have a light_color enum field")
+            .into();
+        let gates = match entity_instance.iter_entity_refs_field("gates") {
+            Ok(gates) => gates
+                .map(|gate| EntityIid::new(gate.entity_iid.clone()))
+                .collect(),
+            Err(_) => vec![],
+        };
+        LightSwitch {
+            color,
+            gates,
+            hit_by: EnumMap::default(),
+            latched: false,
+        }
+    }
+}
+
+#[derive(Bundle, LdtkEntity)]
+pub struct LightSwitchBundle {
+    #[from_entity_instance]
+    physics: FixedEntityBundle,
+    #[default]
+    sensor: Sensor,
+    #[from_entity_instance]
+    light_switch: LightSwitch,
+    #[from_entity_instance]
+    reset_on_respawn: ResetOnRespawn,
+}
+
+/// [`Event`] sent when a [`LightSwitch`] latches or is reset.
+#[derive(Event, Debug)]
+pub struct LightSwitchToggleEvent {
+    pub switch: Entity,
+}
+
+/// [`Component`] for the center of a [`LightSwitch`]'s sprite, which lights up once latched.
+#[derive(Component)]
+pub struct LightSwitchCenter;
+
+pub fn add_light_switch_sprites(
+    mut commands: Commands,
+    q_switches: Query<(Entity, &LightSwitch), Added<LightSwitch>>,
+    asset_server: Res<AssetServer>,
+) {
+    if q_switches.is_empty() {
+        return;
+    }
+    let switch_outer = asset_server.load("sensor/sensor_outer.png");
+    let switch_center = asset_server.load("sensor/sensor_center.png");
+
+    for (entity, switch) in q_switches.iter() {
+        let mut outer_sprite = Sprite::from_image(switch_outer.clone());
+        outer_sprite.color = switch.color.light_beam_color();
+        commands
+            .entity(entity)
+            .insert(outer_sprite)
+            .with_child((Sprite::from_image(switch_center.clone()), LightSwitchCenter));
+    }
+}
+
+/// [`System`] that latches the [`LightSwitch`]es hit by a beam of their color.
+pub fn update_light_switches(
+    mut commands: Commands,
+    mut q_switches: Query<(Entity, &mut LightSwitch, &Children)>,
+    mut q_centers: Query<&mut Sprite, With<LightSwitchCenter>>,
+    mut ev_toggle: EventWriter<LightSwitchToggleEvent>,
+    asset_server: Res<AssetServer>,
+    audio_settings: Res<AudioSettings>,
+) {
+    for (entity, mut switch, children) in q_switches.iter_mut() {
+        if switch.latched || !switch.hit_by[switch.color] {
+            continue;
+        }
+        switch.latched = true;
+        ev_toggle.send(LightSwitchToggleEvent { switch: entity });
+        for child in children.iter() {
+            if let Ok(mut sprite) = q_centers.get_mut(*child) {
+                sprite.color = switch.color.light_beam_color();
+            }
+        }
+        if let Some(sound) = audio_settings.sfx(asset_server.load("sfx/button.wav")) {
+            commands.entity(entity).with_child(sound);
+        }
+    }
+}
+
+/// [`System`] that opens or closes the [`LightGate`]s wired to toggled switches, following each
+/// gate's [`SwitchLogic`](super::light_gate::SwitchLogic).
+pub fn open_switched_gates(
+    mut ev_toggle: EventReader<LightSwitchToggleEvent>,
+    q_switches: Query<&LightSwitch>,
+    mut q_gates: Query<(&EntityIid, &mut LightGate)>,
+) {
+    let toggled: Vec<&LightSwitch> = ev_toggle
+        .read()
+        .filter_map(|ev| q_switches.get(ev.switch).ok())
+        .collect();
+    for (gate_iid, mut gate) in q_gates.iter_mut() {
+        if !toggled.iter().any(|switch| switch.gates.contains(gate_iid)) {
+            continue;
+        }
+        gate.switched = gate.switch_logic.opens(
+            q_switches
+                .iter()
+                .filter(|switch| switch.gates.contains(gate_iid))
+                .map(|switch| switch.latched),
+        );
+    }
+}
+
+/// [`System`] that unlatches the [`LightSwitch`]es when the level is reset. The gates they were
+/// holding open are closed by [`reset_light_gates`](super::light_gate::reset_light_gates).
+pub fn reset_light_switches(
+    mut q_switches: Query<(Entity, &mut LightSwitch, &Children, Option<&ResetOnRespawn>)>,
+    mut q_centers: Query<&mut Sprite, With<LightSwitchCenter>>,
+    mut ev_reset_level: EventReader<ResetLevel>,
+    mut ev_toggle: EventWriter<LightSwitchToggleEvent>,
+) {
+    if ev_reset_level.is_empty() {
+        return;
+    }
+    let respawn_only = is_respawn_only(&mut ev_reset_level);
+    for (entity, mut switch, children, reset_on_respawn) in q_switches.iter_mut() {
+        if respawn_only && !resets_on_respawn(reset_on_respawn) {
+            continue;
+        }
+        switch.hit_by = EnumMap::default();
+        if !switch.latched {
+            continue;
+        }
+        switch.latched = false;
+        ev_toggle.send(LightSwitchToggleEvent { switch: entity });
+        for child in children.iter() {
+            if let Ok(mut sprite) = q_centers.get_mut(*child) {
+                sprite.color = Color::WHITE;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::light_gate::SwitchLogic;
+
+    fn spawn_switch(app: &mut App, gate_iid: &str) -> Entity {
+        app.world_mut()
+            .spawn(LightSwitch {
+                color: LightColor::Green,
+                gates: vec![EntityIid::new(gate_iid.to_string())],
+                ..default()
+            })
+            .id()
+    }
+
+    #[test]
+    fn latched_switches_open_and_gate() {
+        let mut app = App::new();
+        app.add_event::<LightSwitchToggleEvent>()
+            .add_systems(Update, open_switched_gates);
+        let gate = app
+            .world_mut()
+            .spawn((
+                EntityIid::new("gate"),
+                LightGate {
+                    switch_logic: SwitchLogic::And,
+                    ..default()
+                },
+            ))
+            .id();
+        let switches = [
+            spawn_switch(&mut app, "gate"),
+            spawn_switch(&mut app, "gate"),
+        ];
+
+        for (i, switch) in switches.into_iter().enumerate() {
+            app.world_mut()
+                .get_mut::<LightSwitch>(switch)
+                .unwrap()
+                .latched = true;
+            app.world_mut()
+                .send_event(LightSwitchToggleEvent { switch });
+            app.update();
+            let switched = app.world().get::<LightGate>(gate).unwrap().switched;
+            assert_eq!(switched, i == switches.len() - 1);
+        }
+    }
+}
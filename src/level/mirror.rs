@@ -1,15 +1,26 @@
+use std::f32::consts::PI;
+
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::shared::GroupLabel;
 
-use super::entity::FixedEntityBundle;
+use super::{entity::FixedEntityBundle, LevelSystems};
 
 pub struct MirrorPlugin;
 impl Plugin for MirrorPlugin {
     fn build(&self, app: &mut App) {
-        app.register_ldtk_int_cell_for_layer::<MirrorBundle>("Terrain", 16);
+        app.register_ldtk_int_cell_for_layer::<MirrorBundle>("Terrain", 16)
+            .register_ldtk_entity::<AngledMirrorBundle>("Mirror")
+            .add_systems(
+                PreUpdate,
+                add_angled_mirror_sprites.in_set(LevelSystems::Processing),
+            );
     }
 }
 
+/// [`Component`] for surfaces that reflect light beams without using up one of their bounces.
 #[derive(Default, Component)]
 pub struct Mirror;
 
@@ -20,3 +31,85 @@ pub struct MirrorBundle {
     fixed_entity_bundle: FixedEntityBundle,
     mirror: Mirror,
 }
+
+/// [`Component`] for a mirror placed as an entity in Ldtk, a thin line that can be tilted with the
+/// `angle` enum field. Only light beams interact with it, the player walks right through.
+#[derive(Default, Component, Clone, Copy)]
+pub struct AngledMirror {
+    /// Angle of the mirror's surface from the horizontal, in radians
+    pub angle: f32,
+    pub length: f32,
+}
+
+impl From<&EntityInstance> for AngledMirror {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let angle = match entity_instance.get_enum_field("angle") {
+            Ok(angle) => match angle.as_str() {
+                "Deg0" => 0.0,
+                "Deg45" => PI / 4.0,
+                "Deg90" => PI / 2.0,
+                "Deg135" => 3.0 * PI / 4.0,
+                _ => {
+                    warn!("Unknown mirror angle {angle}, using Deg45");
+                    PI / 4.0
+                }
+            },
+            Err(_) => PI / 4.0,
+        };
+        AngledMirror {
+            angle,
+            length: entity_instance.width.max(entity_instance.height) as f32,
+        }
+    }
+}
+
+impl AngledMirror {
+    /// The mirror's surface, from its center to one of its ends.
+    fn half_extent(&self) -> Vec2 {
+        Vec2::from_angle(self.angle) * self.length / 2.0
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct AngledMirrorBundle {
+    #[from_entity_instance]
+    angled_mirror: AngledMirror,
+    mirror: Mirror,
+    #[with(angled_mirror_collider)]
+    collider: Collider,
+    #[with(angled_mirror_rigid_body)]
+    rigid_body: RigidBody,
+    #[with(angled_mirror_collision_groups)]
+    collision_groups: CollisionGroups,
+}
+
+fn angled_mirror_collider(entity_instance: &EntityInstance) -> Collider {
+    let half_extent = AngledMirror::from(entity_instance).half_extent();
+    Collider::segment(-half_extent, half_extent)
+}
+
+fn angled_mirror_rigid_body(_: &EntityInstance) -> RigidBody {
+    RigidBody::Fixed
+}
+
+fn angled_mirror_collision_groups(_: &EntityInstance) -> CollisionGroups {
+    CollisionGroups::new(
+        GroupLabel::TERRAIN,
+        GroupLabel::LIGHT_RAY
+            | GroupLabel::WHITE_RAY
+            | GroupLabel::BLUE_RAY
+            | GroupLabel::BLACK_RAY,
+    )
+}
+
+pub fn add_angled_mirror_sprites(
+    mut commands: Commands,
+    q_mirrors: Query<(Entity, &AngledMirror), Added<AngledMirror>>,
+) {
+    for (entity, mirror) in q_mirrors.iter() {
+        commands.entity(entity).with_child((
+            Sprite::from_color(Color::srgb(0.75, 0.8, 0.86), Vec2::new(mirror.length, 1.5)),
+            Transform::from_rotation(Quat::from_rotation_z(mirror.angle)),
+        ));
+    }
+}
@@ -1,51 +1,92 @@
 use std::time::Duration;
 
+use achievement::AchievementPlugin;
 use bevy::{ecs::system::SystemId, prelude::*};
 use bevy_ecs_ldtk::{ldtk::Level, prelude::*, systems::process_ldtk_levels, LevelIid};
+use checkpoint::CheckpointPlugin;
+use combo::ComboPlugin;
 use cruciera::CrucieraPlugin;
+use cutscene::CutscenePlugin;
+use darkness::DarknessPlugin;
+use death_penalty::DeathPenaltyPlugin;
 use decoration::DecorationPlugin;
 use egg::EggPlugin;
 use enum_map::{enum_map, EnumMap};
+use first_visit::FirstVisitPlugin;
+use force_zone::ForceZonePlugin;
+use lens::LensPlugin;
 use level_completion::LevelCompletionPlugin;
+use light_gate::LightGatePlugin;
+use light_switch::LightSwitchPlugin;
 use merge_tile::spawn_merged_tiles;
 use mirror::MirrorPlugin;
+use one_way_platform::OneWayPlatformPlugin;
+use quicksave::QuicksavePlugin;
+use room::CameraRoomPlugin;
+use secret_wall::SecretWallPlugin;
 use semisolid::SemiSolidPlugin;
 use sensor::LightSensorPlugin;
 use shard::CrystalShardPlugin;
+use shard_counter::ShardCounterPlugin;
 use speedrun::SpeedrunTimerPlugin;
+use stats::LevelStatsPlugin;
+use tint::AmbientTintPlugin;
+use tutorial::TutorialPlugin;
 
 use crate::{
     camera::{
         camera_position_from_level, CameraControlType, CameraMoveEvent, CAMERA_ANIMATION_SECS,
     },
+    config::Config,
     light::LightColor,
     player::{LdtkPlayerBundle, PlayerMarker},
+    save::SaveData,
     shared::{AnimationState, GameState, ResetLevel},
     sound::{BgmTrack, ChangeBgmEvent},
     ui::level_select::handle_level_selection,
 };
 use crystal::CrystalPlugin;
-use entity::SpikeBundle;
+use entity::{ColorCountHazardBundle, HazardRegistry, HazardSpeed, HazardTileBundle, SpikeBundle};
 use platform::PlatformPlugin;
 use setup::LevelSetupPlugin;
 use start_flag::{init_start_marker, StartFlagBundle};
 use walls::{Wall, WallBundle};
 
+pub mod achievement;
+pub mod checkpoint;
+mod combo;
 mod cruciera;
 pub mod crystal;
+mod cutscene;
+mod darkness;
+mod death_penalty;
 mod decoration;
 mod egg;
 pub mod entity;
-mod level_completion;
+pub mod first_visit;
+pub mod force_zone;
+pub mod lens;
+pub mod level_completion;
+pub mod light_gate;
+pub mod light_switch;
 mod merge_tile;
 pub mod mirror;
+mod one_way_platform;
 pub mod platform;
+mod quicksave;
+pub mod room;
+pub mod secret_wall;
 mod semisolid;
 pub mod sensor;
 mod setup;
 pub mod shard;
+pub mod shard_counter;
+pub mod snapshot;
 pub mod speedrun;
 pub mod start_flag;
+pub mod stats;
+pub mod tint;
+mod tutorial;
 mod walls;
 
 /// [`Plugin`] that handles everything related to the level.
@@ -54,23 +95,46 @@ pub struct LevelManagementPlugin;
 impl Plugin for LevelManagementPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(LdtkPlugin)
+            .add_plugins(LevelSystemsPlugin)
             .add_plugins(LevelSetupPlugin)
             .add_plugins(CrystalPlugin)
             .add_plugins(PlatformPlugin)
             .add_plugins(CrystalShardPlugin)
             .add_plugins(LightSensorPlugin)
             .add_plugins(SemiSolidPlugin)
+            .add_plugins(OneWayPlatformPlugin)
             .add_plugins(MirrorPlugin)
             .add_plugins(EggPlugin)
             .add_plugins(LevelCompletionPlugin)
             .add_plugins(DecorationPlugin)
             .add_plugins(CrucieraPlugin)
             .add_plugins(SpeedrunTimerPlugin)
-            .init_resource::<CurrentLevel>()
+            .add_plugins(ShardCounterPlugin)
+            .add_plugins(LevelStatsPlugin)
+            .add_plugins(FirstVisitPlugin)
+            .add_plugins(CameraRoomPlugin)
+            .add_plugins(DarknessPlugin)
+            .add_plugins(ComboPlugin)
+            .add_plugins(QuicksavePlugin)
+            .add_plugins(DeathPenaltyPlugin)
+            .add_plugins(TutorialPlugin)
+            .add_plugins(SecretWallPlugin)
+            .add_plugins(LightGatePlugin)
+            .add_plugins(LightSwitchPlugin)
+            .add_plugins(LensPlugin)
+            .add_plugins(ForceZonePlugin)
+            .add_plugins(AmbientTintPlugin)
+            .add_plugins(CutscenePlugin)
+            .add_plugins(CheckpointPlugin)
+            .add_plugins(AchievementPlugin)
             .register_ldtk_entity::<LdtkPlayerBundle>("Lyra")
             .register_ldtk_entity::<StartFlagBundle>("Start")
+            .register_ldtk_entity::<ColorCountHazardBundle>("ColorCountHazard")
             .register_ldtk_int_cell_for_layer::<WallBundle>("Terrain", 1)
             .register_ldtk_int_cell_for_layer::<SpikeBundle>("Terrain", 2)
+            .register_ldtk_int_cell_for_layer::<HazardTileBundle>("Terrain", 11)
+            .register_ldtk_int_cell_for_layer::<HazardTileBundle>("Terrain", 12)
+            .register_ldtk_int_cell_for_layer::<HazardTileBundle>("Terrain", 13)
             .add_systems(
                 PreUpdate,
                 (spawn_merged_tiles::<Wall>, init_start_marker).in_set(LevelSystems::Processing),
@@ -87,7 +151,20 @@ impl Plugin for LevelManagementPlugin {
             .configure_sets(
                 PreUpdate,
                 LevelSystems::Processing.after(process_ldtk_levels),
-            )
+            );
+    }
+}
+
+/// [`Plugin`] that orders the [`LevelSystems`] and holds the resources describing the current
+/// level, which every level system relies on. Split from [`LevelManagementPlugin`] so the level
+/// systems can also run without Ldtk in the `headless` test app.
+pub struct LevelSystemsPlugin;
+
+impl Plugin for LevelSystemsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CurrentLevel>()
+            .init_resource::<HazardSpeed>()
+            .init_resource::<HazardRegistry>()
             .configure_sets(
                 Update,
                 LevelSystems::Reset
@@ -129,6 +206,52 @@ pub struct CurrentLevel {
     pub level_iid: LevelIid,
     pub level_box: Rect,
     pub allowed_colors: EnumMap<LightColor, bool>,
+    pub death_transition: DeathTransition,
+    /// Whether the level is dark, set by the optional `Darkness` level field
+    pub darkness: bool,
+    /// Whether the camera stays put on respawn if the start flag is already in view, set by the
+    /// optional `KeepCameraOnRespawn` level field
+    pub keep_camera_on_respawn: bool,
+    /// Color the whole level is tinted by, set by the optional `AmbientTint` level field
+    pub ambient_tint: Color,
+    /// Where the player respawns instead of the start flag, set by touching a
+    /// [`CheckpointFlag`](checkpoint::CheckpointFlag)
+    pub respawn_override: Option<Vec2>,
+    /// Whether completing the level never shows the summary screen, set by the optional
+    /// `SkipCompletionSummary` level field
+    pub skip_completion_summary: bool,
+    /// The title shown when entering the level, set by the optional `IntroTitle` and
+    /// `IntroSubtitle` level fields
+    pub intro: Option<LevelIntroText>,
+}
+
+/// The title and subtitle of a level's intro, see
+/// [`LevelIntroPlugin`](crate::ui::level_intro::LevelIntroPlugin).
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct LevelIntroText {
+    pub title: String,
+    pub subtitle: Option<String>,
+}
+
+/// The transition played when the player dies, set per level by the optional `DeathTransition`
+/// level field.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeathTransition {
+    #[default]
+    Black,
+    White,
+    Instant,
+}
+
+// Convert Strings from LDtk Enums into true Enums
+impl From<&String> for DeathTransition {
+    fn from(string: &String) -> Self {
+        match string.as_str() {
+            "White" => DeathTransition::White,
+            "Instant" => DeathTransition::Instant,
+            _ => DeathTransition::Black,
+        }
+    }
 }
 
 /// [`SystemSet`] used to distinguish different types of systems
@@ -177,6 +300,8 @@ pub fn switch_level(
     on_level_switch_finish_cb: Local<OnFinishLevelSwitchCallback>,
     mut ev_move_camera: EventWriter<CameraMoveEvent>,
     mut ev_level_switch: EventWriter<ResetLevel>,
+    config: Res<Config>,
+    save_data: Res<SaveData>,
 ) {
     let Ok(player_transform) = q_player.get_single() else {
         return;
@@ -193,7 +318,16 @@ pub fn switch_level(
         if level_box.contains(player_transform.translation.xy()) {
             if current_level.level_iid.as_str() != level.iid {
                 // relies on camera to reset the state back to switching??
-                if !current_level.level_iid.to_string().is_empty() {
+                if config.camera_config.disable_transitions {
+                    ev_move_camera.send(CameraMoveEvent {
+                        to: camera_position_from_level(
+                            level_box,
+                            player_transform.translation.xy(),
+                        ),
+                        variant: CameraControlType::Instant,
+                    });
+                    ev_level_switch.send(ResetLevel::Switching);
+                } else if !current_level.level_iid.to_string().is_empty() {
                     next_game_state.set(GameState::Animating);
                     next_anim_state.set(AnimationState::Switch);
 
@@ -218,14 +352,55 @@ pub fn switch_level(
                     .map(|color_str| color_str.into())
                     .collect::<Vec<LightColor>>();
 
+                // colors from permanent shards are allowed everywhere once collected
                 let allowed_colors_map = enum_map! {
-                    val => allowed_colors.contains(&val),
+                    val => allowed_colors.contains(&val) || save_data.permanent_colors.contains(&val),
+                };
+
+                let death_transition = match level.get_enum_field("DeathTransition") {
+                    Ok(transition) => transition.into(),
+                    Err(_) => DeathTransition::default(),
+                };
+
+                let darkness = match level.get_bool_field("Darkness") {
+                    Ok(darkness) => *darkness,
+                    Err(_) => false,
                 };
 
+                let keep_camera_on_respawn = match level.get_bool_field("KeepCameraOnRespawn") {
+                    Ok(keep_camera) => *keep_camera,
+                    Err(_) => false,
+                };
+
+                let ambient_tint = match level.get_color_field("AmbientTint") {
+                    Ok(tint) => *tint,
+                    Err(_) => Color::WHITE,
+                };
+
+                let skip_completion_summary = match level.get_bool_field("SkipCompletionSummary") {
+                    Ok(skip) => *skip,
+                    Err(_) => false,
+                };
+
+                let intro = level
+                    .get_string_field("IntroTitle")
+                    .ok()
+                    .map(|title| LevelIntroText {
+                        title: title.clone(),
+                        subtitle: level.get_string_field("IntroSubtitle").ok().cloned(),
+                    });
+
                 *current_level = CurrentLevel {
                     level_iid: LevelIid::new(level.iid.clone()),
                     level_box,
                     allowed_colors: allowed_colors_map,
+                    death_transition,
+                    darkness,
+                    keep_camera_on_respawn,
+                    ambient_tint,
+                    respawn_override: None,
+                    skip_completion_summary,
+                    intro,
                 };
                 *level_selection = LevelSelection::iid(current_level.level_iid.clone());
             }
@@ -0,0 +1,289 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    config::Config,
+    input::gamepad_movement,
+    particle::dust::DustSurface,
+    player::{
+        movement::{move_player, PlayerMovement},
+        PlayerMarker, PLAYER_FEET_OFFSET, PLAYER_HALF_EXTENT,
+    },
+    shared::{GroupLabel, ResetLevel},
+};
+
+use super::{
+    entity::{is_respawn_only, resets_on_respawn, ResetOnRespawn},
+    LevelSystems,
+};
+
+/// Half the thickness of a one way platform
+const PLATFORM_HALF_THICKNESS: f32 = 1.5;
+/// How far below a platform's surface the player's feet can be and still land on it
+const LANDING_TOLERANCE: f32 = 1.0;
+/// How far above a platform's surface the player's feet can be while standing on it
+const STANDING_TOLERANCE: f32 = 1.5;
+
+/// [`Plugin`] for one way platforms, thin platforms placed in Ldtk that the player can jump up
+/// through and land on from above, or drop through by holding the drop through key.
+pub struct OneWayPlatformPlugin;
+
+impl Plugin for OneWayPlatformPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<OneWayPlatformBundle>("OneWayPlatform")
+            .add_systems(
+                PreUpdate,
+                add_one_way_platform_sprites.in_set(LevelSystems::Processing),
+            )
+            .add_systems(Update, reset_one_way_platforms.in_set(LevelSystems::Reset))
+            .add_systems(
+                FixedUpdate,
+                update_one_way_platforms
+                    .before(move_player)
+                    .in_set(LevelSystems::Simulation),
+            );
+    }
+}
+
+/// [`Component`] for a one way platform, spanning the width of its Ldtk entity. It can be tilted
+/// with the `angle` enum field, to make slopes that can be jumped through.
+#[derive(Default, Component, Debug)]
+pub struct OneWayPlatform {
+    /// Angle of the platform's surface from the horizontal, in radians
+    pub angle: f32,
+    pub half_width: f32,
+    /// Whether the player is dropping through the platform, which keeps it open until they're
+    /// below it
+    pub dropping: bool,
+}
+
+impl From<&EntityInstance> for OneWayPlatform {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let angle = match entity_instance.get_enum_field("angle") {
+            Ok(angle) => match angle.as_str() {
+                "Deg0" => 0.0,
+                "Deg45" => PI / 4.0,
+                "Deg135" => 3.0 * PI / 4.0,
+                _ => {
+                    warn!("Unknown one way platform angle {angle}, using Deg0");
+                    0.0
+                }
+            },
+            Err(_) => 0.0,
+        };
+        OneWayPlatform {
+            angle,
+            half_width: entity_instance.width as f32 / 2.0,
+            dropping: false,
+        }
+    }
+}
+
+impl OneWayPlatform {
+    /// Half the length of the platform along its surface.
+    fn half_length(&self) -> f32 {
+        self.half_width / self.angle.cos().abs()
+    }
+
+    /// How high the top of the platform is above its center, at `x` from the center. Past the ends
+    /// of the platform, this is the height at the nearest end.
+    fn surface_height(&self, x: f32) -> f32 {
+        let x = x.clamp(-self.half_width, self.half_width);
+        x * self.angle.tan() + PLATFORM_HALF_THICKNESS / self.angle.cos().abs()
+    }
+
+    /// How far above the platform's surface the player's feet are, if the player is over the
+    /// platform. `player_offset` is the player's position relative to the platform's center. On a
+    /// slope, the side of the player's collider further up the slope is the one that lands.
+    fn feet_above(&self, player_offset: Vec2) -> Option<f32> {
+        if player_offset.x.abs() > self.half_width + PLAYER_HALF_EXTENT.x {
+            return None;
+        }
+        let surface = self
+            .surface_height(player_offset.x - PLAYER_HALF_EXTENT.x)
+            .max(self.surface_height(player_offset.x + PLAYER_HALF_EXTENT.x));
+        Some(player_offset.y + PLAYER_FEET_OFFSET - surface)
+    }
+}
+
+/// Whether a one way platform should stop the player, given how far above its surface their feet
+/// are and how fast they're moving up.
+fn blocks_player(feet_above: Option<f32>, velocity_y: f32, dropping: bool) -> bool {
+    !dropping && velocity_y <= 0.0 && feet_above.is_some_and(|above| above >= -LANDING_TOLERANCE)
+}
+
+#[derive(Bundle, LdtkEntity)]
+pub struct OneWayPlatformBundle {
+    #[from_entity_instance]
+    platform: OneWayPlatform,
+    #[with(one_way_platform_collider)]
+    collider: Collider,
+    #[with(one_way_platform_rigid_body)]
+    rigid_body: RigidBody,
+    #[with(one_way_platform_collision_groups)]
+    collision_groups: CollisionGroups,
+    #[with(one_way_platform_dust_surface)]
+    dust_surface: DustSurface,
+    #[from_entity_instance]
+    reset_on_respawn: ResetOnRespawn,
+}
+
+fn one_way_platform_collider(entity_instance: &EntityInstance) -> Collider {
+    let platform = OneWayPlatform::from(entity_instance);
+    Collider::compound(vec![(
+        Vec2::ZERO,
+        platform.angle,
+        Collider::cuboid(platform.half_length(), PLATFORM_HALF_THICKNESS),
+    )])
+}
+
+fn one_way_platform_rigid_body(_: &EntityInstance) -> RigidBody {
+    RigidBody::Fixed
+}
+
+fn one_way_platform_dust_surface(_: &EntityInstance) -> DustSurface {
+    DustSurface::Wood
+}
+
+fn one_way_platform_collision_groups(_: &EntityInstance) -> CollisionGroups {
+    blocking_collision_groups()
+}
+
+/// One way platforms are terrain to light beams whether or not the player can pass through them,
+/// so beams always bounce off of them, like the semi solid platforms.
+fn blocking_collision_groups() -> CollisionGroups {
+    CollisionGroups::new(GroupLabel::TERRAIN, GroupLabel::ALL)
+}
+
+fn passable_collision_groups() -> CollisionGroups {
+    CollisionGroups::new(
+        GroupLabel::TERRAIN,
+        GroupLabel::ALL & !GroupLabel::PLAYER_COLLIDER,
+    )
+}
+
+pub fn add_one_way_platform_sprites(
+    mut commands: Commands,
+    q_platforms: Query<(Entity, &OneWayPlatform), Added<OneWayPlatform>>,
+) {
+    for (entity, platform) in q_platforms.iter() {
+        commands.entity(entity).with_child((
+            Sprite::from_color(
+                Color::srgb(0.55, 0.4, 0.27),
+                Vec2::new(platform.half_length(), PLATFORM_HALF_THICKNESS) * 2.0,
+            ),
+            Transform::from_rotation(Quat::from_rotation_z(platform.angle)),
+        ));
+    }
+}
+
+/// [`System`] that lets the player through the [`OneWayPlatform`]s they're below or jumping up
+/// through, and stops them on the ones they're falling onto. Holding the drop through key while
+/// standing on a platform drops the player through it.
+pub fn update_one_way_platforms(
+    q_player: Query<
+        (
+            &PlayerMovement,
+            &GlobalTransform,
+            Option<&KinematicCharacterControllerOutput>,
+        ),
+        With<PlayerMarker>,
+    >,
+    mut q_platforms: Query<(&GlobalTransform, &mut OneWayPlatform, &mut CollisionGroups)>,
+    keys: Res<ButtonInput<KeyCode>>,
+    q_gamepads: Query<&Gamepad>,
+    config: Res<Config>,
+) {
+    let Ok((movement, player_transform, output)) = q_player.get_single() else {
+        return;
+    };
+    let grounded = output.is_some_and(|output| output.grounded);
+    let drop_held = keys.pressed(config.controls_config.key_drop_through)
+        || gamepad_movement(&q_gamepads, &config).y < 0.0;
+
+    for (transform, mut platform, mut collision_groups) in q_platforms.iter_mut() {
+        let player_offset = player_transform.translation().xy() - transform.translation().xy();
+        let feet_above = platform.feet_above(player_offset);
+        let standing_on =
+            grounded && feet_above.is_some_and(|above| above.abs() <= STANDING_TOLERANCE);
+        if drop_held && standing_on {
+            platform.dropping = true;
+        } else if feet_above.is_none_or(|above| above < -LANDING_TOLERANCE) {
+            platform.dropping = false;
+        }
+
+        let groups = match blocks_player(feet_above, movement.velocity.y, platform.dropping) {
+            true => blocking_collision_groups(),
+            false => passable_collision_groups(),
+        };
+        if *collision_groups != groups {
+            *collision_groups = groups;
+        }
+    }
+}
+
+/// [`System`] that stops the player dropping through any [`OneWayPlatform`] when the level is
+/// reset.
+pub fn reset_one_way_platforms(
+    mut q_platforms: Query<(&mut OneWayPlatform, Option<&ResetOnRespawn>)>,
+    mut ev_reset_level: EventReader<ResetLevel>,
+) {
+    if ev_reset_level.is_empty() {
+        return;
+    }
+    let respawn_only = is_respawn_only(&mut ev_reset_level);
+    for (mut platform, reset_on_respawn) in q_platforms.iter_mut() {
+        if respawn_only && !resets_on_respawn(reset_on_respawn) {
+            continue;
+        }
+        platform.dropping = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn platforms_block_only_from_above() {
+        let platform = OneWayPlatform {
+            angle: 0.0,
+            half_width: 16.0,
+            dropping: false,
+        };
+        // standing right on top of the platform
+        let standing = platform.feet_above(Vec2::new(4.0, 10.5));
+        assert!(standing.is_some_and(|above| above.abs() < 0.01));
+        assert!(blocks_player(standing, -0.15, false));
+        // jumping up through it
+        let below = platform.feet_above(Vec2::new(4.0, 2.0));
+        assert!(!blocks_player(below, 2.2, false));
+        assert!(!blocks_player(below, -1.0, false));
+        // rising past it doesn't land, and dropping through doesn't either
+        assert!(!blocks_player(standing, 2.2, false));
+        assert!(!blocks_player(standing, -0.15, true));
+        // off the end of the platform
+        assert!(platform.feet_above(Vec2::new(30.0, 10.5)).is_none());
+    }
+
+    #[test]
+    fn sloped_platforms_land_the_uphill_side() {
+        let platform = OneWayPlatform {
+            angle: PI / 4.0,
+            half_width: 16.0,
+            dropping: false,
+        };
+        let thickness = PLATFORM_HALF_THICKNESS * 2.0_f32.sqrt();
+        // the right side of the player's collider touches the slope first
+        let above = platform
+            .feet_above(Vec2::new(
+                0.0,
+                -PLAYER_FEET_OFFSET + PLAYER_HALF_EXTENT.x + thickness,
+            ))
+            .unwrap();
+        assert!(above.abs() < 0.01);
+    }
+}
@@ -6,13 +6,20 @@ use bevy::{
 };
 use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    player::{kill::KillPlayerEvent, PlayerMarker},
+    player::{
+        kill::{KillCause, KillPlayerEvent},
+        PlayerMarker,
+    },
     shared::{GroupLabel, ResetLevel},
 };
 
-use super::{CurrentLevel, LevelSystems};
+use super::{
+    entity::{is_respawn_only, resets_on_respawn, HazardSpeed, ResetOnRespawn},
+    CurrentLevel, LevelSystems,
+};
 
 const PLAYER_WIDTH: f32 = 16.0;
 const PLAYER_HEIGHT: f32 = 19.0;
@@ -50,7 +57,7 @@ pub struct ChangePlatformStateEvent {
 }
 
 /// Enum for the state of a platform
-#[derive(Default, Clone, PartialEq, Eq, Copy, Debug)]
+#[derive(Default, Clone, PartialEq, Eq, Copy, Debug, Serialize, Deserialize)]
 pub enum PlatformState {
     #[default]
     Play,
@@ -92,7 +99,7 @@ pub struct MovingPlatform {
 }
 
 impl MovingPlatform {
-    fn get_next_direction_vec(&mut self, time: &Res<Time>) -> Vec2 {
+    fn get_next_direction_vec(&mut self, delta_secs: f32) -> Vec2 {
         match self.path_curve_points[self.curr_segment_index as usize] {
             false => Vec2::new(
                 self.curr_segment.x as f32 - self.current_position.x,
@@ -107,7 +114,7 @@ impl MovingPlatform {
                     (PI * 8.0 * (self.previous_segment.x as f32 - next_segment.x as f32).abs())
                         / (2.0 * self.speed);
                 if self.curr_state == PlatformState::Play {
-                    self.arc_time += time.delta_secs();
+                    self.arc_time += delta_secs;
                 }
                 let curr_t = (self.arc_time / total_time) * PI / 2.0;
 
@@ -160,7 +167,7 @@ impl MovingPlatform {
         platform_entity: Entity,
         platform_global_transform: &GlobalTransform,
         ev_kill_player: &mut EventWriter<KillPlayerEvent>,
-        time: &Res<Time>,
+        delta_secs: f32,
     ) {
         let (
             entity_above_player,
@@ -178,7 +185,10 @@ impl MovingPlatform {
             && player_controller_output.grounded
             && direction_and_velocity.y < 0.0
         {
-            ev_kill_player.send(KillPlayerEvent);
+            ev_kill_player.send(KillPlayerEvent {
+                cause: KillCause::Crushed,
+                ..default()
+            });
             return;
         }
 
@@ -187,21 +197,23 @@ impl MovingPlatform {
             if self.curr_state == PlatformState::Play {
                 // Crush player if platform moving player into ceiling
                 if direction.y > 0.0 && entity_above_player.is_some() {
-                    ev_kill_player.send(KillPlayerEvent);
+                    ev_kill_player.send(KillPlayerEvent {
+                        cause: KillCause::Crushed,
+                        ..default()
+                    });
                     return;
                 }
                 if (entity_left_of_player.is_none() || direction.x > 0.0)
                     && (entity_right_of_player.is_none() || direction.x < 0.0)
                 {
-                    player_transform.translation += Vec3::new(direction.x, direction.y + 0.1, 0.0)
-                        * self.speed
-                        * time.delta_secs();
+                    player_transform.translation +=
+                        Vec3::new(direction.x, direction.y + 0.1, 0.0) * self.speed * delta_secs;
                 } else {
                     player_transform.translation +=
-                        Vec3::new(0.0, direction.y + 0.1, 0.0) * self.speed * time.delta_secs();
+                        Vec3::new(0.0, direction.y + 0.1, 0.0) * self.speed * delta_secs;
                 }
             } else {
-                player_transform.translation += Vec3::new(0.0, 0.2, 0.0) * 1.0 * time.delta_secs();
+                player_transform.translation += Vec3::new(0.0, 0.2, 0.0) * 1.0 * delta_secs;
             }
         }
 
@@ -226,11 +238,17 @@ impl MovingPlatform {
             if self.curr_state == PlatformState::Play {
                 if relative_horizontal.x < 0.0 {
                     if entity_right_of_player.is_some() {
-                        ev_kill_player.send(KillPlayerEvent);
+                        ev_kill_player.send(KillPlayerEvent {
+                            cause: KillCause::Crushed,
+                            ..default()
+                        });
                         return;
                     }
                 } else if entity_left_of_player.is_some() {
-                    ev_kill_player.send(KillPlayerEvent);
+                    ev_kill_player.send(KillPlayerEvent {
+                        cause: KillCause::Crushed,
+                        ..default()
+                    });
                     return;
                 }
                 // Offset player if they are clipping into the platform
@@ -239,7 +257,7 @@ impl MovingPlatform {
                     _ => self.speed,
                 };
                 player_transform.translation.x +=
-                    direction.x * (self.speed + speed_adjustment) * time.delta_secs();
+                    direction.x * (self.speed + speed_adjustment) * delta_secs;
             }
         }
     }
@@ -370,6 +388,8 @@ pub struct MovingPlatformBundle {
     #[sprite_sheet]
     pub sprite: Sprite,
     pub physics: PlatformPhysicsBundle,
+    #[from_entity_instance]
+    pub reset_on_respawn: ResetOnRespawn,
 }
 
 /// [System] that moves platforms during each [Update] step
@@ -399,11 +419,13 @@ pub fn move_platforms(
     levels: Query<&LevelIid>,
     rapier_context: ReadDefaultRapierContext,
     time: Res<Time>,
+    hazard_speed: Res<HazardSpeed>,
     mut ev_kill_player: EventWriter<KillPlayerEvent>,
 ) {
     let Ok(mut player) = player_q.get_single_mut() else {
         return;
     };
+    let delta_secs = time.delta_secs() * hazard_speed.0;
 
     let (_, _, _, player_transform, _) = &player;
 
@@ -457,16 +479,15 @@ pub fn move_platforms(
 
     for (mut platform, mut transform, entity, global_transform) in platform_q.iter_mut() {
         // Calculate direction vector for platform motion (Depends on linear or circular motion)
-        let direction_vec = platform.get_next_direction_vec(&time);
+        let direction_vec = platform.get_next_direction_vec(delta_secs);
         //print!("X: {:?}, {:?} ", platform.curr_segment.x, platform.current_position.x);
         //println!("Y: {:?}, {:?}", platform.curr_segment.y, platform.current_position.y);
         //println!("{:?}", direction_vec);
 
         // Only move platform if it is in the Play state
         if platform.curr_state == PlatformState::Play {
-            transform.translation += Vec3::new(direction_vec.x, direction_vec.y, 0.0)
-                * platform.speed
-                * time.delta_secs();
+            transform.translation +=
+                Vec3::new(direction_vec.x, direction_vec.y, 0.0) * platform.speed * delta_secs;
 
             let mut new_entity = entity;
             while let Ok(parent) = parents.get(new_entity) {
@@ -499,7 +520,7 @@ pub fn move_platforms(
             entity,
             global_transform,
             &mut ev_kill_player,
-            &time,
+            delta_secs,
         );
 
         // Calculate distance to platform goal (Depends on linear or circular motion)
@@ -539,9 +560,17 @@ pub fn move_platforms(
     }
 }
 
-/// [System] that resets the state of all platforms
-pub fn reset_platforms(mut platform_q: Query<(&mut MovingPlatform, &mut Transform)>) {
-    for (mut platform, mut transform) in platform_q.iter_mut() {
+/// [System] that resets the state of all platforms, or only those with [ResetOnRespawn] when the
+/// player respawns
+pub fn reset_platforms(
+    mut platform_q: Query<(&mut MovingPlatform, &mut Transform, Option<&ResetOnRespawn>)>,
+    mut ev_reset_level: EventReader<ResetLevel>,
+) {
+    let respawn_only = is_respawn_only(&mut ev_reset_level);
+    for (mut platform, mut transform, reset_on_respawn) in platform_q.iter_mut() {
+        if respawn_only && !resets_on_respawn(reset_on_respawn) {
+            continue;
+        }
         transform.translation = Vec3::new(
             (platform.path[0].x as f32 * BLOCK_WIDTH) + (platform.width as f32 / 2.0),
             (22.0 * BLOCK_WIDTH) - (platform.path[0].y as f32 * BLOCK_WIDTH)
@@ -0,0 +1,127 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::{
+    camera::{camera_position_from_level, CameraControlType, CameraMoveEvent},
+    config::Config,
+    player::PlayerMarker,
+    save::SaveData,
+    shared::{GameState, ResetLevel},
+};
+
+use super::{
+    room::{camera_box_at, CameraRoom},
+    snapshot::LevelSnapshot,
+    CurrentLevel,
+};
+
+/// How many frames to wait after starting a quickload before restoring the snapshot, so the reset
+/// systems have run and the level's entities have spawned.
+const QUICKLOAD_WAIT_FRAMES: u32 = 2;
+/// How many frames to wait for the saved level to be switched to before giving up.
+const QUICKLOAD_TIMEOUT_FRAMES: u32 = 120;
+
+/// [`Plugin`] that lets the player save the state of the level they are in to disk, and load it
+/// back later, even from another level.
+pub struct QuicksavePlugin;
+
+impl Plugin for QuicksavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingQuickload>().add_systems(
+            Update,
+            (quicksave, start_quickload, finish_quickload).chain(),
+        );
+    }
+}
+
+struct QuickloadProgress {
+    snapshot: LevelSnapshot,
+    frames: u32,
+}
+
+/// [`Resource`] holding the snapshot being loaded while waiting for the level to be ready.
+#[derive(Resource, Default)]
+pub struct PendingQuickload(Option<QuickloadProgress>);
+
+fn quicksave(world: &mut World) {
+    let key = world.resource::<Config>().controls_config.key_quicksave;
+    if !world.resource::<ButtonInput<KeyCode>>().just_pressed(key)
+        || *world.resource::<State<GameState>>() != GameState::Playing
+    {
+        return;
+    }
+    let Some(snapshot) = LevelSnapshot::capture(world) else {
+        return;
+    };
+    world.resource_mut::<SaveData>().quicksave = Some(snapshot);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn start_quickload(
+    keys: Res<ButtonInput<KeyCode>>,
+    config: Res<Config>,
+    game_state: Res<State<GameState>>,
+    save_data: Res<SaveData>,
+    mut pending: ResMut<PendingQuickload>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut q_player: Query<&mut Transform, With<PlayerMarker>>,
+    mut ev_reset_level: EventWriter<ResetLevel>,
+) {
+    if !keys.just_pressed(config.controls_config.key_quickload)
+        || *game_state != GameState::Playing
+        || pending.0.is_some()
+    {
+        return;
+    }
+    let Some(snapshot) = save_data.quicksave.clone() else {
+        return;
+    };
+
+    if snapshot.level_iid == current_level.level_iid {
+        // reset the level first, so that light beams, sensors and crystals don't carry over
+        ev_reset_level.send(ResetLevel::Quickload);
+    } else {
+        // like the level select, putting the player in the saved level and clearing the current
+        // level makes switch_level switch to it without the camera transition
+        let Ok(mut player_transform) = q_player.get_single_mut() else {
+            return;
+        };
+        player_transform.translation = snapshot.player_translation;
+        current_level.level_iid = LevelIid::new("");
+    }
+    pending.0 = Some(QuickloadProgress {
+        snapshot,
+        frames: 0,
+    });
+}
+
+fn finish_quickload(world: &mut World) {
+    let Some(mut progress) = world.resource_mut::<PendingQuickload>().0.take() else {
+        return;
+    };
+    progress.frames += 1;
+
+    let in_level = world.resource::<CurrentLevel>().level_iid == progress.snapshot.level_iid;
+    if !in_level || progress.frames < QUICKLOAD_WAIT_FRAMES {
+        if progress.frames < QUICKLOAD_TIMEOUT_FRAMES {
+            world.resource_mut::<PendingQuickload>().0 = Some(progress);
+        } else {
+            warn!("Gave up on quickloading, the saved level never loaded");
+        }
+        return;
+    }
+
+    progress.snapshot.restore(world);
+
+    let player_pos = progress.snapshot.player_translation.xy();
+    let level_box = world.resource::<CurrentLevel>().level_box;
+    let camera_box = camera_box_at(
+        world.query::<&CameraRoom>().iter(world),
+        level_box,
+        player_pos,
+    );
+    world.send_event(CameraMoveEvent {
+        to: camera_position_from_level(camera_box, player_pos),
+        variant: CameraControlType::Instant,
+    });
+}
@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+/// [`Plugin`] for camera rooms, rectangles placed in Ldtk that split a large level into smaller
+/// areas for the camera to frame.
+pub struct CameraRoomPlugin;
+
+impl Plugin for CameraRoomPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<CameraRoomBundle>("CameraRoom");
+    }
+}
+
+/// [`Component`] holding the world space bounds of a camera room. While the player is inside a
+/// room, the camera is constrained to it instead of the whole level. Rooms should be at least as
+/// large as the camera.
+#[derive(Default, Component)]
+pub struct CameraRoom {
+    pub room_box: Rect,
+}
+
+impl From<&EntityInstance> for CameraRoom {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        CameraRoom {
+            room_box: entity_box(entity_instance),
+        }
+    }
+}
+
+/// [`Component`] holding the world space bounds of a trigger, an area placed in Ldtk that does
+/// something when the player walks into it. What it does is decided by the other components of the
+/// entity, like a [`CutsceneTrigger`](super::cutscene::CutsceneTrigger).
+#[derive(Default, Component)]
+pub struct TriggerZone {
+    pub trigger_box: Rect,
+}
+
+impl From<&EntityInstance> for TriggerZone {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        TriggerZone {
+            trigger_box: entity_box(entity_instance),
+        }
+    }
+}
+
+/// Returns the world space bounds of a resizable Ldtk entity, like a [`CameraRoom`].
+pub fn entity_box(entity_instance: &EntityInstance) -> Rect {
+    let width = entity_instance.width as f32;
+    let height = entity_instance.height as f32;
+    let left = entity_instance
+        .world_x
+        .expect("Lightborne uses Free world layout") as f32
+        - entity_instance.pivot.x * width;
+    let top = entity_instance
+        .world_y
+        .expect("Lightborne uses Free world layout") as f32
+        - entity_instance.pivot.y * height;
+    Rect::new(left, -top, left + width, -top - height)
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct CameraRoomBundle {
+    #[from_entity_instance]
+    room: CameraRoom,
+}
+
+/// Returns the box the camera should be constrained to when following `pos`: the [`CameraRoom`]
+/// containing it, or the `level_box` if it isn't inside any room in the level.
+pub fn camera_box_at<'a>(
+    rooms: impl IntoIterator<Item = &'a CameraRoom>,
+    level_box: Rect,
+    pos: Vec2,
+) -> Rect {
+    rooms
+        .into_iter()
+        .map(|room| room.room_box)
+        .find(|room_box| room_box.contains(pos) && !room_box.intersect(level_box).is_empty())
+        .unwrap_or(level_box)
+}
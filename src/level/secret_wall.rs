@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    light::LightColor,
+    player::{
+        light::PlayerLightInventory, PlayerMarker, PLAYER_COLLIDER_OFFSET, PLAYER_HALF_EXTENT,
+    },
+    shared::GroupLabel,
+};
+
+use super::LevelSystems;
+
+/// Alpha of a secret wall's sprite once it has faded away
+const OPEN_ALPHA: f32 = 0.15;
+/// How much of the way the sprite's alpha moves to its target each second
+const FADE_SPEED: f32 = 6.0;
+
+/// [`Plugin`] for secret walls, rectangles placed in Ldtk that the player can only walk through
+/// while holding the wall's [`LightColor`].
+pub struct SecretWallPlugin;
+
+impl Plugin for SecretWallPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<SecretWallBundle>("SecretWall")
+            .add_systems(
+                PreUpdate,
+                add_secret_wall_sprites.in_set(LevelSystems::Processing),
+            )
+            .add_systems(
+                FixedUpdate,
+                update_secret_walls.in_set(LevelSystems::Simulation),
+            )
+            .add_systems(Update, fade_secret_walls);
+    }
+}
+
+#[derive(Default, Component)]
+pub struct SecretWall {
+    pub color: LightColor,
+    pub half_extent: Vec2,
+    pub open: bool,
+}
+
+impl From<&EntityInstance> for SecretWall {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let color = entity_instance
+            .get_enum_field("light_color")
+            .expect("All secret walls should have a light_color enum field")
+            .into();
+        SecretWall {
+            color,
+            half_extent: Vec2::new(entity_instance.width as f32, entity_instance.height as f32)
+                / 2.0,
+            open: false,
+        }
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct SecretWallBundle {
+    #[from_entity_instance]
+    wall: SecretWall,
+}
+
+fn closed_collision_groups() -> CollisionGroups {
+    CollisionGroups::new(GroupLabel::TERRAIN, GroupLabel::ALL)
+}
+
+fn open_collision_groups() -> CollisionGroups {
+    CollisionGroups::new(GroupLabel::TERRAIN, Group::NONE)
+}
+
+pub fn add_secret_wall_sprites(
+    mut commands: Commands,
+    q_walls: Query<(Entity, &SecretWall), Added<SecretWall>>,
+) {
+    for (entity, wall) in q_walls.iter() {
+        commands.entity(entity).insert((
+            RigidBody::Fixed,
+            Collider::cuboid(wall.half_extent.x, wall.half_extent.y),
+            closed_collision_groups(),
+            Sprite::from_color(wall.color.light_beam_color(), wall.half_extent * 2.0),
+        ));
+    }
+}
+
+/// [`System`] that opens the secret walls matching the player's current color, and closes the
+/// rest. Since this follows the current color, walls are restored on color changes and level
+/// switches without any extra reset. If a wall closes on top of the player, they are pushed out of
+/// it through its nearest side.
+pub fn update_secret_walls(
+    mut q_player: Query<(&mut Transform, &PlayerLightInventory), With<PlayerMarker>>,
+    mut q_walls: Query<
+        (&GlobalTransform, &mut SecretWall, &mut CollisionGroups),
+        Without<PlayerMarker>,
+    >,
+) {
+    let Ok((mut player_transform, inventory)) = q_player.get_single_mut() else {
+        return;
+    };
+
+    for (transform, mut wall, mut collision_groups) in q_walls.iter_mut() {
+        let open = inventory.current_color == Some(wall.color);
+        if wall.open == open {
+            continue;
+        }
+        wall.open = open;
+        if open {
+            *collision_groups = open_collision_groups();
+            continue;
+        }
+        *collision_groups = closed_collision_groups();
+
+        let wall_box = Rect::from_center_half_size(transform.translation().xy(), wall.half_extent);
+        let player_box = Rect::from_center_half_size(
+            player_transform.translation.xy() + PLAYER_COLLIDER_OFFSET,
+            PLAYER_HALF_EXTENT,
+        );
+        if let Some(push) = eject_direction(wall_box, player_box) {
+            player_transform.translation += push.extend(0.0);
+        }
+    }
+}
+
+/// Returns the shortest push that moves `inner` out of `outer`, or `None` if they don't overlap.
+pub fn eject_direction(outer: Rect, inner: Rect) -> Option<Vec2> {
+    if outer.intersect(inner).is_empty() {
+        return None;
+    }
+    // a little extra so the character controller doesn't start inside the wall
+    const EJECT_EPSILON: f32 = 0.5;
+    [
+        Vec2::new(outer.min.x - inner.max.x - EJECT_EPSILON, 0.0),
+        Vec2::new(outer.max.x - inner.min.x + EJECT_EPSILON, 0.0),
+        Vec2::new(0.0, outer.min.y - inner.max.y - EJECT_EPSILON),
+        Vec2::new(0.0, outer.max.y - inner.min.y + EJECT_EPSILON),
+    ]
+    .into_iter()
+    .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+}
+
+/// [`System`] that fades open secret walls out, and closed ones back in.
+pub fn fade_secret_walls(mut q_walls: Query<(&SecretWall, &mut Sprite)>, time: Res<Time>) {
+    for (wall, mut sprite) in q_walls.iter_mut() {
+        let target = if wall.open { OPEN_ALPHA } else { 1.0 };
+        let alpha = sprite.color.alpha();
+        if alpha == target {
+            continue;
+        }
+        let step = (target - alpha) * (FADE_SPEED * time.delta_secs()).min(1.0);
+        let alpha = if (target - alpha - step).abs() < 0.01 {
+            target
+        } else {
+            alpha + step
+        };
+        sprite.color.set_alpha(alpha);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ejects_through_nearest_side() {
+        let wall = Rect::new(0.0, 0.0, 32.0, 32.0);
+        let player = Rect::from_center_half_size(Vec2::new(4.0, 16.0), PLAYER_HALF_EXTENT);
+        let push = eject_direction(wall, player).unwrap();
+        assert!(push.x < 0.0 && push.y == 0.0);
+        assert!(player.max.x + push.x <= wall.min.x);
+    }
+
+    #[test]
+    fn no_ejection_outside_wall() {
+        let wall = Rect::new(0.0, 0.0, 32.0, 32.0);
+        let player = Rect::from_center_half_size(Vec2::new(48.0, 16.0), PLAYER_HALF_EXTENT);
+        assert_eq!(eject_direction(wall, player), None);
+    }
+}
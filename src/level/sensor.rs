@@ -4,17 +4,23 @@ use bevy_rapier2d::prelude::*;
 use enum_map::EnumMap;
 
 use crate::{
+    config::Config,
     level::{
         crystal::{CrystalIdent, CrystalToggleEvent},
         platform::ChangePlatformStateEvent,
+        tint::tint_effectiveness,
     },
-    light::segments::simulate_light_sources,
+    light::mix::{update_light_mix_nodes, LightMixNode, MIX_SENSOR_RANGE},
     lighting::LineLight2d,
+    shared::ResetLevel,
+    sound::AudioSettings,
 };
 
 use super::{
-    crystal::CrystalColor, entity::FixedEntityBundle, platform::PlatformState, LevelSystems,
-    LightColor,
+    crystal::CrystalColor,
+    entity::{is_respawn_only, resets_on_respawn, FixedEntityBundle, ResetOnRespawn},
+    platform::PlatformState,
+    CurrentLevel, LevelSystems, LightColor,
 };
 
 pub struct LightSensorPlugin;
@@ -30,7 +36,7 @@ impl Plugin for LightSensorPlugin {
             .add_systems(
                 FixedUpdate,
                 update_light_sensors
-                    .after(simulate_light_sources)
+                    .after(update_light_mix_nodes)
                     .in_set(LevelSystems::Simulation),
             );
     }
@@ -60,10 +66,13 @@ pub struct LightSensor {
     pub platform_id: i32,
     /// Stored color used to animate the center of the sensor when the light no longer hits it
     stored_color: Color,
+    /// Colors that have to cross right in front of the sensor to charge it, see
+    /// [`LightMixNode`]. Sensors without any are charged by beams hitting them
+    pub required_mix: EnumMap<LightColor, bool>,
 }
 
 impl LightSensor {
-    fn new(toggle_ident: CrystalIdent, millis: i32, platform_id: i32) -> Self {
+    pub(super) fn new(toggle_ident: CrystalIdent, millis: i32, platform_id: i32) -> Self {
         let rate = 1.0 / (millis as f32) * (1000.0 / 64.0);
         LightSensor {
             meter: 0.0,
@@ -74,6 +83,7 @@ impl LightSensor {
             rate,
             platform_id,
             stored_color: Color::WHITE,
+            required_mix: EnumMap::default(),
         }
     }
 
@@ -84,15 +94,45 @@ impl LightSensor {
         self.cumulative_exposure.reset();
     }
 
-    fn is_hit(&self) -> bool {
-        self.hit_by.iter().any(|(_, hit_by_color)| *hit_by_color)
-    }
-
     fn iter_hit_color(&self) -> impl Iterator<Item = LightColor> + '_ {
         self.hit_by
             .iter()
             .filter_map(|(color, hit_by_color)| if *hit_by_color { Some(color) } else { None })
     }
+
+    fn needs_mix(&self) -> bool {
+        self.required_mix.values().any(|required| *required)
+    }
+
+    /// The colors charging the sensor, which for sensors needing a mix are the required colors
+    /// once they cross at one of the `mix_nodes` in front of it.
+    fn charging_colors<'a>(
+        &self,
+        sensor_pos: Vec2,
+        collider: &Collider,
+        mut mix_nodes: impl Iterator<Item = (&'a LightMixNode, &'a Transform)>,
+    ) -> Vec<LightColor> {
+        if !self.needs_mix() {
+            return self.iter_hit_color().collect();
+        }
+        let mixed = mix_nodes.any(|(node, node_transform)| {
+            node.contains(&self.required_mix)
+                && collider.distance_to_point(
+                    sensor_pos,
+                    0.0,
+                    node_transform.translation.xy(),
+                    true,
+                ) <= MIX_SENSOR_RANGE
+        });
+        match mixed {
+            true => self
+                .required_mix
+                .iter()
+                .filter_map(|(color, required)| required.then_some(color))
+                .collect(),
+            false => vec![],
+        }
+    }
 }
 
 impl From<&EntityInstance> for LightSensor {
@@ -120,7 +160,18 @@ impl From<&EntityInstance> for LightSensor {
             Err(_) => -1,
         };
 
-        LightSensor::new(toggle_ident, millis, platform_id)
+        let required_mix = match entity_instance.iter_enums_field("mix_colors") {
+            Ok(colors) => {
+                let colors: Vec<LightColor> = colors.map(|color| color.into()).collect();
+                EnumMap::from_fn(|color| colors.contains(&color))
+            }
+            Err(_) => EnumMap::default(),
+        };
+
+        LightSensor {
+            required_mix,
+            ..LightSensor::new(toggle_ident, millis, platform_id)
+        }
     }
 }
 
@@ -167,6 +218,8 @@ pub struct LightSensorBundle {
     light_sensor: LightSensor,
     #[with(sensor_point_light)]
     lighting: LineLight2d,
+    #[from_entity_instance]
+    reset_on_respawn: ResetOnRespawn,
 }
 
 pub fn sensor_point_light(entity_instance: &EntityInstance) -> LineLight2d {
@@ -187,8 +240,15 @@ pub fn sensor_point_light(entity_instance: &EntityInstance) -> LineLight2d {
 }
 
 /// [`System`] that resets the [`LightSensor`]s when a [`LevelSwitchEvent`] is received.
-pub fn reset_light_sensors(mut q_sensors: Query<&mut LightSensor>) {
-    for mut sensor in q_sensors.iter_mut() {
+pub fn reset_light_sensors(
+    mut q_sensors: Query<(&mut LightSensor, Option<&ResetOnRespawn>)>,
+    mut ev_reset_level: EventReader<ResetLevel>,
+) {
+    let respawn_only = is_respawn_only(&mut ev_reset_level);
+    for (mut sensor, reset_on_respawn) in q_sensors.iter_mut() {
+        if respawn_only && !resets_on_respawn(reset_on_respawn) {
+            continue;
+        }
         sensor.reset()
     }
 }
@@ -199,30 +259,52 @@ pub fn reset_light_sensors(mut q_sensors: Query<&mut LightSensor>) {
 /// is still imperfect, as while it differs semantically from the previous implementation,
 /// each [`Event`] is generated every frame. Preferably, refactor to include a "yap"-free
 /// implementation across multiple systems to better utilize [`Event`].
+#[allow(clippy::too_many_arguments)]
 pub fn update_light_sensors(
     mut commands: Commands,
-    mut q_sensors: Query<(Entity, &mut LightSensor, &mut Sprite)>,
+    mut q_sensors: Query<(
+        Entity,
+        &mut LightSensor,
+        &mut Sprite,
+        &GlobalTransform,
+        &Collider,
+    )>,
+    q_mix_nodes: Query<(&LightMixNode, &Transform)>,
     mut ev_crystal_toggle: EventWriter<CrystalToggleEvent>,
     mut platform_change: EventWriter<ChangePlatformStateEvent>,
     asset_server: Res<AssetServer>,
     time: Res<Time>,
+    current_level: Res<CurrentLevel>,
+    config: Res<Config>,
+    audio_settings: Res<AudioSettings>,
 ) {
-    for (entity, mut sensor, mut sprite) in q_sensors.iter_mut() {
-        let was_hit = sensor.is_hit();
+    for (entity, mut sensor, mut sprite, transform, collider) in q_sensors.iter_mut() {
+        let hit_colors =
+            sensor.charging_colors(transform.translation().xy(), collider, q_mix_nodes.iter());
+        let was_hit = !hit_colors.is_empty();
 
         if was_hit {
             sensor.cumulative_exposure.tick(time.delta());
 
             // if the sensor was hit, update the stored color for the sensor
             let mut col = Vec3::ZERO;
-            for color in sensor.iter_hit_color() {
+            for color in hit_colors.iter() {
                 col += color.lighting_color() * 0.5;
             }
             col += Vec3::splat(0.6);
             sensor.stored_color = Color::srgb(col.x, col.y, col.z);
         }
 
-        let juice = if was_hit { sensor.rate } else { -sensor.rate };
+        // the sensor charges as fast as the most effective beam hitting it allows
+        let effectiveness = hit_colors
+            .iter()
+            .map(|color| tint_effectiveness(&current_level, &config, *color))
+            .fold(0.0, f32::max);
+        let juice = if was_hit {
+            sensor.rate * effectiveness
+        } else {
+            -sensor.rate
+        };
         sensor.meter += juice;
 
         let mut send_toggle = || {
@@ -240,10 +322,9 @@ pub fn update_light_sensors(
                     id: sensor.platform_id,
                 });
             }
-            commands.entity(entity).with_child((
-                AudioPlayer::new(asset_server.load("sfx/button.wav")),
-                PlaybackSettings::DESPAWN,
-            ));
+            if let Some(sound) = audio_settings.sfx(asset_server.load("sfx/button.wav")) {
+                commands.entity(entity).with_child(sound);
+            }
         };
 
         if sensor.meter > 1.0 {
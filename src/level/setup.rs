@@ -1,4 +1,5 @@
 use crate::config::Config;
+use crate::save::SaveData;
 use crate::shared::GameState;
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
@@ -7,7 +8,12 @@ pub struct LevelSetupPlugin;
 
 impl Plugin for LevelSetupPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(LevelSelection::index(17))
+        // start where the save left off, if it has been played before
+        let level_selection = match &app.world().resource::<SaveData>().last_level {
+            Some(level_iid) => LevelSelection::iid(level_iid),
+            None => LevelSelection::index(17),
+        };
+        app.insert_resource(level_selection)
             .insert_resource(LdtkSettings {
                 level_spawn_behavior: LevelSpawnBehavior::UseWorldTranslation {
                     load_level_neighbors: true,
@@ -1,6 +1,6 @@
 use std::{cmp::Ordering, time::Duration};
 
-use bevy::{ecs::system::SystemId, prelude::*};
+use bevy::{ecs::system::SystemId, prelude::*, time::Real};
 use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
 use enum_map::EnumMap;
@@ -9,28 +9,40 @@ use crate::{
     animation::AnimationConfig,
     camera::{
         camera_position_from_level, camera_position_from_level_with_scale, CameraControlType,
-        CameraMoveEvent, CameraZoomEvent,
+        CameraMoveEvent, CameraZoomEvent, HIGHRES_LAYER,
+    },
+    config::{Config, DuplicateShardBehavior, HurtShardOverlap},
+    debug::not_debug_flying,
+    light::{
+        palette::{ColorPalette, ColorSymbol},
+        LightColor,
     },
-    light::LightColor,
     lighting::LineLight2d,
     player::{
+        kill::{kill_player_on_hurt_intersection, KillPlayerEvent},
         light::{
             despawn_angle_increments_indicators, despawn_angle_indicator, should_shoot_light,
             PlayerLightInventory,
         },
         InputLocked, PlayerHurtMarker, PlayerMarker,
     },
+    save::SaveData,
     shared::{AnimationState, GameState, ResetLevel},
-    sound::{BgmMarker, Fade, FadeSettings, BGM_VOLUME},
+    sound::{AudioSettings, BgmMarker, Fade, FadeSettings, BGM_VOLUME},
 };
 
-use super::{entity::FixedEntityBundle, CurrentLevel, LevelSystems};
+use super::{
+    checkpoint::{activate_checkpoints, CheckpointActivated},
+    entity::{is_respawn_only, FixedEntityBundle},
+    CurrentLevel, LevelSystems,
+};
 
 pub struct CrystalShardPlugin;
 
 impl Plugin for CrystalShardPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<ShardAnimationEvent>()
+            .add_event::<ShardCollectedEvent>()
             .init_resource::<CrystalShardMods>()
             .init_resource::<ShardAnimationCallbacks>()
             .register_ldtk_entity::<CrystalShardBundle>("CrystalShard")
@@ -38,6 +50,10 @@ impl Plugin for CrystalShardPlugin {
                 PreUpdate,
                 add_crystal_shard_sprites.in_set(LevelSystems::Processing),
             )
+            .add_systems(
+                Update,
+                update_shard_symbols.run_if(resource_changed::<ColorPalette>),
+            )
             // FIXME: if the player is holding down the mouse button while collecting a shard,
             // the preview and angle indicator will stay. need to potentially consider a input
             // manager resource
@@ -54,17 +70,29 @@ impl Plugin for CrystalShardPlugin {
                 Update,
                 shard_dialogue.run_if(in_state(AnimationState::ShardDialogue)),
             )
+            .add_systems(Update, (spawn_shard_absorb, update_shard_absorb).chain())
+            .add_systems(Update, (start_shard_flash, update_shard_flash).chain())
             .add_systems(
                 Update,
                 (
-                    reset_shard_visibility,
+                    reset_shards,
                     (reset_shard_effects_on_kill, reset_shard_effects_cache).chain(),
                 )
                     .in_set(LevelSystems::Reset),
             )
             .add_systems(
                 FixedUpdate,
-                (on_player_intersect_shard, start_shard_animation)
+                (
+                    // so a kill in the same tick is known, see HurtShardOverlap
+                    on_player_intersect_shard
+                        .run_if(not_debug_flying)
+                        .after(kill_player_on_hurt_intersection),
+                    play_shard_cutscene,
+                    keep_shards_at_checkpoint
+                        .after(activate_checkpoints)
+                        .run_if(on_event::<CheckpointActivated>),
+                    start_shard_animation,
+                )
                     .chain()
                     .in_set(LevelSystems::Simulation),
             );
@@ -73,7 +101,28 @@ impl Plugin for CrystalShardPlugin {
 
 #[derive(Component, Debug)]
 pub struct CrystalShard {
-    light_color: LightColor,
+    pub(super) light_color: LightColor,
+    /// Whether the color stays unlocked for the rest of the run, instead of being taken away on
+    /// death and when leaving the level
+    pub(super) permanent: bool,
+    /// The color the player has to be holding to collect the shard, set by the optional
+    /// `required_color` enum field
+    pub(super) required_color: Option<LightColor>,
+    /// Whether the player has collected the shard, so a [`ShardCollectedEvent`] is only sent once
+    /// even while the player keeps touching it
+    pub collected: bool,
+}
+
+impl CrystalShard {
+    pub fn light_color(&self) -> LightColor {
+        self.light_color
+    }
+
+    /// Whether the player can collect the shard while their current color is `current_color`.
+    fn collectable_with(&self, current_color: Option<LightColor>) -> bool {
+        self.required_color
+            .is_none_or(|required_color| current_color == Some(required_color))
+    }
 }
 
 impl From<&EntityInstance> for CrystalShard {
@@ -82,8 +131,22 @@ impl From<&EntityInstance> for CrystalShard {
             .get_enum_field("light_color")
             .expect("All crystal shards should have a light_color enum field")
             .into();
+        let permanent = match value.get_bool_field("permanent") {
+            Ok(permanent) => *permanent,
+            Err(_) => false,
+        };
+
+        let required_color = value
+            .get_enum_field("required_color")
+            .ok()
+            .map(|color| color.into());
 
-        Self { light_color }
+        Self {
+            light_color,
+            permanent,
+            required_color,
+            collected: false,
+        }
     }
 }
 
@@ -109,14 +172,26 @@ pub fn crystal_shard_light(entity_instance: &EntityInstance) -> LineLight2d {
 }
 
 #[derive(Resource, Default)]
-/// Sets a value to true if the light color was obtained from a crystal in the current level
-pub struct CrystalShardMods(EnumMap<LightColor, bool>);
+/// Sets a value to true if the light color was obtained from a crystal in the current level.
+/// Permanent shards are never recorded here, they go in [`SaveData::permanent_colors`] instead
+pub struct CrystalShardMods(pub EnumMap<LightColor, bool>);
+
+impl CrystalShardMods {
+    /// Records that a shard of `color` was collected, so the color is taken away again on death.
+    pub fn record_collected(&mut self, color: LightColor, current_level: &CurrentLevel) {
+        if !current_level.allowed_colors[color] {
+            // only mark as temporary modification if not actually allowed
+            self.0[color] = true;
+        }
+    }
+}
 
 pub fn add_crystal_shard_sprites(
     mut commands: Commands,
     q_shards: Query<(Entity, &CrystalShard), Added<CrystalShard>>,
     mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
     asset_server: Res<AssetServer>,
+    palette: Res<ColorPalette>,
 ) {
     const CRYSTAL_SHARD_FRAMES: usize = 7;
     const CRYSTAL_SHARD_ROWS: usize = 4;
@@ -129,35 +204,117 @@ pub fn add_crystal_shard_sprites(
         None,
     ));
 
-    let shard_row = |shard: &CrystalShard| match shard.light_color {
-        LightColor::Blue => 0,
-        LightColor::Green => 1,
-        LightColor::Purple => 2,
-        LightColor::White => 3,
-        LightColor::Black => 4,
+    let symbol_visibility = if palette.shows_symbols() {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
     };
 
     for (shard_entity, shard) in q_shards.iter() {
-        let start_index = shard_row(shard) * CRYSTAL_SHARD_FRAMES;
-        commands.entity(shard_entity).insert((
-            Sprite {
-                image: asset_server.load("crystal_shard_sheet.png"),
-                texture_atlas: Some(TextureAtlas {
-                    layout: texture_atlas_layout.clone(),
-                    index: start_index,
-                }),
-                ..default()
-            },
-            AnimationConfig::new(start_index, start_index + CRYSTAL_SHARD_FRAMES - 1, 6, true),
-        ));
+        let start_index = palette.shard_row(shard.light_color) * CRYSTAL_SHARD_FRAMES;
+        commands
+            .entity(shard_entity)
+            .insert((
+                Sprite {
+                    image: asset_server.load("crystal_shard_sheet.png"),
+                    texture_atlas: Some(TextureAtlas {
+                        layout: texture_atlas_layout.clone(),
+                        index: start_index,
+                    }),
+                    ..default()
+                },
+                AnimationConfig::new(start_index, start_index + CRYSTAL_SHARD_FRAMES - 1, 6, true),
+            ))
+            .with_children(|shard_parent| {
+                shard_parent
+                    .spawn((
+                        Transform::from_xyz(0.0, 0.0, 0.5),
+                        symbol_visibility,
+                        ShardSymbol,
+                    ))
+                    .with_children(|symbol| {
+                        for (size, rotation) in ColorSymbol::from(shard.light_color).parts() {
+                            symbol.spawn((
+                                Sprite::from_color(Color::BLACK.with_alpha(0.8), *size),
+                                Transform::from_rotation(Quat::from_rotation_z(*rotation)),
+                            ));
+                        }
+                    });
+            });
     }
 }
 
-pub fn reset_shard_visibility(mut q_shards: Query<&mut Visibility, With<CrystalShard>>) {
-    for mut visibility in q_shards.iter_mut() {
+/// [`Component`] for the symbol drawn on a shard, which tells its color apart by shape in the
+/// colorblind palette.
+#[derive(Component)]
+pub struct ShardSymbol;
+
+/// [`System`] that shows or hides the symbols of already spawned shards when the palette changes.
+pub fn update_shard_symbols(
+    palette: Res<ColorPalette>,
+    mut q_symbols: Query<&mut Visibility, With<ShardSymbol>>,
+) {
+    let visibility = if palette.shows_symbols() {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    for mut symbol_visibility in q_symbols.iter_mut() {
+        symbol_visibility.set_if_neq(visibility);
+    }
+}
+
+/// [`Component`] for collected shards that stay collected when the player respawns, because a
+/// checkpoint was reached after collecting them.
+#[derive(Component)]
+pub struct KeptAtCheckpoint;
+
+/// [`System`] that shows the collected shards again, so they can be collected again. Shards
+/// collected before the last checkpoint are only reset when switching levels.
+pub fn reset_shards(
+    mut commands: Commands,
+    mut q_shards: Query<(
+        Entity,
+        &mut CrystalShard,
+        &mut Visibility,
+        Has<KeptAtCheckpoint>,
+    )>,
+    mut ev_reset_level: EventReader<ResetLevel>,
+) {
+    let respawn_only = is_respawn_only(&mut ev_reset_level);
+    for (entity, mut shard, mut visibility, kept) in q_shards.iter_mut() {
+        if kept {
+            if respawn_only {
+                continue;
+            }
+            commands.entity(entity).remove::<KeptAtCheckpoint>();
+        }
+        shard.collected = false;
         *visibility = Visibility::Visible;
     }
 }
+
+/// Filter for shards that were not collected before the last checkpoint.
+pub type UnkeptShardFilter = (With<CrystalShard>, Without<KeptAtCheckpoint>);
+
+/// [`System`] that keeps the shards collected so far, and the colors they granted, when the player
+/// reaches a checkpoint.
+pub fn keep_shards_at_checkpoint(
+    mut commands: Commands,
+    q_shards: Query<(Entity, &Visibility), UnkeptShardFilter>,
+    mut shard_mods: ResMut<CrystalShardMods>,
+) {
+    for (entity, visibility) in q_shards.iter() {
+        if visibility == Visibility::Hidden {
+            commands.entity(entity).insert(KeptAtCheckpoint);
+        }
+    }
+    // the colors are no longer temporary, so they aren't taken away on death
+    for (_, is_temporary) in shard_mods.0.iter_mut() {
+        *is_temporary = false;
+    }
+}
+
 pub fn reset_shard_effects_cache(mut shard_mods: ResMut<CrystalShardMods>) {
     for (_, is_temporary) in shard_mods.0.iter_mut() {
         *is_temporary = false;
@@ -180,32 +337,151 @@ pub fn reset_shard_effects_on_kill(
     }
 }
 
+/// How a shard the player touches gets collected.
+#[derive(Debug, PartialEq, Eq)]
+enum ShardPickup {
+    Animated,
+    Quiet,
+    Skipped,
+}
+
+fn shard_pickup(
+    shard_mods: &CrystalShardMods,
+    color: LightColor,
+    behavior: DuplicateShardBehavior,
+) -> ShardPickup {
+    // the color is only marked once a shard grants it, so this is a duplicate
+    if !shard_mods.0[color] {
+        return ShardPickup::Animated;
+    }
+    match behavior {
+        DuplicateShardBehavior::Collect => ShardPickup::Animated,
+        DuplicateShardBehavior::CollectQuietly => ShardPickup::Quiet,
+        DuplicateShardBehavior::Ignore => ShardPickup::Skipped,
+    }
+}
+
+/// Whether shards can be collected in a tick the player was killed in.
+fn collect_while_killed(overlap: HurtShardOverlap) -> bool {
+    match overlap {
+        HurtShardOverlap::KillFirst => false,
+        // start_kill_animation then sees the shard animation starting, and holds back the kill
+        HurtShardOverlap::CollectFirst => true,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn on_player_intersect_shard(
-    q_shards: Query<(Entity, &CrystalShard, &Visibility)>,
+    mut commands: Commands,
+    mut q_shards: Query<(Entity, &mut CrystalShard)>,
     mut q_player: Query<Entity, With<PlayerHurtMarker>>,
+    q_inventory: Query<&PlayerLightInventory, With<PlayerMarker>>,
     rapier_context: Query<&RapierContext>,
     current_level: Res<CurrentLevel>,
     mut shard_mods: ResMut<CrystalShardMods>,
-    mut ev_shard_animation: EventWriter<ShardAnimationEvent>,
+    mut ev_shard_collected: EventWriter<ShardCollectedEvent>,
+    mut ev_kill_player: EventReader<KillPlayerEvent>,
+    config: Res<Config>,
+    mut save_data: ResMut<SaveData>,
 ) {
+    let killed = ev_kill_player.read().count() > 0;
+    if killed && !collect_while_killed(config.level_config.hurt_shard_overlap) {
+        return;
+    }
     let Ok(rapier_context) = rapier_context.get_single() else {
         return;
     };
     let Ok(player_entity) = q_player.get_single_mut() else {
         return;
     };
-    for (shard_entity, shard, shard_visibility) in q_shards.iter() {
-        // FIXME: we hide shards once we collect them, so we don't try to collect them again if
-        // they are hidden
-        if shard_visibility == Visibility::Hidden {
+    let current_color = q_inventory
+        .get_single()
+        .ok()
+        .and_then(|inventory| inventory.current_color);
+    for (shard_entity, mut shard) in q_shards.iter_mut() {
+        // the shard stays visible until its cutscene finishes, but it's only collected once
+        if shard.collected {
             continue;
         }
-        if let Some(true) = rapier_context.intersection_pair(player_entity, shard_entity) {
-            ev_shard_animation.send(ShardAnimationEvent((shard_entity, shard.light_color)));
-            if !current_level.allowed_colors[shard.light_color] {
-                // only mark as temporary modification if not actually allowed
-                shard_mods.0[shard.light_color] = true;
+        // shards requiring another color stay visible, so they can be collected later
+        if rapier_context.intersection_pair(player_entity, shard_entity) != Some(true)
+            || !shard.collectable_with(current_color)
+        {
+            continue;
+        }
+        let behavior = config.level_config.duplicate_shard;
+        let pickup = shard_pickup(&shard_mods, shard.light_color, behavior);
+        if pickup != ShardPickup::Skipped {
+            let quiet = pickup == ShardPickup::Quiet;
+            collect_shard(shard_entity, &mut shard, quiet, &mut ev_shard_collected);
+        }
+        match pickup {
+            ShardPickup::Animated => {
+                if !shard.permanent {
+                    shard_mods.record_collected(shard.light_color, &current_level);
+                } else if !save_data.permanent_colors.contains(&shard.light_color) {
+                    save_data.permanent_colors.insert(shard.light_color);
+                }
             }
+            ShardPickup::Quiet => {
+                commands.entity(shard_entity).insert(Visibility::Hidden);
+            }
+            ShardPickup::Skipped => {}
+        }
+    }
+}
+
+/// [`Event`] sent once when the player collects a [`CrystalShard`], before any cutscene plays. Hook
+/// into this to react to shards being collected.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ShardCollectedEvent {
+    pub shard: Entity,
+    pub light_color: LightColor,
+    /// Whether the shard was collected without its cutscene, like duplicate shards with
+    /// [`DuplicateShardBehavior::CollectQuietly`] and shards collected with the debug key
+    pub quiet: bool,
+}
+
+/// Marks `shard` as collected and sends its [`ShardCollectedEvent`]. Every way of collecting a
+/// shard goes through this, so the systems reacting to the event see all of them.
+pub fn collect_shard(
+    shard_entity: Entity,
+    shard: &mut CrystalShard,
+    quiet: bool,
+    ev_shard_collected: &mut EventWriter<ShardCollectedEvent>,
+) {
+    shard.collected = true;
+    ev_shard_collected.send(ShardCollectedEvent {
+        shard: shard_entity,
+        light_color: shard.light_color,
+        quiet,
+    });
+}
+
+/// [`System`] that starts the shard cutscene for each collected shard, zooming in on the player
+/// with a sound and the shard's light flying into them. With cutscenes turned off in the config,
+/// the shard's color is granted right away instead, so the player never stops.
+pub fn play_shard_cutscene(
+    mut commands: Commands,
+    mut ev_shard_collected: EventReader<ShardCollectedEvent>,
+    mut ev_shard_animation: EventWriter<ShardAnimationEvent>,
+    mut q_inventory: Query<&mut PlayerLightInventory, With<PlayerMarker>>,
+    mut current_level: ResMut<CurrentLevel>,
+    config: Res<Config>,
+) {
+    for ev in ev_shard_collected.read() {
+        if ev.quiet {
+            continue;
+        }
+        if config.level_config.shard_cutscene {
+            ev_shard_animation.send(ShardAnimationEvent((ev.shard, ev.light_color)));
+            continue;
+        }
+        // what the cutscene does once the dialogue is read
+        commands.entity(ev.shard).insert(Visibility::Hidden);
+        current_level.allowed_colors[ev.light_color] = true;
+        if let Ok(mut inventory) = q_inventory.get_single_mut() {
+            inventory.current_color = Some(ev.light_color);
         }
     }
 }
@@ -213,6 +489,137 @@ pub fn on_player_intersect_shard(
 #[derive(Event)]
 pub struct ShardAnimationEvent((Entity, LightColor));
 
+const SHARD_ABSORB_DURATION: Duration = Duration::from_millis(400);
+
+/// [`Component`] for the streak of light that flies from a collected [`CrystalShard`] into the
+/// player, showing where their new color came from.
+#[derive(Component)]
+pub struct ShardAbsorb {
+    start: Vec2,
+    timer: Timer,
+}
+
+pub fn spawn_shard_absorb(
+    mut commands: Commands,
+    mut ev_shard_animation: EventReader<ShardAnimationEvent>,
+    q_shards: Query<&GlobalTransform, With<CrystalShard>>,
+) {
+    for ShardAnimationEvent((shard_entity, light_color)) in ev_shard_animation.read() {
+        let Ok(shard_transform) = q_shards.get(*shard_entity) else {
+            continue;
+        };
+        let start = shard_transform.translation();
+        commands
+            .spawn((
+                ShardAbsorb {
+                    start: start.xy(),
+                    timer: Timer::new(SHARD_ABSORB_DURATION, TimerMode::Once),
+                },
+                Sprite {
+                    color: light_color.light_beam_color(),
+                    custom_size: Some(Vec2::splat(3.)),
+                    ..default()
+                },
+                Transform::from_translation(start),
+                HIGHRES_LAYER,
+            ))
+            .with_child(LineLight2d::point(
+                light_color.lighting_color().extend(1.0),
+                20.0,
+                0.01,
+            ));
+    }
+}
+
+/// [`System`] that moves each [`ShardAbsorb`] toward the player, despawning it once it arrives.
+pub fn update_shard_absorb(
+    mut commands: Commands,
+    mut q_absorb: Query<(Entity, &mut ShardAbsorb, &mut Transform)>,
+    q_player: Query<&GlobalTransform, With<PlayerMarker>>,
+    time: Res<Time>,
+) {
+    let Ok(player_transform) = q_player.get_single() else {
+        return;
+    };
+    let target = player_transform.translation().xy();
+    for (entity, mut absorb, mut transform) in q_absorb.iter_mut() {
+        if absorb.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let t = EasingCurve::new(0.0, 1.0, EaseFunction::SineIn)
+            .sample_clamped(absorb.timer.fraction());
+        transform.translation = absorb.start.lerp(target, t).extend(transform.translation.z);
+    }
+}
+
+const SHARD_FLASH_DURATION: Duration = Duration::from_millis(200);
+
+/// [`Component`] for the full screen overlay that flashes in the color of a collected
+/// [`CrystalShard`]. There is only ever one, so collecting shards in quick succession restarts the
+/// flash instead of stacking overlays.
+#[derive(Component)]
+pub struct ShardFlash {
+    color: LightColor,
+    timer: Timer,
+}
+
+pub fn start_shard_flash(
+    mut commands: Commands,
+    mut ev_shard_animation: EventReader<ShardAnimationEvent>,
+    mut q_flash: Query<&mut ShardFlash>,
+    config: Res<Config>,
+) {
+    let Some(ShardAnimationEvent((_, light_color))) = ev_shard_animation.read().last() else {
+        return;
+    };
+    if config.camera_config.shard_flash_intensity <= 0.0 || config.assist_config.reduce_flashing {
+        return;
+    }
+    let flash = ShardFlash {
+        color: *light_color,
+        timer: Timer::new(SHARD_FLASH_DURATION, TimerMode::Once),
+    };
+    match q_flash.get_single_mut() {
+        Ok(mut existing) => *existing = flash,
+        Err(_) => {
+            commands.spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    ..default()
+                },
+                BackgroundColor(Color::NONE),
+                // above the rest of the ui, but it never blocks it for more than a moment
+                GlobalZIndex(i32::MAX),
+                flash,
+            ));
+        }
+    }
+}
+
+/// [`System`] that fades the [`ShardFlash`] out, despawning it once it is gone.
+pub fn update_shard_flash(
+    mut commands: Commands,
+    mut q_flash: Query<(Entity, &mut ShardFlash, &mut BackgroundColor)>,
+    config: Res<Config>,
+    palette: Res<ColorPalette>,
+    // real time, since the shard animation freezes the game right away
+    time: Res<Time<Real>>,
+) {
+    for (entity, mut flash, mut background) in q_flash.iter_mut() {
+        if flash.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let intensity = config.camera_config.shard_flash_intensity.clamp(0.0, 1.0);
+        let alpha = EasingCurve::new(intensity, 0.0, EaseFunction::QuadraticOut)
+            .sample_clamped(flash.timer.fraction());
+        *background = BackgroundColor(palette.indicator_color(flash.color).with_alpha(alpha));
+    }
+}
+
 #[derive(Resource)]
 pub struct ShardAnimationCallbacks {
     for_shard: Option<(Entity, LightColor)>,
@@ -252,6 +659,7 @@ pub fn start_shard_animation(
         (&AudioSink, Entity, Option<&FadeSettings>),
         (With<BgmMarker>, Without<PlayerMarker>),
     >,
+    audio_settings: Res<AudioSettings>,
 ) {
     if ev_shard_animation.is_empty() {
         return;
@@ -267,10 +675,9 @@ pub fn start_shard_animation(
     };
     commands.entity(player_entity).insert(InputLocked);
 
-    commands.entity(player_entity).with_child((
-        AudioPlayer::new(asset_server.load("sfx/shard_acquire.wav")),
-        PlaybackSettings::DESPAWN,
-    ));
+    if let Some(sound) = audio_settings.sfx(asset_server.load("sfx/shard_acquire.wav")) {
+        commands.entity(player_entity).with_child(sound);
+    }
 
     for (sink, bgm, fade_settings) in q_bgm.iter() {
         // FIXME: If the entity has FadeSettings::Despawn fade just let it despawn
@@ -500,3 +907,278 @@ pub fn on_shard_zoom_back_finish(
     next_game_state.set(GameState::Playing);
     commands.entity(player_entity).remove::<InputLocked>();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        level::{entity::HurtMarker, start_flag::StartFlag},
+        player::movement::PlayerMovement,
+    };
+
+    fn collect_green_shard(app: &mut App) -> Entity {
+        let shard = app
+            .world_mut()
+            .spawn((
+                CrystalShard {
+                    light_color: LightColor::Green,
+                    permanent: false,
+                    required_color: None,
+                    collected: true,
+                },
+                Visibility::Hidden,
+            ))
+            .id();
+        let world = app.world_mut();
+        let mut current_level = world.resource_mut::<CurrentLevel>();
+        let mut shard_mods = CrystalShardMods::default();
+        shard_mods.record_collected(LightColor::Green, &current_level);
+        current_level.allowed_colors[LightColor::Green] = true;
+        world.insert_resource(shard_mods);
+        shard
+    }
+
+    fn shard_reset_app() -> App {
+        let mut app = App::new();
+        app.add_event::<ResetLevel>()
+            .add_event::<CheckpointActivated>()
+            .init_resource::<CurrentLevel>()
+            .init_resource::<CrystalShardMods>()
+            .add_systems(
+                Update,
+                (
+                    keep_shards_at_checkpoint.run_if(on_event::<CheckpointActivated>),
+                    (
+                        reset_shards,
+                        (reset_shard_effects_on_kill, reset_shard_effects_cache).chain(),
+                    )
+                        .run_if(on_event::<ResetLevel>),
+                )
+                    .chain(),
+            );
+        app
+    }
+
+    #[test]
+    fn shards_reappear_on_death() {
+        let mut app = shard_reset_app();
+        let shard = collect_green_shard(&mut app);
+
+        app.world_mut().send_event(ResetLevel::Respawn);
+        app.update();
+
+        let world = app.world();
+        assert_eq!(world.get::<Visibility>(shard), Some(&Visibility::Visible));
+        assert!(!world.get::<CrystalShard>(shard).unwrap().collected);
+        assert!(!world.resource::<CurrentLevel>().allowed_colors[LightColor::Green]);
+    }
+
+    #[test]
+    fn shards_kept_at_checkpoint() {
+        let mut app = shard_reset_app();
+        let shard = collect_green_shard(&mut app);
+        app.world_mut().send_event(CheckpointActivated);
+        app.update();
+
+        app.world_mut().send_event(ResetLevel::Respawn);
+        app.update();
+        let world = app.world();
+        assert_eq!(world.get::<Visibility>(shard), Some(&Visibility::Hidden));
+        assert!(world.get::<CrystalShard>(shard).unwrap().collected);
+        assert!(world.resource::<CurrentLevel>().allowed_colors[LightColor::Green]);
+
+        // leaving the level resets every shard
+        app.world_mut().send_event(ResetLevel::Switching);
+        app.update();
+        assert_eq!(
+            app.world().get::<Visibility>(shard),
+            Some(&Visibility::Visible)
+        );
+    }
+
+    #[test]
+    fn shard_requires_held_color() {
+        let mut app = crate::headless::headless_app();
+        let shard = app
+            .world_mut()
+            .spawn((
+                CrystalShard {
+                    light_color: LightColor::Green,
+                    permanent: false,
+                    required_color: Some(LightColor::Purple),
+                    collected: false,
+                },
+                Collider::cuboid(4.0, 4.0),
+                Sensor,
+                Visibility::Visible,
+                Transform::default(),
+            ))
+            .id();
+        let mut inventory = PlayerLightInventory::new();
+        inventory.current_color = Some(LightColor::Blue);
+        let player = app
+            .world_mut()
+            .spawn((
+                PlayerMarker,
+                PlayerHurtMarker,
+                inventory,
+                // like the hurt box added by add_player_sensors
+                Collider::cuboid(4.0, 5.0),
+                Sensor,
+                RigidBody::Dynamic,
+                GravityScale(0.0),
+                Transform::default(),
+            ))
+            .id();
+        let collected = |app: &App| app.world().resource::<CrystalShardMods>().0[LightColor::Green];
+
+        for _ in 0..3 {
+            app.update();
+        }
+        assert!(!collected(&app));
+        assert_eq!(
+            app.world().get::<Visibility>(shard),
+            Some(&Visibility::Visible)
+        );
+
+        app.world_mut()
+            .get_mut::<PlayerLightInventory>(player)
+            .unwrap()
+            .current_color = Some(LightColor::Purple);
+        let mut cursor = app
+            .world()
+            .resource::<Events<ShardCollectedEvent>>()
+            .get_cursor();
+        let mut collected_events = 0;
+        // the player is still touching the shard while the cutscene starts
+        for _ in 0..3 {
+            app.update();
+            let events = app.world().resource::<Events<ShardCollectedEvent>>();
+            collected_events += cursor.read(events).count();
+        }
+        assert!(collected(&app));
+        assert_eq!(collected_events, 1);
+    }
+
+    /// Touches a shard placed inside a hurt box for a few ticks with `overlap`, returning whether
+    /// the player was killed and whether the shard was collected.
+    fn touch_shard_inside_hurt_box(overlap: HurtShardOverlap) -> (bool, bool) {
+        let mut app = crate::headless::headless_app();
+        {
+            let mut config = app.world_mut().resource_mut::<Config>();
+            config.level_config.hurt_shard_overlap = overlap;
+            // so the kill lands in the same tick the shard is touched
+            config.gameplay_config.hurt_grace_ticks = 0;
+        }
+        let level_iid = LevelIid::new("level");
+        app.world_mut().resource_mut::<CurrentLevel>().level_iid = level_iid.clone();
+        app.world_mut().spawn((
+            StartFlag { level_iid },
+            EntityInstance {
+                world_x: Some(400),
+                world_y: Some(400),
+                ..default()
+            },
+        ));
+        let shard = app
+            .world_mut()
+            .spawn((
+                CrystalShard {
+                    light_color: LightColor::Green,
+                    permanent: false,
+                    required_color: None,
+                    collected: false,
+                },
+                Collider::cuboid(4.0, 4.0),
+                Sensor,
+                Visibility::Visible,
+                Transform::default(),
+            ))
+            .id();
+        app.world_mut().spawn((
+            HurtMarker,
+            Collider::cuboid(8.0, 8.0),
+            Sensor,
+            Transform::default(),
+        ));
+        app.world_mut().spawn((
+            PlayerMarker,
+            PlayerHurtMarker,
+            PlayerLightInventory::new(),
+            PlayerMovement::default(),
+            KinematicCharacterController::default(),
+            Sprite::default(),
+            Collider::cuboid(4.0, 5.0),
+            Sensor,
+            RigidBody::Dynamic,
+            GravityScale(0.0),
+            Transform::default(),
+        ));
+
+        let mut cursor = app
+            .world()
+            .resource::<Events<KillPlayerEvent>>()
+            .get_cursor();
+        let mut killed = false;
+        for _ in 0..3 {
+            app.update();
+            let events = app.world().resource::<Events<KillPlayerEvent>>();
+            killed |= cursor.read(events).count() > 0;
+        }
+        let collected = app.world().get::<CrystalShard>(shard).unwrap().collected;
+        (killed, collected)
+    }
+
+    #[test]
+    fn shard_inside_hurt_box() {
+        assert_eq!(
+            touch_shard_inside_hurt_box(HurtShardOverlap::KillFirst),
+            (true, false)
+        );
+        assert_eq!(
+            touch_shard_inside_hurt_box(HurtShardOverlap::CollectFirst),
+            (true, true)
+        );
+    }
+
+    #[test]
+    fn duplicate_color_shards() {
+        let mut shard_mods = CrystalShardMods::default();
+        let current_level = CurrentLevel::default();
+        let pickup = |shard_mods: &CrystalShardMods, behavior| {
+            shard_pickup(shard_mods, LightColor::Green, behavior)
+        };
+
+        // the first green shard is always collected normally
+        for behavior in [
+            DuplicateShardBehavior::Collect,
+            DuplicateShardBehavior::CollectQuietly,
+            DuplicateShardBehavior::Ignore,
+        ] {
+            assert_eq!(pickup(&shard_mods, behavior), ShardPickup::Animated);
+        }
+        shard_mods.record_collected(LightColor::Green, &current_level);
+
+        assert_eq!(
+            pickup(&shard_mods, DuplicateShardBehavior::Collect),
+            ShardPickup::Animated
+        );
+        assert_eq!(
+            pickup(&shard_mods, DuplicateShardBehavior::CollectQuietly),
+            ShardPickup::Quiet
+        );
+        assert_eq!(
+            pickup(&shard_mods, DuplicateShardBehavior::Ignore),
+            ShardPickup::Skipped
+        );
+        // other colors aren't affected
+        assert_eq!(
+            shard_pickup(
+                &shard_mods,
+                LightColor::Blue,
+                DuplicateShardBehavior::Ignore
+            ),
+            ShardPickup::Animated
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{ldtk::Type, prelude::*};
+
+use crate::{save::SaveData, shared::GameState};
+
+use super::{get_ldtk_level_data, shard::CrystalShard, CurrentLevel};
+
+const CRYSTAL_SHARD_IDENT: &str = "CrystalShard";
+
+pub struct ShardCounterPlugin;
+
+impl Plugin for ShardCounterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShardCounter>().add_systems(
+            Update,
+            (
+                init_shard_totals,
+                record_collected_shards,
+                update_shard_counter_ui,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// [`Resource`] that tracks the [`CrystalShard`]s the player has collected, both in the current
+/// level and across the whole game.
+#[derive(Default, Resource)]
+pub struct ShardCounter {
+    pub enabled: bool,
+    /// The iids of every shard in each level, read from the Ldtk project once it loads
+    level_shards: HashMap<LevelIid, Vec<EntityIid>>,
+    /// The iids of every shard the player has collected at least once
+    pub collected: HashSet<EntityIid>,
+}
+
+impl ShardCounter {
+    /// The number of shards in the level with the given `level_iid`.
+    pub fn level_total(&self, level_iid: &LevelIid) -> usize {
+        self.level_shards.get(level_iid).map_or(0, Vec::len)
+    }
+
+    /// The number of shards in the whole game.
+    pub fn game_total(&self) -> usize {
+        self.level_shards.values().map(Vec::len).sum()
+    }
+
+    /// The number of shards in the whole game that have been collected at least once.
+    pub fn game_collected(&self) -> usize {
+        self.collected.len()
+    }
+
+    /// Replaces the collected shards with the ones recorded in `save_data`, when switching saves.
+    pub fn restore_collected(&mut self, save_data: &SaveData) {
+        self.collected = save_data
+            .collected_shards
+            .iter()
+            .map(|iid| EntityIid::new(iid.clone()))
+            .collect();
+    }
+
+    /// Iterates over the iids of the shards in the level with the given `level_iid`.
+    pub fn iter_level_shards<'a>(
+        &'a self,
+        level_iid: &LevelIid,
+    ) -> impl Iterator<Item = &'a EntityIid> + 'a {
+        self.level_shards.get(level_iid).into_iter().flatten()
+    }
+}
+
+/// [`System`] that counts the shards in every level from the Ldtk project data, so totals are
+/// known even for levels that have not been spawned yet.
+pub fn init_shard_totals(
+    mut shard_counter: ResMut<ShardCounter>,
+    ldtk_projects: Query<&LdtkProjectHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    if !shard_counter.level_shards.is_empty() {
+        return;
+    }
+    let Ok(ldtk_handle) = ldtk_projects.get_single() else {
+        return;
+    };
+    let Ok(ldtk_levels) = get_ldtk_level_data(ldtk_project_assets.into_inner(), ldtk_handle) else {
+        return;
+    };
+
+    for level in ldtk_levels {
+        // FIXME: ignore all levels prefixed with ., same as the level select
+        let level_id = level
+            .get_string_field("LevelId")
+            .expect("Levels should always have a level id!");
+        if level_id.starts_with('.') {
+            continue;
+        }
+        let Some(layers) = level.layer_instances.as_ref() else {
+            continue;
+        };
+        let shards = layers
+            .iter()
+            .filter(|layer| layer.layer_instance_type == Type::Entities)
+            .flat_map(|layer| layer.entity_instances.iter())
+            .filter(|entity| entity.identifier == CRYSTAL_SHARD_IDENT)
+            .map(|entity| EntityIid::new(entity.iid.clone()))
+            .collect();
+        shard_counter
+            .level_shards
+            .insert(LevelIid::new(level.iid.clone()), shards);
+    }
+}
+
+/// Filter for shards whose [`Visibility`] just changed.
+pub type ChangedShardFilter = (With<CrystalShard>, Changed<Visibility>);
+
+/// [`System`] that records shards as collected once they are hidden after the shard animation.
+pub fn record_collected_shards(
+    mut shard_counter: ResMut<ShardCounter>,
+    mut save_data: ResMut<SaveData>,
+    q_shards: Query<(&EntityIid, &Visibility), ChangedShardFilter>,
+) {
+    for (iid, visibility) in q_shards.iter() {
+        if *visibility != Visibility::Hidden {
+            continue;
+        }
+        shard_counter.collected.insert(iid.clone());
+        // checked first so the save isn't rewritten for shards that were already collected
+        if !save_data.collected_shards.contains(iid.as_str()) {
+            save_data.collected_shards.insert(iid.as_str().to_string());
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct ShardCounterUi;
+
+pub type ShardCounterUiQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static mut Text, &'static mut Visibility),
+    (With<ShardCounterUi>, Without<CrystalShard>),
+>;
+
+/// [`System`] that displays the collected shards of the current level and of the whole game. The
+/// counter is hidden in levels without any shards.
+pub fn update_shard_counter_ui(
+    mut commands: Commands,
+    shard_counter: Res<ShardCounter>,
+    current_level: Res<CurrentLevel>,
+    q_shards: Query<(&EntityIid, &Visibility), With<CrystalShard>>,
+    mut q_shard_counter_ui: ShardCounterUiQuery,
+    game_state: Res<State<GameState>>,
+    asset_server: Res<AssetServer>,
+) {
+    let level_total = shard_counter.level_total(&current_level.level_iid);
+    let show = shard_counter.enabled && level_total > 0 && *game_state != GameState::Ui;
+
+    let Ok((mut text, mut visibility)) = q_shard_counter_ui.get_single_mut() else {
+        if show {
+            let font = TextFont {
+                font: asset_server.load("fonts/Outfit-Medium.ttf"),
+                ..default()
+            };
+            commands.spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(32.),
+                    bottom: Val::Px(32.),
+                    ..default()
+                },
+                Text::new(""),
+                font.with_font_size(24.),
+                ShardCounterUi,
+            ));
+        }
+        return;
+    };
+
+    if !show {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    *visibility = Visibility::Visible;
+
+    let level_shards = shard_counter
+        .iter_level_shards(&current_level.level_iid)
+        .collect::<HashSet<_>>();
+    let level_collected = q_shards
+        .iter()
+        .filter(|(iid, visibility)| {
+            level_shards.contains(iid) && **visibility == Visibility::Hidden
+        })
+        .count();
+
+    let game_total = shard_counter.game_total();
+    let game_collected = shard_counter.game_collected();
+    let game_percent = game_collected as f32 / game_total as f32 * 100.;
+
+    *text = Text::new(format!(
+        "Shards: {level_collected} / {level_total}\nTotal: {game_collected} / {game_total} ({game_percent:.0}%)"
+    ));
+}
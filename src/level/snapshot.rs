@@ -0,0 +1,385 @@
+use std::{collections::HashMap, time::Duration};
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use enum_map::EnumMap;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    light::LightColor,
+    player::{light::PlayerLightInventory, movement::PlayerMovement, PlayerMarker},
+};
+
+use super::{
+    crystal::{restore_crystals, Crystal, CrystalGroup, CrystalIdent},
+    platform::{MovingPlatform, PlatformState},
+    sensor::LightSensor,
+    shard::{CrystalShard, CrystalShardMods},
+    CurrentLevel,
+};
+
+/// The state of a [`MovingPlatform`] needed to put it back where it was.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlatformSnapshot {
+    pub translation: Vec3,
+    pub curr_state: PlatformState,
+    pub curr_segment: IVec2,
+    pub previous_segment: IVec2,
+    pub curr_segment_index: i32,
+    pub has_activated: bool,
+    pub arc_time: f32,
+    pub current_position: Vec2,
+}
+
+impl From<(&MovingPlatform, &Transform)> for PlatformSnapshot {
+    fn from((platform, transform): (&MovingPlatform, &Transform)) -> Self {
+        PlatformSnapshot {
+            translation: transform.translation,
+            curr_state: platform.curr_state,
+            curr_segment: platform.curr_segment,
+            previous_segment: platform.previous_segment,
+            curr_segment_index: platform.curr_segment_index,
+            has_activated: platform.has_activated,
+            arc_time: platform.arc_time,
+            current_position: platform.current_position,
+        }
+    }
+}
+
+/// The state of a [`LightSensor`] needed to put it back where it was.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SensorSnapshot {
+    pub meter: f32,
+    pub is_active: bool,
+    pub exposure_secs: f32,
+}
+
+impl From<&LightSensor> for SensorSnapshot {
+    fn from(sensor: &LightSensor) -> Self {
+        SensorSnapshot {
+            meter: sensor.meter,
+            is_active: sensor.is_active,
+            exposure_secs: sensor.cumulative_exposure.elapsed_secs(),
+        }
+    }
+}
+
+/// A copy of the state of the current level, which can be restored later to put the player back
+/// into the exact same situation. Useful for setting up puzzle scenarios in tests, and for
+/// quicksaving.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(into = "SnapshotData", from = "SnapshotData")]
+pub struct LevelSnapshot {
+    pub level_iid: LevelIid,
+    pub player_translation: Vec3,
+    pub player_velocity: Vec2,
+    pub current_color: Option<LightColor>,
+    pub allowed_colors: EnumMap<LightColor, bool>,
+    pub shard_mods: EnumMap<LightColor, bool>,
+    /// Iids of the shards that have been collected in the level
+    pub collected_shards: Vec<EntityIid>,
+    pub platforms: Vec<(EntityIid, PlatformSnapshot)>,
+    /// Crystals that have been toggled away from their initial state. All crystals with the same
+    /// ident toggle together, so this is enough to restore every crystal.
+    pub toggled_crystals: Vec<CrystalIdent>,
+    pub sensors: Vec<(EntityIid, SensorSnapshot)>,
+    /// The respawn point set by the last checkpoint touched
+    pub respawn_override: Option<Vec2>,
+}
+
+/// Serialized form of a [`LevelSnapshot`], since iids and [`EnumMap`]s can't be serialized
+/// directly.
+#[derive(Serialize, Deserialize)]
+struct SnapshotData {
+    level_iid: String,
+    current_color: Option<LightColor>,
+    allowed_colors: Vec<LightColor>,
+    shard_mods: Vec<LightColor>,
+    collected_shards: Vec<String>,
+    player_translation: Vec3,
+    player_velocity: Vec2,
+    platforms: HashMap<String, PlatformSnapshot>,
+    // defaulted so quicksaves from before these were captured still load
+    #[serde(default)]
+    toggled_crystals: Vec<CrystalIdent>,
+    #[serde(default)]
+    sensors: HashMap<String, SensorSnapshot>,
+    #[serde(default)]
+    respawn_override: Option<Vec2>,
+}
+
+fn colors_to_vec(colors: EnumMap<LightColor, bool>) -> Vec<LightColor> {
+    colors
+        .into_iter()
+        .filter_map(|(color, set)| set.then_some(color))
+        .collect()
+}
+
+impl From<LevelSnapshot> for SnapshotData {
+    fn from(snapshot: LevelSnapshot) -> Self {
+        SnapshotData {
+            level_iid: snapshot.level_iid.into(),
+            current_color: snapshot.current_color,
+            allowed_colors: colors_to_vec(snapshot.allowed_colors),
+            shard_mods: colors_to_vec(snapshot.shard_mods),
+            collected_shards: snapshot
+                .collected_shards
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            player_translation: snapshot.player_translation,
+            player_velocity: snapshot.player_velocity,
+            platforms: snapshot
+                .platforms
+                .into_iter()
+                .map(|(iid, platform)| (String::from(iid), platform))
+                .collect(),
+            toggled_crystals: snapshot.toggled_crystals,
+            sensors: snapshot
+                .sensors
+                .into_iter()
+                .map(|(iid, sensor)| (String::from(iid), sensor))
+                .collect(),
+            respawn_override: snapshot.respawn_override,
+        }
+    }
+}
+
+impl From<SnapshotData> for LevelSnapshot {
+    fn from(data: SnapshotData) -> Self {
+        LevelSnapshot {
+            level_iid: LevelIid::new(data.level_iid),
+            player_translation: data.player_translation,
+            player_velocity: data.player_velocity,
+            current_color: data.current_color,
+            allowed_colors: EnumMap::from_fn(|color| data.allowed_colors.contains(&color)),
+            shard_mods: EnumMap::from_fn(|color| data.shard_mods.contains(&color)),
+            collected_shards: data
+                .collected_shards
+                .into_iter()
+                .map(EntityIid::new)
+                .collect(),
+            platforms: data
+                .platforms
+                .into_iter()
+                .map(|(iid, platform)| (EntityIid::new(iid), platform))
+                .collect(),
+            toggled_crystals: data.toggled_crystals,
+            sensors: data
+                .sensors
+                .into_iter()
+                .map(|(iid, sensor)| (EntityIid::new(iid), sensor))
+                .collect(),
+            respawn_override: data.respawn_override,
+        }
+    }
+}
+
+impl LevelSnapshot {
+    /// Captures the current level's state. Returns [`None`] if there is no player to snapshot.
+    pub fn capture(world: &mut World) -> Option<Self> {
+        let (player_transform, movement, inventory) = world
+            .query_filtered::<(&Transform, &PlayerMovement, &PlayerLightInventory), With<PlayerMarker>>()
+            .get_single(world)
+            .ok()?;
+        let player_translation = player_transform.translation;
+        let player_velocity = movement.velocity;
+        let current_color = inventory.current_color;
+
+        let collected_shards = world
+            .query::<(&EntityIid, &CrystalShard)>()
+            .iter(world)
+            .filter(|(_, shard)| shard.collected)
+            .map(|(iid, _)| iid.clone())
+            .collect();
+        let platforms = world
+            .query::<(&EntityIid, &MovingPlatform, &Transform)>()
+            .iter(world)
+            .map(|(iid, platform, transform)| (iid.clone(), (platform, transform).into()))
+            .collect();
+        let mut q_crystals = world.query::<&Crystal>();
+        let mut q_crystal_groups = world.query::<&CrystalGroup>();
+        let toggled_crystals = q_crystals
+            .iter(world)
+            .chain(
+                q_crystal_groups
+                    .iter(world)
+                    .map(|group| &group.representative),
+            )
+            .filter(|crystal| crystal.is_toggled())
+            .map(|crystal| crystal.ident)
+            .unique()
+            .collect();
+        let sensors = world
+            .query::<(&EntityIid, &LightSensor)>()
+            .iter(world)
+            .map(|(iid, sensor)| (iid.clone(), sensor.into()))
+            .collect();
+
+        let current_level = world.resource::<CurrentLevel>();
+        Some(LevelSnapshot {
+            level_iid: current_level.level_iid.clone(),
+            player_translation,
+            player_velocity,
+            current_color,
+            allowed_colors: current_level.allowed_colors,
+            shard_mods: world.resource::<CrystalShardMods>().0,
+            collected_shards,
+            platforms,
+            toggled_crystals,
+            sensors,
+            respawn_override: current_level.respawn_override,
+        })
+    }
+
+    /// Restores the snapshot. Snapshots can only be restored while in the level they were taken
+    /// in, otherwise nothing happens.
+    pub fn restore(&self, world: &mut World) {
+        if world.resource::<CurrentLevel>().level_iid != self.level_iid {
+            warn!("Tried to restore a snapshot of another level");
+            return;
+        }
+
+        let mut current_level = world.resource_mut::<CurrentLevel>();
+        current_level.allowed_colors = self.allowed_colors;
+        current_level.respawn_override = self.respawn_override;
+        world.resource_mut::<CrystalShardMods>().0 = self.shard_mods;
+
+        let mut q_player = world.query_filtered::<(
+            &mut Transform,
+            &mut PlayerMovement,
+            &mut PlayerLightInventory,
+        ), With<PlayerMarker>>();
+        if let Ok((mut transform, mut movement, mut inventory)) = q_player.get_single_mut(world) {
+            transform.translation = self.player_translation;
+            movement.velocity = self.player_velocity;
+            inventory.current_color = self.current_color;
+        }
+
+        let mut q_shards = world.query::<(&EntityIid, &mut CrystalShard, &mut Visibility)>();
+        for (iid, mut shard, mut visibility) in q_shards.iter_mut(world) {
+            shard.collected = self.collected_shards.contains(iid);
+            *visibility = match shard.collected {
+                true => Visibility::Hidden,
+                false => Visibility::Visible,
+            };
+        }
+
+        let mut q_platforms = world.query::<(&EntityIid, &mut MovingPlatform, &mut Transform)>();
+        for (iid, mut platform, mut transform) in q_platforms.iter_mut(world) {
+            let Some((_, snapshot)) = self.platforms.iter().find(|(other, _)| other == iid) else {
+                continue;
+            };
+            transform.translation = snapshot.translation;
+            platform.curr_state = snapshot.curr_state;
+            platform.curr_segment = snapshot.curr_segment;
+            platform.previous_segment = snapshot.previous_segment;
+            platform.curr_segment_index = snapshot.curr_segment_index;
+            platform.has_activated = snapshot.has_activated;
+            platform.arc_time = snapshot.arc_time;
+            platform.current_position = snapshot.current_position;
+        }
+
+        restore_crystals(world, &self.toggled_crystals);
+
+        let mut q_sensors = world.query::<(&EntityIid, &mut LightSensor)>();
+        for (iid, mut sensor) in q_sensors.iter_mut(world) {
+            let Some((_, snapshot)) = self.sensors.iter().find(|(other, _)| other == iid) else {
+                continue;
+            };
+            sensor.meter = snapshot.meter;
+            sensor.is_active = snapshot.is_active;
+            sensor
+                .cumulative_exposure
+                .set_elapsed(Duration::from_secs_f32(snapshot.exposure_secs));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs_tilemap::tiles::TileTextureIndex;
+
+    use super::*;
+    use crate::{headless::headless_app, level::crystal::CrystalColor};
+
+    /// The parts of the level a snapshot restores that `state` reads back, to compare.
+    type LevelState = (bool, f32, bool, f32, bool, Visibility);
+
+    fn state(world: &mut World) -> LevelState {
+        let crystal = world.query::<&Crystal>().single(world).is_toggled();
+        let sensor = world.query::<&LightSensor>().single(world);
+        let sensor = (
+            sensor.meter,
+            sensor.is_active,
+            sensor.cumulative_exposure.elapsed_secs(),
+        );
+        let (shard, visibility) = world.query::<(&CrystalShard, &Visibility)>().single(world);
+        (
+            crystal,
+            sensor.0,
+            sensor.1,
+            sensor.2,
+            shard.collected,
+            *visibility,
+        )
+    }
+
+    #[test]
+    fn snapshot_round_trips_crystals_sensors_and_shards() {
+        let mut app = headless_app();
+        let world = app.world_mut();
+        world.resource_mut::<CurrentLevel>().level_iid = LevelIid::new("level");
+        world.spawn((
+            PlayerMarker,
+            PlayerMovement::default(),
+            PlayerLightInventory::default(),
+            Transform::default(),
+        ));
+        let ident = CrystalIdent {
+            color: CrystalColor::Red,
+            id: 1,
+        };
+        let mut crystal = Crystal::from(IntGridCell { value: 5 });
+        crystal.ident = ident;
+        world.spawn((crystal, TileTextureIndex(0)));
+        world.spawn((EntityIid::new("sensor"), LightSensor::new(ident, 1000, -1)));
+        world.spawn((
+            EntityIid::new("shard"),
+            CrystalShard {
+                light_color: LightColor::Green,
+                permanent: false,
+                required_color: None,
+                collected: false,
+            },
+            Visibility::Visible,
+        ));
+
+        let before = state(world);
+        let snapshot = LevelSnapshot::capture(world).unwrap();
+
+        restore_crystals(world, &[ident]);
+        let mut sensor = world.query::<&mut LightSensor>().single_mut(world);
+        sensor.meter = 0.5;
+        sensor.is_active = true;
+        sensor
+            .cumulative_exposure
+            .set_elapsed(Duration::from_secs(2));
+        let (mut shard, mut visibility) = world
+            .query::<(&mut CrystalShard, &mut Visibility)>()
+            .single_mut(world);
+        shard.collected = true;
+        *visibility = Visibility::Hidden;
+        let after = state(world);
+        assert_eq!(after, (true, 0.5, true, 2.0, true, Visibility::Hidden));
+        // goes through the same serialization as quicksaves
+        let changed = toml::to_string(&LevelSnapshot::capture(world).unwrap()).unwrap();
+        let changed: LevelSnapshot = toml::from_str(&changed).unwrap();
+
+        snapshot.restore(world);
+        assert_eq!(state(world), before);
+        changed.restore(world);
+        assert_eq!(state(world), after);
+    }
+}
@@ -1,14 +1,28 @@
+use std::{collections::HashMap, time::Duration};
+
 use bevy::{prelude::*, time::Stopwatch};
+use bevy_ecs_ldtk::LevelIid;
 use hhmmss::Hhmmss;
 
 use crate::shared::GameState;
 
+use super::{level_completion::LevelCompleteEvent, CurrentLevel};
+
 pub struct SpeedrunTimerPlugin;
 
 impl Plugin for SpeedrunTimerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, tick_speedrun_timer)
-            .init_resource::<SpeedrunTimer>();
+        app.add_systems(
+            Update,
+            (
+                tick_level_timer,
+                stop_level_timer.run_if(on_event::<LevelCompleteEvent>),
+                tick_speedrun_timer,
+            )
+                .chain(),
+        )
+        .init_resource::<SpeedrunTimer>()
+        .init_resource::<LevelTimer>();
     }
 }
 
@@ -21,10 +35,82 @@ pub struct SpeedrunTimer {
 #[derive(Component)]
 pub struct SpeedrunUi;
 
+/// [`Resource`] timing the current level, for splits. It is restarted whenever the player enters a
+/// level, and stopped when the level is completed, keeping the best time for each level. Only time
+/// spent in [`GameState::Playing`] counts, so animations and the pause menu don't.
+#[derive(Default, Resource)]
+pub struct LevelTimer {
+    level_iid: Option<LevelIid>,
+    timer: Stopwatch,
+    best: HashMap<LevelIid, Duration>,
+}
+
+impl LevelTimer {
+    /// Restarts the timer for the level with `level_iid`.
+    pub fn start(&mut self, level_iid: LevelIid) {
+        self.level_iid = Some(level_iid);
+        self.timer.reset();
+        self.timer.unpause();
+    }
+
+    /// Stops the timer if it is timing the level with `level_iid`, keeping the time if it's the
+    /// best for the level.
+    pub fn complete(&mut self, level_iid: &LevelIid) {
+        if self.level_iid.as_ref() != Some(level_iid) || self.timer.is_paused() {
+            return;
+        }
+        self.timer.pause();
+        let time = self.timer.elapsed();
+        self.best
+            .entry(level_iid.clone())
+            .and_modify(|best| *best = (*best).min(time))
+            .or_insert(time);
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.timer.elapsed()
+    }
+
+    /// The best time the level with `level_iid` was completed in this session.
+    pub fn best(&self, level_iid: &LevelIid) -> Option<Duration> {
+        self.best.get(level_iid).copied()
+    }
+
+    fn tick(&mut self, delta: Duration) {
+        if self.level_iid.is_some() {
+            self.timer.tick(delta);
+        }
+    }
+}
+
+fn tick_level_timer(
+    time: Res<Time<Real>>,
+    mut level_timer: ResMut<LevelTimer>,
+    game_state: Res<State<GameState>>,
+) {
+    if *game_state == GameState::Playing {
+        level_timer.tick(time.delta());
+    }
+}
+
+fn stop_level_timer(
+    mut ev_level_complete: EventReader<LevelCompleteEvent>,
+    mut level_timer: ResMut<LevelTimer>,
+) {
+    for event in ev_level_complete.read() {
+        level_timer.complete(&event.level_iid);
+    }
+}
+
+/// [`System`] that ticks the speedrun timer and shows it, along with the split for the current
+/// level, while it is enabled.
+#[allow(clippy::too_many_arguments)]
 pub fn tick_speedrun_timer(
     mut commands: Commands,
-    time: Res<Time>,
+    time: Res<Time<Real>>,
     mut speedrun_timer: ResMut<SpeedrunTimer>,
+    level_timer: Res<LevelTimer>,
+    current_level: Res<CurrentLevel>,
     q_speedrun_timer: Query<Entity, With<SpeedrunUi>>,
     game_state: Res<State<GameState>>,
     asset_server: Res<AssetServer>,
@@ -41,12 +127,20 @@ pub fn tick_speedrun_timer(
         ..default()
     };
 
+    let best = match level_timer.best(&current_level.level_iid) {
+        Some(best) => best.hhmmssxxx(),
+        None => "--".into(),
+    };
+    let text = format!(
+        "{}\nLevel {} (best {best})",
+        speedrun_timer.timer.elapsed().hhmmssxxx(),
+        level_timer.elapsed().hhmmssxxx()
+    );
+
     let timer = q_speedrun_timer.get_single();
     match timer {
         Ok(timer) => {
-            commands
-                .entity(timer)
-                .insert(Text::new(speedrun_timer.timer.elapsed().hhmmssxxx()));
+            commands.entity(timer).insert(Text::new(text));
         }
         Err(_) => {
             commands
@@ -56,11 +150,35 @@ pub fn tick_speedrun_timer(
                     padding: UiRect::all(Val::Px(32.)),
                     ..default()
                 })
-                .with_child((
-                    Text::new(speedrun_timer.timer.elapsed().hhmmssxxx()),
-                    SpeedrunUi,
-                    font.with_font_size(36.),
-                ));
+                .with_child((Text::new(text), SpeedrunUi, font.with_font_size(36.)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_timer_keeps_best_time() {
+        let level_iid = LevelIid::new("level");
+        let mut level_timer = LevelTimer::default();
+        // nothing is timed before entering a level
+        level_timer.tick(Duration::from_secs(5));
+        assert_eq!(level_timer.elapsed(), Duration::ZERO);
+
+        level_timer.start(level_iid.clone());
+        level_timer.tick(Duration::from_secs(20));
+        level_timer.complete(&level_iid);
+        // stopped once the level is completed
+        level_timer.tick(Duration::from_secs(5));
+        assert_eq!(level_timer.elapsed(), Duration::from_secs(20));
+
+        for secs in [30, 12] {
+            level_timer.start(level_iid.clone());
+            level_timer.tick(Duration::from_secs(secs));
+            level_timer.complete(&level_iid);
         }
+        assert_eq!(level_timer.best(&level_iid), Some(Duration::from_secs(12)));
     }
 }
@@ -1,5 +1,5 @@
 use bevy::prelude::*;
-use bevy_ecs_ldtk::prelude::*;
+use bevy_ecs_ldtk::{ldtk::Level, prelude::*};
 
 /// [`Component`] to mark start flags in the level. Used to query for when start flags are loaded
 /// by Ldtk.
@@ -14,7 +14,8 @@ pub struct StartFlag {
     pub level_iid: LevelIid,
 }
 
-/// [`Bundle`] spawned in by Ldtk corresponding to start flags.
+/// [`Bundle`] spawned in by Ldtk corresponding to start flags. The optional `FacingLeft` bool field
+/// sets which way Lyra faces when respawning at the flag.
 #[derive(Default, Bundle, LdtkEntity)]
 pub struct StartFlagBundle {
     flag: StartFlag,
@@ -46,3 +47,20 @@ pub fn init_start_marker(
         });
     }
 }
+
+/// The position of the start flag in the Ldtk `level`, straight from the level data, so it can be
+/// found before the level is spawned.
+pub fn start_flag_pos(level: &Level) -> Option<Vec2> {
+    level
+        .layer_instances
+        .iter()
+        .flatten()
+        .flat_map(|layer| layer.entity_instances.iter())
+        .find(|entity| entity.identifier == "Start")
+        .map(|entity| {
+            Vec2::new(
+                entity.world_x.expect("Lightborne uses Free world layout") as f32,
+                -entity.world_y.expect("Lightborne uses Free world layout") as f32,
+            )
+        })
+}
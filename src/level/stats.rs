@@ -0,0 +1,254 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, time::Stopwatch};
+use bevy_ecs_ldtk::prelude::*;
+use hhmmss::Hhmmss;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    save::SaveData,
+    shared::{GameState, ResetLevel},
+};
+
+use super::{
+    level_completion::{LevelCompleteEvent, LevelStartEvent},
+    shard_counter::ShardCounter,
+    CurrentLevel, LevelSystems,
+};
+
+/// How long the stats panel stays on screen after completing a level
+const STATS_PANEL_SECS: f32 = 4.0;
+/// How long the summary screen ignores input, so a key held while finishing the level doesn't
+/// dismiss it right away
+const SUMMARY_MIN_SECS: f32 = 0.5;
+
+/// [`Plugin`] that tracks deaths and time for each attempt at a level, and keeps the best of each
+/// in the [`SaveData`].
+pub struct LevelStatsPlugin;
+
+impl Plugin for LevelStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelAttempt>()
+            .add_systems(Update, count_attempt_deaths.in_set(LevelSystems::Reset))
+            .add_systems(
+                Update,
+                (
+                    start_level_attempt.run_if(on_event::<LevelStartEvent>),
+                    tick_level_attempt,
+                    record_level_stats.run_if(on_event::<LevelCompleteEvent>),
+                    despawn_level_stats_ui,
+                    dismiss_level_summary.run_if(in_state(GameState::LevelComplete)),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// The fewest deaths and fastest time the player has completed a level with. Both are tracked
+/// separately, so they don't have to come from the same attempt.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct LevelBestStats {
+    pub deaths: u32,
+    pub time_secs: f32,
+}
+
+/// [`Resource`] that tracks the attempt at the level the player is currently trying to complete.
+#[derive(Resource, Default)]
+pub struct LevelAttempt {
+    pub level_iid: Option<LevelIid>,
+    pub deaths: u32,
+    pub timer: Stopwatch,
+}
+
+#[derive(Component)]
+pub struct LevelStatsUi(Timer);
+
+/// Marker [`Component`] for the level complete summary screen, which waits for input instead of
+/// timing out.
+#[derive(Component)]
+pub struct LevelSummaryUi;
+
+fn start_level_attempt(
+    mut ev_level_start: EventReader<LevelStartEvent>,
+    mut attempt: ResMut<LevelAttempt>,
+) {
+    for event in ev_level_start.read() {
+        *attempt = LevelAttempt {
+            level_iid: Some(event.level_iid.clone()),
+            ..default()
+        };
+    }
+}
+
+pub fn count_attempt_deaths(
+    mut ev_reset_level: EventReader<ResetLevel>,
+    mut attempt: ResMut<LevelAttempt>,
+) {
+    for event in ev_reset_level.read() {
+        if *event == ResetLevel::Respawn && attempt.level_iid.is_some() {
+            attempt.deaths += 1;
+        }
+    }
+}
+
+fn tick_level_attempt(
+    mut attempt: ResMut<LevelAttempt>,
+    game_state: Res<State<GameState>>,
+    // real time, so the timer isn't affected by anything slowing down the game
+    time: Res<Time<Real>>,
+) {
+    if attempt.level_iid.is_some() && *game_state == GameState::Playing {
+        attempt.timer.tick(time.delta());
+    }
+}
+
+/// [`System`] that updates the best stats of a level once it is completed, and shows them to the
+/// player. With the `completion_summary` on, they are shown on a summary screen along with the
+/// shards collected in the level, and the game waits in [`GameState::LevelComplete`] until the
+/// player presses a key.
+#[allow(clippy::too_many_arguments)]
+pub fn record_level_stats(
+    mut commands: Commands,
+    mut ev_level_complete: EventReader<LevelCompleteEvent>,
+    mut attempt: ResMut<LevelAttempt>,
+    mut save_data: ResMut<SaveData>,
+    q_stats_ui: Query<Entity, With<LevelStatsUi>>,
+    asset_server: Res<AssetServer>,
+    (config, current_level, shard_counter): (Res<Config>, Res<CurrentLevel>, Res<ShardCounter>),
+    (game_state, mut next_game_state): (Res<State<GameState>>, ResMut<NextState<GameState>>),
+) {
+    for event in ev_level_complete.read() {
+        if attempt.level_iid.as_ref() != Some(&event.level_iid) {
+            continue;
+        }
+        let deaths = attempt.deaths;
+        let time_secs = attempt.timer.elapsed_secs();
+        *attempt = LevelAttempt::default();
+
+        let prev_best = save_data.level_stats.get(event.level_iid.as_str()).copied();
+        let best = match prev_best {
+            Some(prev) => LevelBestStats {
+                deaths: prev.deaths.min(deaths),
+                time_secs: prev.time_secs.min(time_secs),
+            },
+            None => LevelBestStats { deaths, time_secs },
+        };
+        if prev_best != Some(best) {
+            save_data
+                .level_stats
+                .insert(event.level_iid.to_string(), best);
+        }
+
+        let deaths_line = match prev_best {
+            Some(prev) if prev.deaths <= deaths => {
+                format!("Deaths: {deaths} (Best: {})", prev.deaths)
+            }
+            _ => format!("Deaths: {deaths} (New best!)"),
+        };
+        let time = Duration::from_secs_f32(time_secs).hhmmssxxx();
+        let time_line = match prev_best {
+            Some(prev) if prev.time_secs <= time_secs => format!(
+                "Time: {time} (Best: {})",
+                Duration::from_secs_f32(prev.time_secs).hhmmssxxx()
+            ),
+            _ => format!("Time: {time} (New best!)"),
+        };
+
+        for entity in q_stats_ui.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        let font = TextFont {
+            font: asset_server.load("fonts/Outfit-Medium.ttf"),
+            ..default()
+        };
+
+        // the summary would cut off a level switch or death animation that has already started
+        let can_pause =
+            *game_state == GameState::Playing && !matches!(*next_game_state, NextState::Pending(_));
+        if config.level_config.completion_summary
+            && !current_level.skip_completion_summary
+            && can_pause
+        {
+            let collected = shard_counter
+                .iter_level_shards(&event.level_iid)
+                .filter(|iid| shard_counter.collected.contains(*iid))
+                .count();
+            let total = shard_counter.level_total(&event.level_iid);
+            commands
+                .spawn((
+                    Node {
+                        width: Val::Percent(100.),
+                        height: Val::Percent(100.),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BackgroundColor(Color::BLACK.with_alpha(0.8)),
+                    LevelStatsUi(Timer::from_seconds(SUMMARY_MIN_SECS, TimerMode::Once)),
+                    LevelSummaryUi,
+                ))
+                .with_child((
+                    Text::new(format!(
+                        "Level Complete!\n{deaths_line}\n{time_line}\nShards: {collected}/{total}\n\n[Press any key to continue]"
+                    )),
+                    TextLayout::new_with_justify(JustifyText::Center),
+                    font.with_font_size(32.),
+                ));
+            next_game_state.set(GameState::LevelComplete);
+            continue;
+        }
+
+        commands
+            .spawn((
+                Node {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    justify_content: JustifyContent::Center,
+                    padding: UiRect::all(Val::Px(32.)),
+                    ..default()
+                },
+                LevelStatsUi(Timer::from_seconds(STATS_PANEL_SECS, TimerMode::Once)),
+            ))
+            .with_child((
+                Text::new(format!("Level Complete!\n{deaths_line}\n{time_line}")),
+                TextLayout::new_with_justify(JustifyText::Center),
+                font.with_font_size(24.),
+            ));
+    }
+}
+
+fn despawn_level_stats_ui(
+    mut commands: Commands,
+    mut q_stats_ui: Query<(Entity, &mut LevelStatsUi), Without<LevelSummaryUi>>,
+    time: Res<Time<Real>>,
+) {
+    for (entity, mut stats_ui) in q_stats_ui.iter_mut() {
+        if stats_ui.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// [`System`] that closes the level complete summary on any key or mouse press once it has been
+/// up for [`SUMMARY_MIN_SECS`], and goes back to playing.
+fn dismiss_level_summary(
+    mut commands: Commands,
+    mut q_summary: Query<(Entity, &mut LevelStatsUi), With<LevelSummaryUi>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    time: Res<Time<Real>>,
+) {
+    let Ok((entity, mut summary)) = q_summary.get_single_mut() else {
+        next_game_state.set(GameState::Playing);
+        return;
+    };
+    if !summary.0.tick(time.delta()).finished() {
+        return;
+    }
+    if keys.get_just_pressed().next().is_some() || mouse.get_just_pressed().next().is_some() {
+        commands.entity(entity).despawn_recursive();
+        next_game_state.set(GameState::Playing);
+    }
+}
@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+
+use crate::{
+    config::Config,
+    light::{segments::LightSegment, LightColor},
+    lighting::{AmbientLight2d, LineLight2d},
+};
+
+use super::CurrentLevel;
+
+/// The least effective a light beam can be made by the ambient tint, so beams never vanish
+/// entirely
+const MIN_TINT_EFFECTIVENESS: f32 = 0.2;
+
+/// [`Plugin`] for the per-level ambient tint, set by the optional `AmbientTint` level field. The
+/// tint colors the whole scene, and with `tint_affects_light` enabled also dims light beams and
+/// slows down the sensors they charge, the further the beam's color is from the tint.
+pub struct AmbientTintPlugin;
+
+impl Plugin for AmbientTintPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (update_ambient_tint, update_beam_tint));
+    }
+}
+
+/// How effective a beam of `color` is under the level's tint, from [`MIN_TINT_EFFECTIVENESS`] to
+/// 1. Beams are fully effective in neutral levels, or when the tint doesn't affect light.
+pub fn tint_effectiveness(current_level: &CurrentLevel, config: &Config, color: LightColor) -> f32 {
+    if !config.level_config.tint_affects_light {
+        return 1.0;
+    }
+    let tint = current_level.ambient_tint.to_srgba();
+    let tint = Vec3::new(tint.red, tint.green, tint.blue);
+    let beam = color.lighting_color();
+    let total = beam.element_sum();
+    if total <= 0.0 {
+        return 1.0;
+    }
+    (tint.dot(beam) / total).clamp(MIN_TINT_EFFECTIVENESS, 1.0)
+}
+
+pub fn update_ambient_tint(
+    current_level: Res<CurrentLevel>,
+    mut q_ambient_light: Query<&mut AmbientLight2d>,
+) {
+    let tint = current_level.ambient_tint.to_srgba();
+    let tint = Vec3::new(tint.red, tint.green, tint.blue);
+    for mut ambient_light in q_ambient_light.iter_mut() {
+        if ambient_light.color.xyz() != tint {
+            // the intensity in w is left to the darkness
+            let intensity = ambient_light.color.w;
+            ambient_light.color = tint.extend(intensity);
+        }
+    }
+}
+
+/// [`System`] that dims the [`LineLight2d`] of each light segment by its
+/// [`tint_effectiveness`].
+pub fn update_beam_tint(
+    current_level: Res<CurrentLevel>,
+    config: Res<Config>,
+    q_segments: Query<(&LightSegment, &Children)>,
+    mut q_line_lights: Query<&mut LineLight2d>,
+) {
+    for (segment, children) in q_segments.iter() {
+        let effectiveness = tint_effectiveness(&current_level, &config, segment.color);
+        let color = (segment.color.lighting_color() * effectiveness).extend(1.0);
+        for child in children.iter() {
+            let Ok(mut line_light) = q_line_lights.get_mut(*child) else {
+                continue;
+            };
+            if line_light.color != color {
+                line_light.color = color;
+            }
+        }
+    }
+}
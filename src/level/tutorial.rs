@@ -0,0 +1,235 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::{
+    animation::AnimationConfig,
+    config::Config,
+    player::{
+        animation::PlayerAnimationType, light::update_aim_time_scale, skin::PlayerSkin,
+        PlayerMarker,
+    },
+    save::SaveData,
+    shared::GameState,
+};
+
+use super::room::TriggerZone;
+
+/// How long a tutorial has to be on screen before it can be dismissed, so that input the player
+/// was already giving when entering the zone doesn't dismiss it right away
+const TUTORIAL_MIN_SECS: f32 = 0.5;
+
+/// [`Plugin`] for tutorial zones, [`TriggerZone`]s placed in Ldtk that show an overlay teaching a
+/// mechanic the first time the player walks into them.
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<TutorialZoneBundle>("TutorialZone")
+            .add_systems(
+                Update,
+                (
+                    show_tutorial.run_if(|config: Res<Config>| config.tutorial_config.enabled),
+                    dismiss_tutorial,
+                    animate_tutorial_demo,
+                    scale_time_during_tutorial.after(update_aim_time_scale),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// [`Component`] for a [`TriggerZone`] that shows a tutorial. The `tutorial_id` is what is saved
+/// once the tutorial is seen, so zones in different levels can share an id to only teach a mechanic
+/// once. Zones without an id never show anything.
+#[derive(Default, Component)]
+pub struct TutorialZone {
+    pub tutorial_id: String,
+    pub text: String,
+    /// The animation of Lyra shown next to the text
+    pub demo: Option<PlayerAnimationType>,
+}
+
+impl From<&EntityInstance> for TutorialZone {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let (Ok(tutorial_id), Ok(text)) = (
+            entity_instance.get_string_field("tutorial_id"),
+            entity_instance.get_string_field("text"),
+        ) else {
+            warn!(
+                "Tutorial zone {} needs a tutorial_id and text, it won't show anything",
+                entity_instance.iid
+            );
+            return TutorialZone::default();
+        };
+        let demo = match entity_instance.get_string_field("demo_animation") {
+            Ok(demo) => demo_animation(demo),
+            Err(_) => None,
+        };
+        TutorialZone {
+            tutorial_id: tutorial_id.clone(),
+            text: text.clone(),
+            demo,
+        }
+    }
+}
+
+fn demo_animation(name: &str) -> Option<PlayerAnimationType> {
+    match name {
+        "Idle" => Some(PlayerAnimationType::Idle),
+        "Walk" => Some(PlayerAnimationType::Walk),
+        "Crouch" => Some(PlayerAnimationType::Crouch),
+        "Jump" => Some(PlayerAnimationType::Jump),
+        "Fall" => Some(PlayerAnimationType::Fall),
+        "Land" => Some(PlayerAnimationType::Land),
+        _ => {
+            warn!("Unknown tutorial demo animation {name:?}, showing no demo");
+            None
+        }
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct TutorialZoneBundle {
+    #[from_entity_instance]
+    zone: TriggerZone,
+    #[from_entity_instance]
+    tutorial: TutorialZone,
+}
+
+/// [`Component`] for the tutorial overlay, which can be dismissed once its timer finishes.
+#[derive(Component)]
+pub struct TutorialUi(Timer);
+
+#[derive(Component)]
+pub struct TutorialDemo;
+
+/// [`System`] that shows the tutorial of the zone the player is in, if they haven't seen it yet.
+#[allow(clippy::too_many_arguments)]
+pub fn show_tutorial(
+    mut commands: Commands,
+    q_player: Query<&Transform, With<PlayerMarker>>,
+    q_zones: Query<(&TriggerZone, &TutorialZone)>,
+    q_tutorial_ui: Query<(), With<TutorialUi>>,
+    game_state: Res<State<GameState>>,
+    mut save_data: ResMut<SaveData>,
+    asset_server: Res<AssetServer>,
+    skin: Res<PlayerSkin>,
+) {
+    if *game_state != GameState::Playing || !q_tutorial_ui.is_empty() {
+        return;
+    }
+    let Ok(player_transform) = q_player.get_single() else {
+        return;
+    };
+    let player_pos = player_transform.translation.xy();
+    let Some((_, zone)) = q_zones.iter().find(|(trigger_zone, zone)| {
+        trigger_zone.trigger_box.contains(player_pos)
+            && !zone.tutorial_id.is_empty()
+            && !save_data.seen_tutorials.contains(&zone.tutorial_id)
+    }) else {
+        return;
+    };
+    // marked as seen right away, so quitting with the tutorial open doesn't show it again
+    save_data.seen_tutorials.insert(zone.tutorial_id.clone());
+
+    let font = TextFont {
+        font: asset_server.load("fonts/Outfit-Medium.ttf"),
+        ..default()
+    };
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                bottom: Val::Px(64.),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            TutorialUi(Timer::from_seconds(TUTORIAL_MIN_SECS, TimerMode::Once)),
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(24.),
+                        padding: UiRect::all(Val::Px(16.)),
+                        ..default()
+                    },
+                    BackgroundColor(Color::BLACK.with_alpha(0.8)),
+                ))
+                .with_children(|panel| {
+                    if let Some(demo) = zone.demo {
+                        let animation = AnimationConfig::from(demo);
+                        panel.spawn((
+                            ImageNode::from_atlas_image(
+                                skin.image.clone(),
+                                TextureAtlas {
+                                    layout: skin.layout.clone(),
+                                    index: animation.cur_index,
+                                },
+                            ),
+                            Node {
+                                width: Val::Px(60.),
+                                height: Val::Px(80.),
+                                ..default()
+                            },
+                            animation,
+                            TutorialDemo,
+                        ));
+                    }
+                    panel.spawn((
+                        Text::new(format!("{}\n[Press any key to continue]", zone.text)),
+                        font.with_font_size(24.),
+                    ));
+                });
+        });
+}
+
+/// [`System`] that despawns the tutorial overlay on any key or mouse press.
+pub fn dismiss_tutorial(
+    mut commands: Commands,
+    mut q_tutorial_ui: Query<(Entity, &mut TutorialUi)>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    // real time, so the overlay can be dismissed while the game is paused
+    time: Res<Time<Real>>,
+) {
+    let Ok((entity, mut tutorial_ui)) = q_tutorial_ui.get_single_mut() else {
+        return;
+    };
+    if !tutorial_ui.0.tick(time.delta()).finished() {
+        return;
+    }
+    if keys.get_just_pressed().next().is_some() || mouse.get_just_pressed().next().is_some() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn animate_tutorial_demo(
+    mut q_demo: Query<(&mut AnimationConfig, &mut ImageNode), With<TutorialDemo>>,
+    time: Res<Time<Real>>,
+) {
+    for (mut animation, mut image) in q_demo.iter_mut() {
+        animation.tick(time.delta());
+        if let Some(atlas) = &mut image.texture_atlas {
+            atlas.index = animation.cur_index;
+        }
+    }
+}
+
+/// [`System`] that slows down (or pauses) [`Virtual`] time while a tutorial is shown. Runs after
+/// [`update_aim_time_scale`], which puts the speed back once the tutorial is dismissed.
+pub fn scale_time_during_tutorial(
+    q_tutorial_ui: Query<(), With<TutorialUi>>,
+    config: Res<Config>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    if q_tutorial_ui.is_empty() {
+        return;
+    }
+    let speed = config.tutorial_config.time_scale.clamp(0.0, 1.0);
+    if time.relative_speed() != speed {
+        time.set_relative_speed(speed);
+    }
+}
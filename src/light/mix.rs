@@ -0,0 +1,251 @@
+use bevy::prelude::*;
+use enum_map::EnumMap;
+
+use crate::{camera::HIGHRES_LAYER, lighting::LineLight2d};
+
+use super::{
+    palette::rgb_to_color, segments::LightBeamPoints, LightBeamSource, LightColor,
+    LightSegmentZMarker,
+};
+
+/// How close crossings have to be to count as the same [`LightMixNode`], so three beams crossing at
+/// nearly the same point mix all of their colors instead of making three separate nodes.
+const MIX_MERGE_DISTANCE: f32 = 2.0;
+
+/// How far a [`LightMixNode`] can be from a sensor and still charge it. Beams bounce off of
+/// sensors, so they have to cross right in front of one.
+pub const MIX_SENSOR_RANGE: f32 = 4.0;
+
+/// The color of the light where beams of `a` and `b` cross, or [`None`] if they don't mix. Beams of
+/// the same color don't mix, and black light absorbs the other colors instead of mixing with them.
+pub fn mix_colors(a: LightColor, b: LightColor) -> Option<Vec3> {
+    use LightColor::*;
+    match (a, b) {
+        (Green, Purple) | (Purple, Green) => Some(Vec3::new(0.6, 0.8, 1.0)),
+        (Green, White) | (White, Green) => Some(Vec3::new(0.6, 1.0, 0.7)),
+        (Green, Blue) | (Blue, Green) => Some(Vec3::new(0.0, 0.8, 0.9)),
+        (Purple, White) | (White, Purple) => Some(Vec3::new(0.9, 0.6, 1.0)),
+        (Purple, Blue) | (Blue, Purple) => Some(Vec3::new(0.4, 0.2, 1.0)),
+        (White, Blue) | (Blue, White) => Some(Vec3::new(0.5, 0.7, 1.0)),
+        _ => None,
+    }
+}
+
+/// [`Component`] for the point where light beams of different colors cross, which counts as light
+/// of all of their colors at once. These are kept in sync with the beams by
+/// [`update_light_mix_nodes`].
+#[derive(Component, Debug)]
+pub struct LightMixNode {
+    /// Colors of every beam crossing here
+    pub colors: EnumMap<LightColor, bool>,
+    /// The beams crossing here, sorted so the node can be found again on the next tick
+    sources: Vec<Entity>,
+}
+
+impl LightMixNode {
+    /// Whether every color in `colors` crosses at this node.
+    pub fn contains(&self, colors: &EnumMap<LightColor, bool>) -> bool {
+        colors
+            .iter()
+            .all(|(color, required)| !*required || self.colors[color])
+    }
+}
+
+/// A crossing found by [`find_light_mixes`].
+#[derive(Debug)]
+struct LightMix {
+    point: Vec2,
+    colors: EnumMap<LightColor, bool>,
+    sources: Vec<Entity>,
+    /// Sum of the mixed colors of every pair of beams crossing here, see `mixed_pairs`
+    rgb_sum: Vec3,
+    mixed_pairs: usize,
+}
+
+impl LightMix {
+    fn rgb(&self) -> Vec3 {
+        self.rgb_sum / self.mixed_pairs as f32
+    }
+}
+
+/// Where the segments `a0`-`a1` and `b0`-`b1` cross, if they do.
+fn segment_crossing(a0: Vec2, a1: Vec2, b0: Vec2, b1: Vec2) -> Option<Vec2> {
+    let (r, s) = (a1 - a0, b1 - b0);
+    let denom = r.perp_dot(s);
+    // parallel segments don't cross at a single point
+    if denom.abs() < f32::EPSILON {
+        return None;
+    }
+    let t = (b0 - a0).perp_dot(s) / denom;
+    let u = (b0 - a0).perp_dot(r) / denom;
+    ((0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)).then(|| a0 + r * t)
+}
+
+/// Finds every point where beams of colors that mix cross, given each beam's source entity, color
+/// and path. Crossings within [`MIX_MERGE_DISTANCE`] of each other are merged into one.
+fn find_light_mixes(beams: &[(Entity, LightColor, &[Vec2])]) -> Vec<LightMix> {
+    let mut mixes: Vec<LightMix> = vec![];
+    for (i, (a_entity, a_color, a_points)) in beams.iter().enumerate() {
+        for (b_entity, b_color, b_points) in beams.iter().skip(i + 1) {
+            let Some(rgb) = mix_colors(*a_color, *b_color) else {
+                continue;
+            };
+            for a in a_points.windows(2) {
+                for b in b_points.windows(2) {
+                    let Some(point) = segment_crossing(a[0], a[1], b[0], b[1]) else {
+                        continue;
+                    };
+                    let mix = match mixes
+                        .iter_mut()
+                        .find(|mix| mix.point.distance(point) < MIX_MERGE_DISTANCE)
+                    {
+                        Some(mix) => mix,
+                        None => {
+                            mixes.push(LightMix {
+                                point,
+                                colors: EnumMap::default(),
+                                sources: vec![],
+                                rgb_sum: Vec3::ZERO,
+                                mixed_pairs: 0,
+                            });
+                            mixes.last_mut().unwrap()
+                        }
+                    };
+                    for (entity, color) in [(*a_entity, *a_color), (*b_entity, *b_color)] {
+                        mix.colors[color] = true;
+                        if !mix.sources.contains(&entity) {
+                            mix.sources.push(entity);
+                        }
+                    }
+                    mix.rgb_sum += rgb;
+                    mix.mixed_pairs += 1;
+                }
+            }
+        }
+    }
+    for mix in mixes.iter_mut() {
+        mix.sources.sort();
+    }
+    mixes
+}
+
+/// [`System`] that spawns a [`LightMixNode`] where light beams of mixing colors cross, moving the
+/// existing nodes along with the beams and despawning the ones whose beams no longer cross.
+pub fn update_light_mix_nodes(
+    mut commands: Commands,
+    q_light_sources: Query<(Entity, &LightBeamSource, &LightBeamPoints)>,
+    mut q_mix_nodes: Query<(Entity, &mut LightMixNode, &mut Transform)>,
+    q_light_segment_z: Query<&GlobalTransform, With<LightSegmentZMarker>>,
+) {
+    let Ok(light_segment_z) = q_light_segment_z.get_single() else {
+        return;
+    };
+    // just above the beams
+    let z = light_segment_z.translation().z + 0.5;
+    let beams: Vec<_> = q_light_sources
+        .iter()
+        .map(|(entity, source, points)| (entity, source.color, points.points()))
+        .collect();
+
+    let mut unmatched: Vec<_> = q_mix_nodes.iter_mut().collect();
+    for mix in find_light_mixes(&beams) {
+        // the same two beams can cross more than once, in which case any of their nodes will do
+        match unmatched
+            .iter()
+            .position(|(_, node, _)| node.sources == mix.sources)
+        {
+            Some(i) => {
+                let (_, mut node, mut transform) = unmatched.swap_remove(i);
+                node.colors = mix.colors;
+                transform.translation = mix.point.extend(z);
+            }
+            None => {
+                let rgb = mix.rgb();
+                commands
+                    .spawn((
+                        LightMixNode {
+                            colors: mix.colors,
+                            sources: mix.sources,
+                        },
+                        Sprite::from_color(rgb_to_color(rgb * 2.0), Vec2::splat(3.0)),
+                        Transform::from_translation(mix.point.extend(z)),
+                        HIGHRES_LAYER,
+                    ))
+                    .with_child(LineLight2d::point(rgb.extend(1.0), 24.0, 0.01));
+            }
+        }
+    }
+    for (entity, _, _) in unmatched {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// [`System`] that despawns the [`LightMixNode`]s of beams that were despawned right away, instead
+/// of waiting for the next [`update_light_mix_nodes`], which doesn't run during animations.
+pub fn despawn_removed_source_mix_nodes(
+    mut commands: Commands,
+    mut removed_sources: RemovedComponents<LightBeamSource>,
+    q_mix_nodes: Query<(Entity, &LightMixNode)>,
+) {
+    let removed: Vec<Entity> = removed_sources.read().collect();
+    if removed.is_empty() {
+        return;
+    }
+    for (entity, node) in q_mix_nodes.iter() {
+        if node.sources.iter().any(|source| removed.contains(source)) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crossing_beams_mix() {
+        let (green, blue) = (Entity::from_raw(1), Entity::from_raw(2));
+        let green_path = [Vec2::new(-10.0, 0.0), Vec2::new(10.0, 0.0)];
+        let blue_path = [Vec2::new(0.0, -10.0), Vec2::new(0.0, 10.0)];
+        let mixes = find_light_mixes(&[
+            (green, LightColor::Green, &green_path),
+            (blue, LightColor::Blue, &blue_path),
+        ]);
+        assert_eq!(mixes.len(), 1);
+        assert_eq!(mixes[0].point, Vec2::ZERO);
+        assert_eq!(mixes[0].sources, vec![green, blue]);
+        assert!(mixes[0].colors[LightColor::Green] && mixes[0].colors[LightColor::Blue]);
+
+        // same color, and black absorbs instead of mixing
+        for color in [LightColor::Green, LightColor::Black] {
+            let mixes = find_light_mixes(&[
+                (green, LightColor::Green, &green_path),
+                (blue, color, &blue_path),
+            ]);
+            assert!(mixes.is_empty());
+        }
+    }
+
+    #[test]
+    fn three_beams_mix_at_one_point() {
+        let paths = [
+            [Vec2::new(-10.0, 0.0), Vec2::new(10.0, 0.0)],
+            [Vec2::new(0.0, -10.0), Vec2::new(0.0, 10.0)],
+            // crosses the others slightly off their crossing
+            [Vec2::new(-10.0, -9.5), Vec2::new(10.0, 10.5)],
+        ];
+        let colors = [LightColor::Green, LightColor::Blue, LightColor::Purple];
+        let beams: Vec<_> = (0..3)
+            .map(|i| (Entity::from_raw(i as u32), colors[i], &paths[i][..]))
+            .collect();
+        let mixes = find_light_mixes(&beams);
+        assert_eq!(mixes.len(), 1);
+        assert_eq!(mixes[0].sources.len(), 3);
+        let node = LightMixNode {
+            colors: mixes[0].colors,
+            sources: mixes[0].sources.clone(),
+        };
+        assert!(node.contains(&EnumMap::from_fn(|color| colors.contains(&color))));
+        assert!(!node.contains(&EnumMap::from_fn(|color| color == LightColor::White)));
+    }
+}
@@ -1,3 +1,5 @@
+use std::{fmt, str::FromStr};
+
 use bevy::{
     prelude::*,
     sprite::{AlphaMode2d, Material2dPlugin},
@@ -5,14 +7,22 @@ use bevy::{
 use bevy_ecs_ldtk::prelude::*;
 
 use enum_map::Enum;
+use mix::{despawn_removed_source_mix_nodes, update_light_mix_nodes};
+use palette::{
+    palette_rgb, rgb_to_color, update_light_materials, ColorPalette, ColorRole, PaletteMode,
+};
 use render::{LightMaterial, LightRenderData};
 use segments::{
-    cleanup_light_sources, simulate_light_sources, spawn_needed_segments, tick_light_sources,
-    visually_sync_segments, LightSegmentCache, PrevLightBeamPlayback,
+    cleanup_light_sources, despawn_removed_source_segments, simulate_light_sources,
+    spawn_needed_segments, tick_light_sources, update_lens_beams, visually_sync_segments,
+    LightBeamStats, LightSegmentCache, PrevLightBeamPlayback,
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{level::LevelSystems, lighting::LineLight2d};
 
+pub mod mix;
+pub mod palette;
 mod render;
 pub mod segments;
 
@@ -31,6 +41,8 @@ impl Plugin for LightManagementPlugin {
         app.add_plugins(Material2dPlugin::<LightMaterial>::default())
             .init_resource::<LightRenderData>()
             .init_resource::<LightSegmentCache>()
+            .init_resource::<LightBeamStats>()
+            .init_resource::<ColorPalette>()
             .register_ldtk_entity::<LightSegmentZBundle>("LightSegmentZMarker")
             .register_ldtk_entity::<LightSourceZBundle>("LightSourceZMarker")
             .register_ldtk_entity::<LightSourceBundle>("LightSource")
@@ -39,8 +51,10 @@ impl Plugin for LightManagementPlugin {
                 (
                     (
                         simulate_light_sources,
+                        update_lens_beams,
                         spawn_needed_segments,
                         visually_sync_segments,
+                        update_light_mix_nodes,
                     )
                         .chain(),
                     tick_light_sources,
@@ -49,6 +63,13 @@ impl Plugin for LightManagementPlugin {
             )
             // why does this need to be on update???
             .add_systems(Update, cleanup_light_sources.in_set(LevelSystems::Reset))
+            .add_systems(
+                Update,
+                (
+                    despawn_removed_source_segments,
+                    despawn_removed_source_mix_nodes,
+                ),
+            )
             .add_systems(
                 PostUpdate,
                 spawn_level_light_beams.in_set(LevelSystems::Simulation),
@@ -56,6 +77,10 @@ impl Plugin for LightManagementPlugin {
             .add_systems(
                 PostUpdate,
                 add_light_beam_added.in_set(LevelSystems::Processing),
+            )
+            .add_systems(
+                Update,
+                update_light_materials.run_if(resource_changed::<ColorPalette>),
             );
     }
 }
@@ -201,7 +226,7 @@ pub struct LightSegmentZBundle {
 }
 
 /// [`Enum`] for each of the light colors.
-#[derive(Enum, Clone, Copy, Default, PartialEq, Debug, Eq, Hash)]
+#[derive(Enum, Clone, Copy, Default, PartialEq, Debug, Eq, Hash, Serialize, Deserialize)]
 pub enum LightColor {
     #[default]
     Green,
@@ -223,16 +248,45 @@ impl From<LightColor> for LightMaterial {
     }
 }
 
+/// Error returned when parsing a string that isn't the name of a [`LightColor`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownLightColor(pub String);
+
+impl fmt::Display for UnknownLightColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "\"{}\" is not a light color, expected one of Green, Purple, White, Blue or Black",
+            self.0
+        )
+    }
+}
+
+/// Parses the name of a [`LightColor`], ignoring case. All Ldtk enum fields holding light colors
+/// should go through this.
+impl FromStr for LightColor {
+    type Err = UnknownLightColor;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "purple" => Ok(LightColor::Purple),
+            "green" => Ok(LightColor::Green),
+            "white" => Ok(LightColor::White),
+            "blue" => Ok(LightColor::Blue),
+            "black" => Ok(LightColor::Black),
+            _ => Err(UnknownLightColor(value.to_string())),
+        }
+    }
+}
+
+/// Converts an Ldtk enum value into a [`LightColor`], logging an error and falling back to the
+/// default color if it isn't a known color.
 impl From<&String> for LightColor {
     fn from(value: &String) -> Self {
-        match value.as_str() {
-            "Purple" => LightColor::Purple,
-            "Green" => LightColor::Green,
-            "White" => LightColor::White,
-            "Blue" => LightColor::Blue,
-            "Black" => LightColor::Black,
-            _ => panic!("String {} does not represent Light Color", value),
-        }
+        value.parse().unwrap_or_else(|err| {
+            error!("{err}, using {:?} instead", LightColor::default());
+            LightColor::default()
+        })
     }
 }
 
@@ -246,34 +300,30 @@ impl LightColor {
         }
     }
 
+    /// The color of the light cast by this color in the standard palette. Prefer
+    /// [`ColorPalette::lighting_color`] where the palette is at hand.
     pub fn lighting_color(&self) -> Vec3 {
-        match self {
-            LightColor::Purple => Vec3::new(0.7, 0.2, 0.8),
-            LightColor::Green => Vec3::new(0.0, 0.9, 0.5),
-            LightColor::White => Vec3::new(0.8, 0.8, 0.5),
-            LightColor::Blue => Vec3::new(0.1, 0.2, 0.8),
-            LightColor::Black => Vec3::new(0.2, 0.2, 0.2),
-        }
+        palette_rgb(PaletteMode::Standard, *self, ColorRole::Lighting)
     }
 
+    /// The color of light beams of this color in the standard palette. Prefer
+    /// [`ColorPalette::light_beam_color`] where the palette is at hand.
     pub fn light_beam_color(&self) -> Color {
-        match self {
-            LightColor::Purple => Color::srgb(1.5, 0.5, 3.0),
-            LightColor::Green => Color::srgb(1.0, 4.0, 3.0),
-            LightColor::White => Color::srgb(2.0, 2.0, 2.0),
-            LightColor::Blue => Color::srgb(1.0, 2.0, 4.0),
-            LightColor::Black => Color::srgb(0.2, 0.2, 0.2),
-        }
+        rgb_to_color(palette_rgb(
+            PaletteMode::Standard,
+            *self,
+            ColorRole::LightBeam,
+        ))
     }
 
+    /// The color of UI showing this color in the standard palette. Prefer
+    /// [`ColorPalette::indicator_color`] where the palette is at hand.
     pub fn indicator_color(&self) -> Color {
-        match self {
-            LightColor::Purple => Color::srgb(0.7, 0.3, 1.0),
-            LightColor::Green => Color::srgb(0.25, 0.9, 0.75),
-            LightColor::White => Color::srgb(1.0, 1.0, 1.0),
-            LightColor::Blue => Color::srgb(0.25, 0.5, 1.0),
-            LightColor::Black => Color::srgb(0.2, 0.2, 0.2),
-        }
+        rgb_to_color(palette_rgb(
+            PaletteMode::Standard,
+            *self,
+            ColorRole::Indicator,
+        ))
     }
 
     pub fn indicator_dimmed_color(&self) -> Color {
@@ -292,3 +342,25 @@ pub struct LightBeamSource {
     pub time_traveled: f32,
     pub color: LightColor,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_light_color_ignores_case() {
+        assert_eq!("Green".parse(), Ok(LightColor::Green));
+        assert_eq!("purple".parse(), Ok(LightColor::Purple));
+        assert_eq!("WHITE".parse(), Ok(LightColor::White));
+        assert_eq!(" Blue ".parse(), Ok(LightColor::Blue));
+        assert_eq!("bLaCk".parse(), Ok(LightColor::Black));
+    }
+
+    #[test]
+    fn parse_unknown_light_color() {
+        assert_eq!(
+            "Red".parse::<LightColor>(),
+            Err(UnknownLightColor("Red".to_string()))
+        );
+    }
+}
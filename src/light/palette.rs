@@ -0,0 +1,186 @@
+use std::f32::consts::FRAC_PI_4;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::config::Config;
+
+use super::{
+    render::{LightMaterial, LightRenderData},
+    LightColor,
+};
+
+/// Which set of colors the [`LightColor`]s are drawn with.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PaletteMode {
+    #[default]
+    Standard,
+    /// Hues that stay apart for the common kinds of color blindness, with a symbol drawn on each
+    /// shard
+    Colorblind,
+}
+
+/// What a [`LightColor`] is being drawn as, since each needs a different brightness.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColorRole {
+    /// The color of the light cast by beams, shards and the player, before it is extended to a
+    /// [`Vec4`]
+    Lighting,
+    /// The color of light beams themselves, which is bright enough to bloom
+    LightBeam,
+    /// The color of UI like the player's light indicator
+    Indicator,
+}
+
+/// Looks up the rgb value of a [`LightColor`] in a palette. Every conversion from a light color to
+/// rgb should go through this, so that the palettes stay in sync.
+pub fn palette_rgb(mode: PaletteMode, color: LightColor, role: ColorRole) -> Vec3 {
+    match (mode, role) {
+        (PaletteMode::Standard, ColorRole::Lighting) => match color {
+            LightColor::Purple => Vec3::new(0.7, 0.2, 0.8),
+            LightColor::Green => Vec3::new(0.0, 0.9, 0.5),
+            LightColor::White => Vec3::new(0.8, 0.8, 0.5),
+            LightColor::Blue => Vec3::new(0.1, 0.2, 0.8),
+            LightColor::Black => Vec3::new(0.2, 0.2, 0.2),
+        },
+        (PaletteMode::Standard, ColorRole::LightBeam) => match color {
+            LightColor::Purple => Vec3::new(1.5, 0.5, 3.0),
+            LightColor::Green => Vec3::new(1.0, 4.0, 3.0),
+            LightColor::White => Vec3::new(2.0, 2.0, 2.0),
+            LightColor::Blue => Vec3::new(1.0, 2.0, 4.0),
+            LightColor::Black => Vec3::new(0.2, 0.2, 0.2),
+        },
+        (PaletteMode::Standard, ColorRole::Indicator) => match color {
+            LightColor::Purple => Vec3::new(0.7, 0.3, 1.0),
+            LightColor::Green => Vec3::new(0.25, 0.9, 0.75),
+            LightColor::White => Vec3::new(1.0, 1.0, 1.0),
+            LightColor::Blue => Vec3::new(0.25, 0.5, 1.0),
+            LightColor::Black => Vec3::new(0.2, 0.2, 0.2),
+        },
+        // based on the Okabe-Ito palette
+        (PaletteMode::Colorblind, ColorRole::Lighting) => match color {
+            LightColor::Purple => Vec3::new(0.8, 0.47, 0.65),
+            LightColor::Green => Vec3::new(0.0, 0.62, 0.45),
+            LightColor::White => Vec3::new(0.8, 0.8, 0.8),
+            LightColor::Blue => Vec3::new(0.0, 0.45, 0.7),
+            LightColor::Black => Vec3::new(0.2, 0.2, 0.2),
+        },
+        (PaletteMode::Colorblind, ColorRole::LightBeam) => match color {
+            LightColor::Purple => Vec3::new(3.0, 1.2, 1.8),
+            LightColor::Green => Vec3::new(0.6, 3.0, 2.2),
+            LightColor::White => Vec3::new(2.0, 2.0, 2.0),
+            LightColor::Blue => Vec3::new(0.4, 1.6, 3.4),
+            LightColor::Black => Vec3::new(0.2, 0.2, 0.2),
+        },
+        (PaletteMode::Colorblind, ColorRole::Indicator) => match color {
+            LightColor::Purple => Vec3::new(0.8, 0.47, 0.65),
+            LightColor::Green => Vec3::new(0.0, 0.62, 0.45),
+            LightColor::White => Vec3::new(1.0, 1.0, 1.0),
+            LightColor::Blue => Vec3::new(0.0, 0.45, 0.7),
+            LightColor::Black => Vec3::new(0.2, 0.2, 0.2),
+        },
+    }
+}
+
+pub fn rgb_to_color(rgb: Vec3) -> Color {
+    Color::srgb(rgb.x, rgb.y, rgb.z)
+}
+
+/// The symbol drawn on shards in the colorblind palette, so colors can be told apart by shape.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColorSymbol {
+    Diamond,
+    Plus,
+    Square,
+    Bar,
+    Pillar,
+}
+
+impl From<LightColor> for ColorSymbol {
+    fn from(color: LightColor) -> Self {
+        match color {
+            LightColor::Green => ColorSymbol::Diamond,
+            LightColor::Purple => ColorSymbol::Plus,
+            LightColor::White => ColorSymbol::Square,
+            LightColor::Blue => ColorSymbol::Bar,
+            LightColor::Black => ColorSymbol::Pillar,
+        }
+    }
+}
+
+impl ColorSymbol {
+    /// The rectangles the symbol is drawn with, as their size and rotation.
+    pub fn parts(&self) -> &'static [(Vec2, f32)] {
+        const DIAMOND: &[(Vec2, f32)] = &[(Vec2::splat(3.0), FRAC_PI_4)];
+        const PLUS: &[(Vec2, f32)] = &[(Vec2::new(5.0, 1.0), 0.0), (Vec2::new(1.0, 5.0), 0.0)];
+        const SQUARE: &[(Vec2, f32)] = &[(Vec2::splat(3.0), 0.0)];
+        const BAR: &[(Vec2, f32)] = &[(Vec2::new(5.0, 1.5), 0.0)];
+        const PILLAR: &[(Vec2, f32)] = &[(Vec2::new(1.5, 5.0), 0.0)];
+        match self {
+            ColorSymbol::Diamond => DIAMOND,
+            ColorSymbol::Plus => PLUS,
+            ColorSymbol::Square => SQUARE,
+            ColorSymbol::Bar => BAR,
+            ColorSymbol::Pillar => PILLAR,
+        }
+    }
+}
+
+/// [`Resource`] holding the palette the light colors are drawn with, which starts out as
+/// `color_palette` in the `assist_config` and can be changed in the settings.
+#[derive(Resource, Debug)]
+pub struct ColorPalette {
+    pub mode: PaletteMode,
+}
+
+impl FromWorld for ColorPalette {
+    fn from_world(world: &mut World) -> Self {
+        ColorPalette {
+            mode: world.resource::<Config>().assist_config.color_palette,
+        }
+    }
+}
+
+impl ColorPalette {
+    pub fn lighting_color(&self, color: LightColor) -> Vec3 {
+        palette_rgb(self.mode, color, ColorRole::Lighting)
+    }
+
+    pub fn light_beam_color(&self, color: LightColor) -> Color {
+        rgb_to_color(palette_rgb(self.mode, color, ColorRole::LightBeam))
+    }
+
+    pub fn indicator_color(&self, color: LightColor) -> Color {
+        rgb_to_color(palette_rgb(self.mode, color, ColorRole::Indicator))
+    }
+
+    /// The row of the crystal shard sprite sheet drawn for shards of `color`. The sheet only has
+    /// the standard colors, which is why the colorblind palette adds symbols instead.
+    pub fn shard_row(&self, color: LightColor) -> usize {
+        match color {
+            LightColor::Blue => 0,
+            LightColor::Green => 1,
+            LightColor::Purple => 2,
+            LightColor::White => 3,
+            LightColor::Black => 4,
+        }
+    }
+
+    /// Whether a [`ColorSymbol`] is drawn on each shard.
+    pub fn shows_symbols(&self) -> bool {
+        self.mode == PaletteMode::Colorblind
+    }
+}
+
+/// [`System`] that recolors the light beam materials when the palette changes.
+pub fn update_light_materials(
+    palette: Res<ColorPalette>,
+    render_data: Res<LightRenderData>,
+    mut materials: ResMut<Assets<LightMaterial>>,
+) {
+    for (color, material) in render_data.material_map.iter() {
+        if let Some(material) = materials.get_mut(&material.0) {
+            material.color = palette.light_beam_color(color).into();
+        }
+    }
+}
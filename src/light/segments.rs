@@ -2,15 +2,21 @@ use bevy::{prelude::*, utils::HashMap};
 use bevy_rapier2d::prelude::*;
 
 use super::{
+    palette::ColorPalette,
     render::{LightMaterial, LightRenderData},
     BlackRayComponent, LightBeamSource, LightColor, LightSegmentZMarker, LIGHT_SPEED,
 };
 use crate::{
     camera::HIGHRES_LAYER,
-    level::{mirror::Mirror, sensor::LightSensor},
+    config::Config,
+    level::{
+        lens::Lens, light_gate::LightGate, light_switch::LightSwitch, mirror::Mirror,
+        sensor::LightSensor,
+    },
     lighting::LineLight2d,
     particle::spark::SparkExplosionEvent,
     shared::GroupLabel,
+    sound::AudioSettings,
 };
 
 /// Marker [`Component`] used to query for light segments.
@@ -73,6 +79,39 @@ pub struct LightBeamPlayback {
     pub intersections: Vec<LightBeamIntersection>,
     pub end_point: Option<Vec2>,
     pub elapsed_time: f32,
+    /// How many raycasts it took to play the beam
+    pub raycasts: usize,
+    /// Whether the beam was cut short by the max reflection depth while it still had bounces left
+    pub hit_depth_limit: bool,
+    /// Where the beam went into a [`Lens`] of another color, which ends the beam
+    pub refraction: Option<LensRefraction>,
+}
+
+/// Where a light beam comes out of a [`Lens`] of a different color, as a beam of the lens's color
+/// continuing in the same direction. See [`update_lens_beams`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LensRefraction {
+    pub lens: Entity,
+    /// Where the beam leaves the far side of the lens
+    pub exit: Vec2,
+    pub dir: Vec2,
+    pub color: LightColor,
+}
+
+/// [`Component`] with where the beam of a [`LightBeamSource`] went into a [`Lens`] on the last
+/// [`simulate_light_sources`], once the beam has reached it.
+#[derive(Default, Component, Debug)]
+pub struct LightBeamRefraction(pub Option<LensRefraction>);
+
+/// [`Component`] for a [`LightBeamSource`] continuing another beam out of a [`Lens`], which is
+/// despawned once that beam stops going into the lens.
+#[derive(Component, Debug)]
+pub struct LensBeam {
+    /// The source of the beam going into the lens
+    pub parent: Entity,
+    pub lens: Entity,
+    /// How many lenses the light went through before this one
+    depth: usize,
 }
 
 impl LightBeamPlayback {
@@ -95,17 +134,39 @@ pub struct PrevLightBeamPlayback {
     pub intersections: Vec<Option<LightBeamIntersection>>,
 }
 
-const LIGHT_MAX_SEGMENTS: usize = 15;
+/// How close to parallel a beam can be to a mirror before it counts as hitting the mirror edge-on,
+/// in which case it passes through unchanged
+const MIRROR_EDGE_ON_EPSILON: f32 = 0.01;
+
+/// How far past the surface of a [`Lens`] a beam coming out of it starts, so it doesn't start
+/// inside the lens
+const LENS_EPSILON: f32 = 0.01;
+
+/// How many lenses in a row can recolor the same light. This stops lenses reflected into each other
+/// by mirrors from splitting the light into new beams forever
+pub const MAX_LENS_CHAIN: usize = 8;
+
+/// Where a ray going into `lens` at `entry` in the direction `dir` comes out of the far side.
+fn lens_exit(rapier_context: &RapierContext, lens: Entity, entry: Vec2, dir: Vec2) -> Vec2 {
+    let inside = entry + dir * LENS_EPSILON;
+    let only_lens = |entity| entity == lens;
+    // rays cast from inside a shape that isn't solid hit where they leave it
+    let toi = rapier_context
+        .cast_ray(
+            inside,
+            dir,
+            f32::MAX,
+            false,
+            QueryFilter::new().predicate(&only_lens),
+        )
+        .map_or(0.0, |(_, toi)| toi);
+    inside + dir * (toi + LENS_EPSILON)
+}
 
-pub fn play_light_beam(
-    rapier_context: &mut RapierContext,
-    source: &LightBeamSource,
-    black_ray_qry: &Query<(Entity, &BlackRayComponent)>,
-    q_mirrors: &Query<&Mirror>,
-) -> LightBeamPlayback {
-    let mut ray_pos = source.start_pos;
-    let mut ray_dir = source.start_dir;
-    let collision_groups = match source.color {
+/// The [`CollisionGroups`] of the rays cast for light beams of `color`, which stop at terrain,
+/// platforms, light sensors and the beams of colors that block them.
+pub fn light_ray_collision_groups(color: LightColor) -> CollisionGroups {
+    match color {
         LightColor::White => CollisionGroups::new(
             GroupLabel::WHITE_RAY,
             GroupLabel::TERRAIN | GroupLabel::PLATFORM | GroupLabel::LIGHT_SENSOR,
@@ -130,7 +191,23 @@ pub fn play_light_beam(
                 | GroupLabel::WHITE_RAY
                 | GroupLabel::BLACK_RAY,
         ),
-    };
+    }
+}
+
+/// Plays a light beam from its source, bouncing it at most `max_segments - 1` times. Beams pass
+/// through [`Lens`]es of their own color, and end at lenses of any other color, see
+/// [`LightBeamPlayback::refraction`].
+pub fn play_light_beam(
+    rapier_context: &mut RapierContext,
+    source: &LightBeamSource,
+    black_ray_qry: &Query<(Entity, &BlackRayComponent)>,
+    q_mirrors: &Query<&Mirror>,
+    q_lenses: &Query<&Lens>,
+    max_segments: usize,
+) -> LightBeamPlayback {
+    let mut ray_pos = source.start_pos;
+    let mut ray_dir = source.start_dir;
+    let collision_groups = light_ray_collision_groups(source.color);
 
     let mut ray_qry = QueryFilter::new().groups(collision_groups);
     let mut remaining_time = source.time_traveled;
@@ -139,6 +216,9 @@ pub fn play_light_beam(
         intersections: vec![],
         end_point: None,
         elapsed_time: 0.0,
+        raycasts: 0,
+        hit_depth_limit: false,
+        refraction: None,
     };
 
     // for _ in 0..source.color.num_bounces() + 1 {
@@ -146,7 +226,12 @@ pub fn play_light_beam(
 
     let mut i = 0;
     let mut extra_bounces_from_mirror = 0;
-    while i < num_segments + extra_bounces_from_mirror && i < LIGHT_MAX_SEGMENTS {
+    while i < num_segments + extra_bounces_from_mirror {
+        if i >= max_segments {
+            playback.hit_depth_limit = true;
+            break;
+        }
+        playback.raycasts += 1;
         let Some((entity, intersection)) =
             rapier_context.cast_ray_and_get_normal(ray_pos, ray_dir, remaining_time, true, ray_qry)
         else {
@@ -156,8 +241,36 @@ pub fn play_light_beam(
             break;
         };
         if q_mirrors.contains(entity) {
+            if ray_dir.dot(intersection.normal).abs() < MIRROR_EDGE_ON_EPSILON {
+                playback.elapsed_time += intersection.time_of_impact;
+                remaining_time -= intersection.time_of_impact;
+                ray_pos = intersection.point;
+                ray_qry = ray_qry.exclude_collider(entity);
+                continue;
+            }
             extra_bounces_from_mirror += 1;
         }
+        if let Ok(lens) = q_lenses.get(entity) {
+            playback.elapsed_time += intersection.time_of_impact;
+            remaining_time -= intersection.time_of_impact;
+            ray_pos = intersection.point;
+            ray_qry = ray_qry.exclude_collider(entity);
+            if lens.color == source.color {
+                continue;
+            }
+            playback.intersections.push(LightBeamIntersection {
+                entity,
+                point: intersection.point,
+                time: playback.elapsed_time,
+            });
+            playback.refraction = Some(LensRefraction {
+                lens: entity,
+                exit: lens_exit(rapier_context, entity, ray_pos, ray_dir),
+                dir: ray_dir,
+                color: lens.color,
+            });
+            break;
+        }
 
         // if inside something???
         let mut ignore_entity = true;
@@ -189,9 +302,60 @@ pub fn play_light_beam(
     playback
 }
 
+/// [`Resource`] with how much the light beams cost to simulate in the last [`FixedUpdate`], shown
+/// in the debug ui.
+#[derive(Resource, Default, Debug)]
+pub struct LightBeamStats {
+    pub beams: usize,
+    pub raycasts: usize,
+    /// The most intersections any one beam had
+    pub max_depth: usize,
+    /// How many beams were cut short by the max reflection depth
+    pub depth_limit_hits: usize,
+}
+
+impl LightBeamStats {
+    fn record(&mut self, playback: &LightBeamPlayback) {
+        self.beams += 1;
+        self.raycasts += playback.raycasts;
+        self.max_depth = self.max_depth.max(playback.intersections.len());
+        if playback.hit_depth_limit {
+            self.depth_limit_hits += 1;
+        }
+    }
+}
+
 #[derive(Default, Component)]
 pub struct LightBeamPoints(Vec<Vec2>);
 
+impl LightBeamPoints {
+    /// The points the beam passes through, from its source to where it ends.
+    pub fn points(&self) -> &[Vec2] {
+        &self.0
+    }
+}
+
+/// Marks `entity` as hit, or no longer hit, by a beam of `color`, if it's a [`LightSensor`], a
+/// [`LightGate`] or a [`LightSwitch`].
+fn set_hit_by(
+    q_light_sensor: &mut Query<&mut LightSensor>,
+    q_light_gate: &mut Query<&mut LightGate>,
+    q_light_switch: &mut Query<&mut LightSwitch>,
+    entity: Entity,
+    color: LightColor,
+    hit: bool,
+) {
+    if let Ok(mut sensor) = q_light_sensor.get_mut(entity) {
+        sensor.hit_by[color] = hit;
+    }
+    if let Ok(mut gate) = q_light_gate.get_mut(entity) {
+        gate.hit_by[color] = hit;
+    }
+    if let Ok(mut switch) = q_light_switch.get_mut(entity) {
+        switch.hit_by[color] = hit;
+    }
+}
+
 /// [`System`] that runs on [`Update`], calculating the [`Transform`] of light segments from the
 /// corresponding [`LightBeamSource`]. Note that this calculation happens every frame, so instead of
 /// rapidly spawning/despawning the entities, we spawn them and cache them in the
@@ -206,11 +370,16 @@ pub fn simulate_light_sources(
     q_black_ray: Query<(Entity, &BlackRayComponent)>,
     mut q_rapier: Query<&mut RapierContext>,
     mut q_light_sensor: Query<&mut LightSensor>,
+    mut q_light_gate: Query<&mut LightGate>,
+    mut q_light_switch: Query<&mut LightSwitch>,
     // used to tell if a collision was against a white beam (a different sound is played)
     q_segments: Query<&LightSegment, Without<LightSegmentZMarker>>,
     light_bounce_sfx: Local<LightBounceSfx>,
-    q_mirrors: Query<&Mirror>,
+    (q_mirrors, q_lenses): (Query<&Mirror>, Query<&Lens>),
     mut ev_spark_explosion: EventWriter<SparkExplosionEvent>,
+    config: Res<Config>,
+    mut stats: ResMut<LightBeamStats>,
+    audio_settings: Res<AudioSettings>,
 ) {
     let Ok(rapier_context) = q_rapier.get_single_mut() else {
         return;
@@ -218,8 +387,17 @@ pub fn simulate_light_sources(
     // Reborrow!!!
     let rapier_context = rapier_context.into_inner();
 
+    *stats = LightBeamStats::default();
     for (source_entity, mut source, mut prev_playback) in q_light_sources.iter_mut() {
-        let playback = play_light_beam(rapier_context, &source, &q_black_ray, &q_mirrors);
+        let playback = play_light_beam(
+            rapier_context,
+            &source,
+            &q_black_ray,
+            &q_mirrors,
+            &q_lenses,
+            config.light_config.max_reflection_depth,
+        );
+        stats.record(&playback);
         let mut pts: Vec<Vec2> = playback.iter_points(&source).collect();
 
         let intersections = playback.intersections.len();
@@ -243,9 +421,14 @@ pub fn simulate_light_sources(
                 // handle remove before add because it could be the case that both are true
                 if remove_intersection {
                     pts[i + 1] = prev_x.unwrap().point;
-                    if let Ok(mut sensor) = q_light_sensor.get_mut(prev_x.unwrap().entity) {
-                        sensor.hit_by[source.color] = false;
-                    }
+                    set_hit_by(
+                        &mut q_light_sensor,
+                        &mut q_light_gate,
+                        &mut q_light_switch,
+                        prev_x.unwrap().entity,
+                        source.color,
+                        false,
+                    );
                     prev_playback.intersections[i] = None;
                     source.time_traveled = prev_x.unwrap().time;
 
@@ -254,17 +437,27 @@ pub fn simulate_light_sources(
                         let Some(intersection) = prev_playback.intersections[j] else {
                             continue;
                         };
-                        if let Ok(mut sensor) = q_light_sensor.get_mut(intersection.entity) {
-                            sensor.hit_by[source.color] = false;
-                        }
+                        set_hit_by(
+                            &mut q_light_sensor,
+                            &mut q_light_gate,
+                            &mut q_light_switch,
+                            intersection.entity,
+                            source.color,
+                            false,
+                        );
                     }
                 }
 
                 if add_intersection {
                     pts[i + 1] = new_x.point;
-                    if let Ok(mut sensor) = q_light_sensor.get_mut(new_x.entity) {
-                        sensor.hit_by[source.color] = true;
-                    }
+                    set_hit_by(
+                        &mut q_light_sensor,
+                        &mut q_light_gate,
+                        &mut q_light_switch,
+                        new_x.entity,
+                        source.color,
+                        true,
+                    );
                     if i >= prev_playback.intersections.len() {
                         assert!(i == prev_playback.intersections.len());
                         prev_playback.intersections.push(Some(new_x));
@@ -274,7 +467,11 @@ pub fn simulate_light_sources(
                     source.time_traveled = new_x.time;
                 }
 
-                if play_sound && source.color != LightColor::Black {
+                // beams go into lenses quietly
+                if play_sound
+                    && source.color != LightColor::Black
+                    && !q_lenses.contains(new_x.entity)
+                {
                     let reflect = match q_segments.get(new_x.entity) {
                         Ok(segment) => segment.color == LightColor::White,
                         _ => false,
@@ -295,9 +492,9 @@ pub fn simulate_light_sources(
                         pos: new_x.point,
                         color: source.color.light_beam_color(),
                     });
-                    commands
-                        .entity(new_x.entity)
-                        .with_child((AudioPlayer::new(audio), PlaybackSettings::DESPAWN));
+                    if let Some(sound) = audio_settings.sfx(audio) {
+                        commands.entity(new_x.entity).with_child(sound);
+                    }
                 }
 
                 prev_playback.intersections.truncate(i + 1);
@@ -308,7 +505,92 @@ pub fn simulate_light_sources(
                 prev_playback.intersections[i] = Some(new_x);
             }
         }
-        commands.entity(source_entity).insert(LightBeamPoints(pts));
+        // the beam only comes out of a lens once it has reached it
+        let refraction = playback
+            .refraction
+            .filter(|_| pts.last() == playback.intersections.last().map(|x| &x.point));
+        commands
+            .entity(source_entity)
+            .insert((LightBeamPoints(pts), LightBeamRefraction(refraction)));
+    }
+}
+
+pub type LensBeamSourceQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut LightBeamSource,
+        &'static PrevLightBeamPlayback,
+        Option<&'static LightBeamRefraction>,
+        Option<&'static LensBeam>,
+    ),
+>;
+
+/// [`System`] that spawns a [`LensBeam`] of the lens's color where a light beam comes out of a
+/// [`Lens`], keeping it lined up with the beam going in. Lens beams are despawned once their beam
+/// stops going into the lens, and stop hitting whatever they were hitting.
+pub fn update_lens_beams(
+    mut commands: Commands,
+    mut q_light_sources: LensBeamSourceQuery,
+    mut q_light_sensor: Query<&mut LightSensor>,
+    mut q_light_gate: Query<&mut LightGate>,
+    mut q_light_switch: Query<&mut LightSwitch>,
+) {
+    let mut refractions: HashMap<Entity, (LensRefraction, usize)> = q_light_sources
+        .iter()
+        .filter_map(|(entity, _, _, refraction, lens_beam)| {
+            let depth = lens_beam.map_or(0, |lens_beam| lens_beam.depth + 1);
+            let refraction = refraction?.0?;
+            (depth < MAX_LENS_CHAIN).then_some((entity, (refraction, depth)))
+        })
+        .collect();
+
+    for (entity, mut source, prev_playback, _, lens_beam) in q_light_sources.iter_mut() {
+        let Some(lens_beam) = lens_beam else {
+            continue;
+        };
+        match refractions.remove(&lens_beam.parent) {
+            Some((refraction, _)) if refraction.lens == lens_beam.lens => {
+                source.start_pos = refraction.exit;
+                source.start_dir = refraction.dir;
+                continue;
+            }
+            // the beam moved onto another lens, which gets a new lens beam
+            Some(refraction) => {
+                refractions.insert(lens_beam.parent, refraction);
+            }
+            None => {}
+        }
+        for intersection in prev_playback.intersections.iter().flatten() {
+            set_hit_by(
+                &mut q_light_sensor,
+                &mut q_light_gate,
+                &mut q_light_switch,
+                intersection.entity,
+                source.color,
+                false,
+            );
+        }
+        commands.entity(entity).despawn_recursive();
+    }
+
+    for (parent, (refraction, depth)) in refractions {
+        commands.spawn((
+            LightBeamSource {
+                start_pos: refraction.exit,
+                start_dir: refraction.dir,
+                time_traveled: 0.0,
+                color: refraction.color,
+            },
+            LensBeam {
+                parent,
+                lens: refraction.lens,
+                depth,
+            },
+            // only the beam's segments are drawn
+            Visibility::Hidden,
+        ));
     }
 }
 
@@ -317,7 +599,10 @@ pub fn spawn_needed_segments(
     q_light_sources: Query<(Entity, &LightBeamSource, &LightBeamPoints)>,
     mut segment_cache: ResMut<LightSegmentCache>,
     light_render_data: Res<LightRenderData>,
+    palette: Res<ColorPalette>,
+    config: Res<Config>,
 ) {
+    let max_segments = config.light_config.max_reflection_depth;
     for (entity, source, pts) in q_light_sources.iter() {
         let segments = pts.0.len() - 1;
         // lazily spawn segment entities until there are enough segments to display the light beam
@@ -328,7 +613,7 @@ pub fn spawn_needed_segments(
                 .insert(entity, (vec![], source.color));
         }
 
-        while segment_cache.segments[&entity].0.len() < segments.min(LIGHT_MAX_SEGMENTS) {
+        while segment_cache.segments[&entity].0.len() < segments.min(max_segments) {
             let id = commands
                 .spawn((
                     LightSegmentBundle {
@@ -343,7 +628,7 @@ pub fn spawn_needed_segments(
                     HIGHRES_LAYER,
                 ))
                 .with_child(LineLight2d {
-                    color: source.color.lighting_color().extend(1.0),
+                    color: palette.lighting_color(source.color).extend(1.0),
                     half_length: 10.0,
                     radius: 20.0,
                     volumetric_intensity: 0.008,
@@ -445,6 +730,23 @@ pub fn tick_light_sources(mut q_light_sources: Query<&mut LightBeamSource>) {
     }
 }
 
+/// [`System`] that despawns the cached [`LightSegment`](LightSegmentBundle)s of
+/// [`LightBeamSource`]s that were despawned, e.g. when the player's oldest beam is dropped.
+pub fn despawn_removed_source_segments(
+    mut commands: Commands,
+    mut removed_sources: RemovedComponents<LightBeamSource>,
+    mut segment_cache: ResMut<LightSegmentCache>,
+) {
+    for source_entity in removed_sources.read() {
+        let Some((segments, _)) = segment_cache.segments.remove(&source_entity) else {
+            continue;
+        };
+        for segment in segments {
+            commands.entity(segment).despawn_recursive();
+        }
+    }
+}
+
 /// [`System`] that is responsible for hiding all of the [`LightSegment`](LightSegmentBundle)s
 /// and despawning [`LightBeamSource`]s when the level changes.
 pub fn cleanup_light_sources(
@@ -474,3 +776,85 @@ pub fn cleanup_light_sources(
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    fn spawn_lens(app: &mut App, x: f32, color: LightColor) -> Entity {
+        app.world_mut()
+            .spawn((
+                Lens {
+                    color,
+                    half_extent: Vec2::new(2.0, 8.0),
+                },
+                Collider::cuboid(2.0, 8.0),
+                Sensor,
+                RigidBody::Fixed,
+                CollisionGroups::new(GroupLabel::LIGHT_SENSOR, GroupLabel::ALL),
+                Transform::from_xyz(x, 0.0, 0.0),
+            ))
+            .id()
+    }
+
+    /// Plays a beam going right from `start`, returning where it comes out of a lens.
+    fn refraction(app: &mut App, start: Vec2, color: LightColor) -> Option<LensRefraction> {
+        app.world_mut()
+            .run_system_once(
+                move |mut q_rapier: Query<&mut RapierContext>,
+                      q_black_ray: Query<(Entity, &BlackRayComponent)>,
+                      q_mirrors: Query<&Mirror>,
+                      q_lenses: Query<&Lens>| {
+                    let source = LightBeamSource {
+                        start_pos: start,
+                        start_dir: Vec2::X,
+                        time_traveled: 1000.0,
+                        color,
+                    };
+                    play_light_beam(
+                        q_rapier.single_mut().into_inner(),
+                        &source,
+                        &q_black_ray,
+                        &q_mirrors,
+                        &q_lenses,
+                        15,
+                    )
+                    .refraction
+                },
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn lenses_in_series_recolor_beams() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            HierarchyPlugin,
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(8.0),
+        ));
+        let blue_lens = spawn_lens(&mut app, 20.0, LightColor::Blue);
+        spawn_lens(&mut app, 40.0, LightColor::Blue);
+        let purple_lens = spawn_lens(&mut app, 60.0, LightColor::Purple);
+        // lets rapier pick up the colliders
+        app.update();
+        app.update();
+
+        let into_blue = refraction(&mut app, Vec2::ZERO, LightColor::Green).unwrap();
+        assert_eq!(into_blue.lens, blue_lens);
+        assert_eq!(into_blue.color, LightColor::Blue);
+        assert_eq!(into_blue.dir, Vec2::X);
+        assert!((into_blue.exit.x - 22.0).abs() < 0.1);
+
+        // the blue beam goes straight through the second blue lens
+        let into_purple = refraction(&mut app, into_blue.exit, LightColor::Blue).unwrap();
+        assert_eq!(into_purple.lens, purple_lens);
+        assert_eq!(into_purple.color, LightColor::Purple);
+        assert!((into_purple.exit.x - 62.0).abs() < 0.1);
+
+        assert!(refraction(&mut app, into_purple.exit, LightColor::Purple).is_none());
+    }
+}
@@ -7,16 +7,23 @@ use bevy_rapier2d::prelude::*;
 use camera::{CameraPlugin, HIGHRES_LAYER};
 use config::ConfigPlugin;
 use debug::DebugPlugin;
-use input::{init_cursor_world_coords, update_cursor_world_coords};
+use input::{aim_with_right_stick, init_cursor_world_coords, update_cursor_world_coords};
 use level::LevelManagementPlugin;
 use light::LightManagementPlugin;
 use lighting::DeferredLightingPlugin;
 use particle::ParticlePlugin;
 use player::PlayerManagementPlugin;
-use shared::{AnimationState, GameState, ResetLevel, UiState};
+use save::SavePlugin;
+use shared::{
+    send_game_state_changed, AnimationState, GameState, GameStateChanged, ResetLevel, UiState,
+};
 use sound::SoundPlugin;
+use telemetry::TelemetryPlugin;
+use ui::hud::HudPlugin;
+use ui::level_intro::LevelIntroPlugin;
 use ui::level_select::LevelSelectPlugin;
 use ui::pause::PausePlugin;
+use ui::save_slots::SaveSlotsPlugin;
 use ui::settings::SettingsPlugin;
 use ui::start_menu::StartMenuPlugin;
 
@@ -24,14 +31,18 @@ mod animation;
 mod camera;
 mod config;
 mod debug;
+#[cfg(test)]
+mod headless;
 mod input;
 mod level;
 mod light;
 mod lighting;
 mod particle;
 mod player;
+mod save;
 mod shared;
 mod sound;
+mod telemetry;
 mod ui;
 
 fn main() {
@@ -68,6 +79,8 @@ fn main() {
         )
         .add_plugins(bevy_mod_debugdump::CommandLineArgs)
         .add_plugins(ConfigPlugin)
+        .add_plugins(SavePlugin)
+        .add_plugins(TelemetryPlugin)
         .add_plugins(LogDiagnosticsPlugin::default())
         .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(8.0).in_fixed_schedule())
         .add_plugins(SpriteAnimationPlugin)
@@ -78,6 +91,9 @@ fn main() {
         .add_plugins(ParticlePlugin)
         .add_plugins(PausePlugin)
         .add_plugins(StartMenuPlugin)
+        .add_plugins(SaveSlotsPlugin)
+        .add_plugins(HudPlugin)
+        .add_plugins(LevelIntroPlugin)
         .add_plugins(LevelSelectPlugin)
         .add_plugins(SettingsPlugin)
         .add_plugins(CameraPlugin)
@@ -88,7 +104,12 @@ fn main() {
         .insert_state(UiState::StartMenu)
         .add_plugins(DeferredLightingPlugin)
         .add_event::<ResetLevel>()
+        .add_event::<GameStateChanged>()
+        .add_systems(PreUpdate, send_game_state_changed)
         .add_systems(Startup, init_cursor_world_coords)
-        .add_systems(Update, update_cursor_world_coords)
+        .add_systems(
+            Update,
+            (update_cursor_world_coords, aim_with_right_stick).chain(),
+        )
         .run();
 }
@@ -2,7 +2,10 @@ use bevy::{math::vec2, prelude::*};
 use bevy_rapier2d::prelude::*;
 
 use crate::{
-    animation::AnimationConfig, input::CursorWorldCoords, level::platform::cast_player_ray_shape,
+    animation::AnimationConfig,
+    config::Config,
+    input::{gamepad_pressed, CursorWorldCoords},
+    level::platform::cast_player_ray_shape,
     shared::GroupLabel,
 };
 
@@ -93,7 +96,9 @@ pub fn flip_player_direction(
         With<PlayerMarker>,
     >,
     buttons: Res<ButtonInput<MouseButton>>,
+    q_gamepads: Query<&Gamepad>,
     q_cursor: Query<&CursorWorldCoords>,
+    config: Res<Config>,
 ) {
     let Ok((mut player_sprite, player_controller_output, player_transform, player_light_inventory)) =
         q_player.get_single_mut()
@@ -104,16 +109,22 @@ pub fn flip_player_direction(
         return;
     };
 
-    if buttons.pressed(MouseButton::Left) && player_light_inventory.can_shoot() {
+    let aiming = buttons.pressed(MouseButton::Left)
+        || gamepad_pressed(&q_gamepads, config.controls_config.gamepad_shoot);
+    if aiming && player_light_inventory.can_shoot() {
         let to_cursor = cursor_coords.pos - player_transform.translation().xy();
         player_sprite.flip_x = to_cursor.x < 0.0;
         return;
     }
 
-    const PLAYER_FACING_EPSILON: f32 = 0.01;
-    if player_controller_output.desired_translation.x < -PLAYER_FACING_EPSILON {
+    if !config.player_config.flip_with_movement {
+        return;
+    }
+
+    let deadzone = config.player_config.facing_deadzone;
+    if player_controller_output.desired_translation.x < -deadzone {
         player_sprite.flip_x = true;
-    } else if player_controller_output.desired_translation.x > PLAYER_FACING_EPSILON {
+    } else if player_controller_output.desired_translation.x > deadzone {
         player_sprite.flip_x = false;
     }
 }
@@ -1,24 +1,39 @@
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 
-use bevy::{ecs::system::SystemId, input::common_conditions::input_just_pressed, prelude::*};
+use bevy::{ecs::system::SystemId, prelude::*, time::Real};
 use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
 
 use crate::{
     camera::{
-        camera_position_from_level, CameraControlType, CameraMoveEvent, CameraTransition,
-        CameraTransitionEvent,
+        camera_position_from_level, camera_position_from_level_with_scale, CameraControlType,
+        CameraMoveEvent, CameraShakeEvent, CameraTransition, CameraTransitionEvent,
+        CameraZoomEvent, MainCamera, CAMERA_HEIGHT, CAMERA_WIDTH,
     },
+    config::{Config, KillDuringAnimation, KillTransitionConfig, RepeatDeathConfig},
+    debug::not_debug_flying,
+    input::gamepad_just_pressed,
     level::{
-        entity::HurtMarker, shard::reset_shard_effects_on_kill, start_flag::StartFlag,
-        CurrentLevel, LevelSystems,
+        entity::{
+            ColorCountHazard, HazardRegistry, HazardSpeed, HazardType, HurtImmunity, HurtMarker,
+        },
+        get_ldtk_level_data, level_box_from_level,
+        room::{camera_box_at, CameraRoom},
+        shard::reset_shard_effects_on_kill,
+        shard_counter::ShardCounter,
+        speedrun::LevelTimer,
+        start_flag::StartFlag,
+        CurrentLevel, DeathTransition, LevelSystems,
     },
-    shared::{AnimationState, GameState, ResetLevel, LYRA_RESPAWN_EPSILON},
+    save::SaveData,
+    shared::{AnimationState, GameState, ResetLevel, UiState, LYRA_RESPAWN_EPSILON},
+    sound::AudioSettings,
 };
 
 use super::{
     light::{AngleMarker, PlayerLightInventory},
     movement::PlayerMovement,
+    spawn_in::SpawnIn,
     PlayerHurtMarker, PlayerMarker,
 };
 
@@ -27,13 +42,28 @@ pub struct PlayerKillPlugin;
 impl Plugin for PlayerKillPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<KillAnimationCallbacks>()
+            .init_resource::<HardcoreRun>()
+            .init_resource::<QueuedKill>()
+            .init_resource::<LastRespawn>()
+            .init_resource::<RepeatDeath>()
+            .init_resource::<DeathCounter>()
+            .init_resource::<HurtContacts>()
             .add_event::<KillPlayerEvent>()
-            .add_systems(Update, reset_player_on_kill.in_set(LevelSystems::Reset))
+            .add_event::<DeathRecorded>()
+            .add_systems(
+                Update,
+                (
+                    reset_player_on_kill,
+                    finish_dying_on_respawn,
+                    reset_hurt_contacts,
+                )
+                    .in_set(LevelSystems::Reset),
+            )
             .add_systems(
                 Update,
                 (
                     quick_reset
-                        .run_if(input_just_pressed(KeyCode::KeyR))
+                        .run_if(reset_key_pressed)
                         .run_if(in_state(GameState::Playing)),
                     // reset player will try to preserve the current color, the calculations for
                     // which depend on proper values for the current level's allowed colors
@@ -44,37 +74,191 @@ impl Plugin for PlayerKillPlugin {
             )
             .add_systems(
                 FixedUpdate,
-                kill_player_on_hurt_intersection.in_set(LevelSystems::Simulation),
+                (kill_player_on_hurt_intersection, kill_player_out_of_bounds)
+                    .run_if(not_debug_flying)
+                    .in_set(LevelSystems::Simulation),
             )
             .add_systems(
                 FixedUpdate,
-                (start_kill_animation, play_death_sound_on_kill)
-                    .run_if(on_event::<KillPlayerEvent>),
+                (
+                    send_queued_kill
+                        .run_if(in_state(GameState::Playing))
+                        .before(start_kill_animation),
+                    (
+                        record_repeat_death.before(start_kill_animation),
+                        // so animations started by the simulation this tick are known
+                        start_kill_animation.after(LevelSystems::Simulation),
+                        play_death_sound_on_kill,
+                        shake_camera_on_kill,
+                        zoom_camera_on_kill.after(start_kill_animation),
+                    )
+                        .run_if(on_event::<KillPlayerEvent>),
+                ),
             );
     }
 }
 
-/// [`System`] that will kill the player on press of the R key
-pub fn quick_reset(mut ev_kill_player: EventWriter<KillPlayerEvent>) {
-    ev_kill_player.send(KillPlayerEvent);
+/// [`Resource`] counting the player's deaths since the game was started from the save slot menu,
+/// in total and per level. Kills while already dying or animating aren't counted.
+#[derive(Resource, Default, Debug)]
+pub struct DeathCounter {
+    total: u32,
+    per_level: HashMap<LevelIid, u32>,
+}
+
+impl DeathCounter {
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+
+    /// The number of deaths in the level with the given `level_iid`.
+    pub fn level(&self, level_iid: &LevelIid) -> u32 {
+        self.per_level.get(level_iid).copied().unwrap_or(0)
+    }
+
+    /// Counts a death in the level with the given `level_iid`, returning the new total.
+    fn record(&mut self, level_iid: &LevelIid) -> u32 {
+        self.total += 1;
+        *self.per_level.entry(level_iid.clone()).or_default() += 1;
+        self.total
+    }
 }
 
+/// [`Event`] sent whenever the [`DeathCounter`] counts a death.
+#[derive(Event, Debug)]
+pub struct DeathRecorded {
+    /// The new total number of deaths
+    pub total: u32,
+}
+
+/// [`Resource`] holding the [`Real`] time the player last got back control after respawning.
+#[derive(Resource, Default)]
+pub struct LastRespawn(pub Option<Duration>);
+
+/// Reads the key from the [`Config`] every frame, so rebinding it takes effect right away.
+fn reset_key_pressed(
+    keys: Res<ButtonInput<KeyCode>>,
+    q_gamepads: Query<&Gamepad>,
+    config: Res<Config>,
+) -> bool {
+    keys.just_pressed(config.controls_config.key_reset)
+        || gamepad_just_pressed(&q_gamepads, config.controls_config.gamepad_reset)
+}
+
+/// [`System`] that will kill the player on press of the reset key, unless they only just respawned
+pub fn quick_reset(
+    mut ev_kill_player: EventWriter<KillPlayerEvent>,
+    last_respawn: Res<LastRespawn>,
+    config: Res<Config>,
+    time: Res<Time<Real>>,
+) {
+    let grace = Duration::from_millis(config.controls_config.reset_grace_millis);
+    if let Some(respawned_at) = last_respawn.0 {
+        if time.elapsed().saturating_sub(respawned_at) < grace {
+            return;
+        }
+    }
+    ev_kill_player.send(KillPlayerEvent {
+        cause: KillCause::Reset,
+        ..default()
+    });
+}
+
+/// Plays the death sound of the kill, or the default one. Does nothing in apps without audio, like
+/// the `headless` test app.
 pub fn play_death_sound_on_kill(
     mut commands: Commands,
     q_player: Query<Entity, With<PlayerMarker>>,
-    asset_server: Res<AssetServer>,
+    mut ev_kill_player: EventReader<KillPlayerEvent>,
+    asset_server: Option<Res<AssetServer>>,
+    audio_settings: Option<Res<AudioSettings>>,
 ) {
+    let (Some(asset_server), Some(audio_settings)) = (asset_server, audio_settings) else {
+        return;
+    };
+    let death_sound = ev_kill_player
+        .read()
+        .find_map(|ev| ev.death_sound.clone())
+        .unwrap_or_else(|| "sfx/death.wav".into());
     let Ok(player) = q_player.get_single() else {
         return;
     };
-    commands.entity(player).with_child((
-        AudioPlayer::new(asset_server.load("sfx/death.wav")),
-        PlaybackSettings::DESPAWN,
-    ));
+    if let Some(sound) = audio_settings.sfx(asset_server.load(death_sound)) {
+        commands.entity(player).with_child(sound);
+    }
+}
+
+/// [`System`] that shakes the camera when the player is killed, before the death transition covers
+/// the screen.
+pub fn shake_camera_on_kill(
+    config: Res<Config>,
+    mut ev_camera_shake: EventWriter<CameraShakeEvent>,
+) {
+    let camera_config = &config.camera_config;
+    if camera_config.death_shake_amplitude <= 0. || camera_config.death_shake_secs <= 0. {
+        return;
+    }
+    ev_camera_shake.send(CameraShakeEvent {
+        amplitude: camera_config.death_shake_amplitude,
+        duration: Duration::from_secs_f32(camera_config.death_shake_secs),
+    });
 }
 
+/// [`System`] that zooms the camera in on where the player died over the death transition's
+/// slide to black, if the transition was started. The respawn moves the camera and resets the zoom
+/// instantly, which stops these animations, so they never hold up the transition's callbacks.
+#[allow(clippy::too_many_arguments)]
+pub fn zoom_camera_on_kill(
+    next_anim_state: Res<NextState<AnimationState>>,
+    current_level: Res<CurrentLevel>,
+    config: Res<Config>,
+    repeat_death: Res<RepeatDeath>,
+    q_player: Query<&Transform, With<PlayerMarker>>,
+    q_rooms: Query<&CameraRoom>,
+    mut ev_move_camera: EventWriter<CameraMoveEvent>,
+    mut ev_zoom_camera: EventWriter<CameraZoomEvent>,
+) {
+    let camera_config = &config.camera_config;
+    let transition_started = matches!(
+        *next_anim_state,
+        NextState::Pending(AnimationState::Respawn)
+    );
+    if !camera_config.death_zoom || !transition_started || current_level.keep_camera_on_respawn {
+        return;
+    }
+    let Ok(player_transform) = q_player.get_single() else {
+        return;
+    };
+    let scale = camera_config.death_zoom_scale.clamp(0.1, 1.0);
+    let death_pos = player_transform.translation.xy();
+    let camera_box = camera_box_at(q_rooms.iter(), current_level.level_box, death_pos);
+    let variant = || CameraControlType::Animated {
+        duration: repeat_death.slide_to_black(&config.kill_transition_config),
+        ease_fn: EaseFunction::SineIn,
+        callback: None,
+    };
+    ev_move_camera.send(CameraMoveEvent {
+        to: camera_position_from_level_with_scale(camera_box, death_pos, scale),
+        variant: variant(),
+    });
+    ev_zoom_camera.send(CameraZoomEvent {
+        scale,
+        variant: variant(),
+    });
+}
+
+pub type MainCameraQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Transform, &'static OrthographicProjection),
+    (With<MainCamera>, Without<PlayerMarker>),
+>;
+
 /// [`System`] that runs on [`GameState::Respawning`]. Will turn the state back into playing
-/// immediately.
+/// immediately. The player respawns at the last checkpoint touched in the level, or at the start
+/// flag if there is none. The camera is moved to the respawn point, unless the level keeps the
+/// camera on respawn and the respawn point is already in view.
+#[allow(clippy::too_many_arguments)]
 pub fn reset_player_on_kill(
     mut commands: Commands,
     // angle marker despawn should realistically happen in a diff system?
@@ -83,54 +267,104 @@ pub fn reset_player_on_kill(
     q_start_flag: Query<(&StartFlag, &EntityInstance)>,
     current_level: Res<CurrentLevel>,
     mut ev_move_camera: EventWriter<CameraMoveEvent>,
-    mut q_player: Query<&mut Transform, With<PlayerMarker>>,
+    mut q_player: Query<
+        (
+            &mut Transform,
+            &mut Sprite,
+            &mut PlayerMovement,
+            &mut KinematicCharacterController,
+        ),
+        With<PlayerMarker>,
+    >,
+    q_rooms: Query<&CameraRoom>,
+    q_camera: MainCameraQuery,
 ) {
     // check that we recieved a ResetLevel event asking us to Respawn
     if !ev_reset_level.read().any(|x| *x == ResetLevel::Respawn) {
         return;
     }
-    let Ok(mut player_transform) = q_player.get_single_mut() else {
+    let Ok((mut player_transform, mut player_sprite, mut movement, mut controller)) =
+        q_player.get_single_mut()
+    else {
         return;
     };
+    // otherwise the speed from before the death carries over into the respawn
+    movement.velocity = Vec2::ZERO;
+    movement.reset_jump_windows();
+    movement.zone_force = Vec2::ZERO;
+    controller.translation = None;
 
     if let Ok(angle_marker) = q_angle_marker.get_single() {
         commands.entity(angle_marker).despawn_recursive();
     }
 
-    for (flag, instance) in q_start_flag.iter() {
-        if current_level.level_iid == flag.level_iid {
-            player_transform.translation.x =
-                instance.world_x.expect("Lightborne uses Free world layout") as f32;
-            player_transform.translation.y =
-                -instance.world_y.expect("Lightborne uses Free world layout") as f32
-                    + LYRA_RESPAWN_EPSILON;
-            // add small height so Lyra is not stuck into the floor
-            ev_move_camera.send(CameraMoveEvent {
-                to: camera_position_from_level(
-                    current_level.level_box,
-                    player_transform.translation.xy(),
-                ),
-                variant: CameraControlType::Instant,
-            });
-            return;
+    let respawn_pos = match current_level.respawn_override {
+        // Lyra keeps facing the same way when respawning at a checkpoint
+        Some(checkpoint_pos) => checkpoint_pos,
+        None => {
+            let Some((_, instance)) = q_start_flag
+                .iter()
+                .find(|(flag, _)| flag.level_iid == current_level.level_iid)
+            else {
+                panic!("Couldn't find start flag to respawn at");
+            };
+            player_sprite.flip_x = match instance.get_bool_field("FacingLeft") {
+                Ok(facing_left) => *facing_left,
+                Err(_) => false,
+            };
+            Vec2::new(
+                instance.world_x.expect("Lightborne uses Free world layout") as f32,
+                -instance.world_y.expect("Lightborne uses Free world layout") as f32,
+            )
         }
-    }
+    };
+    player_transform.translation.x = respawn_pos.x;
+    // add small height so Lyra is not stuck into the floor
+    player_transform.translation.y = respawn_pos.y + LYRA_RESPAWN_EPSILON;
 
-    panic!("Couldn't find start flag to respawn at");
+    let player_pos = player_transform.translation.xy();
+    if current_level.keep_camera_on_respawn {
+        if let Ok((camera_transform, projection)) = q_camera.get_single() {
+            let view = Rect::from_center_size(
+                camera_transform.translation.xy(),
+                Vec2::new(CAMERA_WIDTH as f32, CAMERA_HEIGHT as f32) * projection.scale,
+            );
+            if view.contains(player_pos) {
+                return;
+            }
+        }
+    }
+    ev_move_camera.send(CameraMoveEvent {
+        to: camera_position_from_level(
+            camera_box_at(q_rooms.iter(), current_level.level_box, player_pos),
+            player_pos,
+        ),
+        variant: CameraControlType::Instant,
+    });
 }
 
-/// Resets the player inventory and movement information on a [`LevelSwitchEvent`]
+/// Resets the player inventory and movement information on a [`LevelSwitchEvent`], and restarts
+/// the [`LevelTimer`] for the new level.
 pub fn reset_player_on_level_switch(
     mut q_player: Query<(&mut PlayerMovement, &mut PlayerLightInventory), With<PlayerMarker>>,
-    current_level: Res<CurrentLevel>,
+    mut ev_reset_level: EventReader<ResetLevel>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut level_timer: ResMut<LevelTimer>,
 ) {
     let Ok((mut movement, mut inventory)) = q_player.get_single_mut() else {
         return;
     };
 
+    // checkpoints only last until the player leaves the level
+    if ev_reset_level.read().any(|ev| *ev == ResetLevel::Switching) {
+        current_level.respawn_override = None;
+        level_timer.start(current_level.level_iid.clone());
+    }
+
     let old_color = inventory.current_color;
 
     *movement = PlayerMovement::default();
+    // this also clears the color undo history
     *inventory = PlayerLightInventory::new();
 
     // if the new level has the current color as an allowed color, preserve it
@@ -141,12 +375,41 @@ pub fn reset_player_on_level_switch(
     }
 }
 
-/// Kills player upon touching a HURT_BOX
+/// [`Resource`] holding how many ticks in a row the player has been touching each hurt box for.
+#[derive(Resource, Default)]
+pub struct HurtContacts(HashMap<Entity, u32>);
+
+/// [`System`] that forgets the hurt boxes the player was touching, so every life and level starts
+/// with the full grace window.
+pub fn reset_hurt_contacts(mut hurt_contacts: ResMut<HurtContacts>) {
+    hurt_contacts.0.clear();
+}
+
+/// Kills player upon touching a HURT_BOX for longer than its [`HazardType`] allows, scaled by the
+/// [`HazardSpeed`], and at least the `hurt_grace_ticks` in the `gameplay_config`, so clipping a
+/// corner at speed isn't fatal. Only the colliders rapier's broad phase has already paired with the
+/// player are checked, so far away hurt boxes cost nothing. A [`ColorCountHazard`] is only touched
+/// while the player holds too many colors, and a hurt box with a [`HurtImmunity`] is harmless while
+/// the player's current color is its immune color.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn kill_player_on_hurt_intersection(
     rapier_context: Query<&RapierContext>,
     q_player: Query<Entity, With<PlayerHurtMarker>>,
-    q_hurt: Query<Entity, With<HurtMarker>>,
+    q_inventory: Query<&PlayerLightInventory, With<PlayerMarker>>,
+    q_hurt: Query<
+        (
+            Option<&HazardType>,
+            Option<&ColorCountHazard>,
+            Option<&HurtImmunity>,
+        ),
+        With<HurtMarker>,
+    >,
+    current_level: Res<CurrentLevel>,
+    hazard_registry: Res<HazardRegistry>,
+    hazard_speed: Res<HazardSpeed>,
     mut ev_kill_player: EventWriter<KillPlayerEvent>,
+    mut contact_ticks: ResMut<HurtContacts>,
+    config: Res<Config>,
 ) {
     let Ok(rapier) = rapier_context.get_single() else {
         return;
@@ -155,18 +418,145 @@ pub fn kill_player_on_hurt_intersection(
         return;
     };
 
-    for hurt in q_hurt.iter() {
-        if rapier.intersection_pair(player, hurt) == Some(true) {
-            ev_kill_player.send(KillPlayerEvent);
+    let inventory = q_inventory.get_single().ok();
+    let held_colors = inventory
+        .map(|inventory| inventory.held_color_count(&current_level.allowed_colors))
+        .unwrap_or(0);
+    let current_color = inventory.and_then(|inventory| inventory.current_color);
+
+    let mut touching = HashMap::new();
+    for (e1, e2, intersecting) in rapier.intersection_pairs_with(player) {
+        let other = if e1 == player { e2 } else { e1 };
+        if !intersecting {
+            continue;
+        }
+        let Ok((hazard_type, color_count_hazard, immunity)) = q_hurt.get(other) else {
+            continue;
+        };
+        if color_count_hazard.is_some_and(|hazard| !hazard.hurts(held_colors))
+            || immunity.is_some_and(|immunity| !immunity.hurts(current_color))
+        {
+            continue;
+        }
+        let params = hazard_registry.get(hazard_type);
+        let ticks = contact_ticks.0.get(&other).copied().unwrap_or(0) + 1;
+        let allowed_ticks = params
+            .map_or(0, |params| hazard_speed.scale_ticks(params.contact_ticks))
+            .max(config.gameplay_config.hurt_grace_ticks);
+        if ticks > allowed_ticks {
+            contact_ticks.0.clear();
+            ev_kill_player.send(KillPlayerEvent {
+                death_sound: params.map(|params| params.death_sound.clone()),
+                cause: KillCause::Hazard(hazard_type.copied().unwrap_or_default()),
+            });
             return;
         }
+        touching.insert(other, ticks);
+    }
+    contact_ticks.0 = touching;
+}
+
+/// The last place the player stood on the ground, used by the edge assist to put the player back
+/// after barely missing a ledge.
+#[derive(Default)]
+pub struct SafeGround {
+    pos: Vec2,
+    ticks_since_grounded: u32,
+}
+
+/// Kills the player once they have been outside of every level for longer than the configured
+/// grace period. If the edge assist is enabled and the player was on the ground very recently, they
+/// are put back where they last stood instead.
+#[allow(clippy::too_many_arguments)]
+pub fn kill_player_out_of_bounds(
+    mut q_player: Query<
+        (
+            &mut Transform,
+            &mut PlayerMovement,
+            &KinematicCharacterControllerOutput,
+        ),
+        With<PlayerMarker>,
+    >,
+    current_level: Res<CurrentLevel>,
+    ldtk_projects: Query<&LdtkProjectHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    config: Res<Config>,
+    mut safe_ground: Local<SafeGround>,
+    mut out_of_bounds_ticks: Local<u32>,
+    mut ev_kill_player: EventWriter<KillPlayerEvent>,
+) {
+    let Ok((mut player_transform, mut movement, output)) = q_player.get_single_mut() else {
+        return;
+    };
+    let player_pos = player_transform.translation.xy();
+
+    if output.grounded {
+        *safe_ground = SafeGround {
+            pos: player_pos,
+            ticks_since_grounded: 0,
+        };
+    } else {
+        safe_ground.ticks_since_grounded = safe_ground.ticks_since_grounded.saturating_add(1);
     }
+
+    if current_level.level_box.contains(player_pos) {
+        *out_of_bounds_ticks = 0;
+        return;
+    }
+    let Ok(ldtk_handle) = ldtk_projects.get_single() else {
+        return;
+    };
+    let Ok(ldtk_levels) = get_ldtk_level_data(ldtk_project_assets.into_inner(), ldtk_handle) else {
+        return;
+    };
+    // the player is moving into another level, which is handled by switch_level
+    if ldtk_levels
+        .iter()
+        .any(|level| level_box_from_level(level).contains(player_pos))
+    {
+        *out_of_bounds_ticks = 0;
+        return;
+    }
+
+    *out_of_bounds_ticks += 1;
+    if *out_of_bounds_ticks <= config.level_config.out_of_bounds_grace_ticks {
+        return;
+    }
+    *out_of_bounds_ticks = 0;
+
+    let assist = &config.assist_config;
+    if assist.edge_assist && safe_ground.ticks_since_grounded <= assist.edge_assist_grace_ticks {
+        player_transform.translation = safe_ground.pos.extend(player_transform.translation.z);
+        movement.velocity = Vec2::ZERO;
+        return;
+    }
+    ev_kill_player.send(KillPlayerEvent {
+        cause: KillCause::OutOfBounds,
+        ..default()
+    });
 }
 
 /// Systems that kill the player should send this event instead of ResetLevel::Respawn, so the
 /// transition is started.
-#[derive(Event)]
-pub struct KillPlayerEvent;
+#[derive(Event, Default)]
+pub struct KillPlayerEvent {
+    /// Sound played instead of the default death sound
+    pub death_sound: Option<String>,
+    pub cause: KillCause,
+}
+
+/// What killed the player, for anything that wants to tell deaths apart.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KillCause {
+    #[default]
+    Other,
+    /// The player pressed the reset key
+    Reset,
+    OutOfBounds,
+    /// Crushed by a moving platform
+    Crushed,
+    Hazard(HazardType),
+}
 
 #[derive(Resource)]
 pub struct KillAnimationCallbacks {
@@ -185,40 +575,518 @@ impl FromWorld for KillAnimationCallbacks {
     }
 }
 
+/// [`Resource`] that tracks where and when the player last died, so dying again and again in the
+/// same spot can use a shorter death transition.
+#[derive(Resource, Default)]
+pub struct RepeatDeath {
+    last_death: Option<(Vec2, Duration)>,
+    /// How long each half of the next death transition takes, if it is shortened
+    shortened: Option<Duration>,
+}
+
+impl RepeatDeath {
+    /// Records a death at `pos`, shortening the transition if it is close enough in space and
+    /// time to the last one.
+    fn record(&mut self, pos: Vec2, now: Duration, config: &RepeatDeathConfig) {
+        let repeated = self.last_death.is_some_and(|(last_pos, last_time)| {
+            last_pos.distance(pos) <= config.radius
+                && now.saturating_sub(last_time).as_secs_f32() <= config.window_secs
+        });
+        self.shortened =
+            (config.enabled && repeated).then(|| Duration::from_millis(config.transition_millis));
+        self.last_death = Some((pos, now));
+    }
+
+    /// How long the next death transition takes to slide to black, see [`KillTransitionConfig`].
+    fn slide_to_black(&self, config: &KillTransitionConfig) -> Duration {
+        self.shortened
+            .unwrap_or(Duration::from_millis(config.slide_to_black_ms))
+    }
+
+    /// How long the next death transition takes to slide back from black.
+    fn slide_from_black(&self, config: &KillTransitionConfig) -> Duration {
+        self.shortened
+            .unwrap_or(Duration::from_millis(config.slide_from_black_ms))
+    }
+
+    /// Whether both halves of the next death transition take no time, so it can be skipped. If
+    /// only one of them does, the transition still runs so its callbacks are called.
+    fn is_instant(&self, config: &KillTransitionConfig) -> bool {
+        self.slide_to_black(config).is_zero() && self.slide_from_black(config).is_zero()
+    }
+}
+
+pub fn record_repeat_death(
+    mut repeat_death: ResMut<RepeatDeath>,
+    q_player: Query<&Transform, With<PlayerMarker>>,
+    cur_game_state: Res<State<GameState>>,
+    config: Res<Config>,
+    time: Res<Time<Real>>,
+) {
+    // kills while animating don't start another death
+    if *cur_game_state.get() == GameState::Animating {
+        return;
+    }
+    let Ok(player_transform) = q_player.get_single() else {
+        return;
+    };
+    repeat_death.record(
+        player_transform.translation.xy(),
+        time.elapsed(),
+        &config.repeat_death_config,
+    );
+}
+
+fn death_transition_color(transition: DeathTransition) -> Color {
+    match transition {
+        DeathTransition::White => Color::WHITE,
+        _ => Color::BLACK,
+    }
+}
+
+/// [`Resource`] that tracks the lives left in the current hardcore run. In hardcore mode the
+/// player always respawns at the start of the level, since checkpoints are ignored, see
+/// [`activate_checkpoints`](crate::level::checkpoint::activate_checkpoints).
+#[derive(Resource)]
+pub struct HardcoreRun {
+    pub lives_left: u32,
+}
+
+impl FromWorld for HardcoreRun {
+    fn from_world(world: &mut World) -> Self {
+        let config = world.resource::<Config>();
+        HardcoreRun {
+            lives_left: config.hardcore_config.lives,
+        }
+    }
+}
+
+impl HardcoreRun {
+    /// Takes a life from the run, returning true if that was the last one. Always returns false
+    /// outside of hardcore mode.
+    fn lose_life(&mut self, config: &Config) -> bool {
+        if !config.hardcore_config.enabled {
+            return false;
+        }
+        self.lives_left = self.lives_left.saturating_sub(1);
+        self.lives_left == 0
+    }
+}
+
+/// Ends the hardcore run by sending the player back to the start menu, and resets the progress made
+/// during it.
+fn end_hardcore_run(
+    run: &mut HardcoreRun,
+    config: &Config,
+    shard_counter: &mut ShardCounter,
+    save_data: &mut SaveData,
+    next_game_state: &mut NextState<GameState>,
+    next_ui_state: &mut NextState<UiState>,
+) {
+    reset_hardcore_progress(run, config, shard_counter, save_data);
+    next_game_state.set(GameState::Ui);
+    next_ui_state.set(UiState::StartMenu);
+}
+
+/// Gives the run its lives back and forgets the collected shards, including the ones in the save
+/// file, so they aren't restored on the next load.
+fn reset_hardcore_progress(
+    run: &mut HardcoreRun,
+    config: &Config,
+    shard_counter: &mut ShardCounter,
+    save_data: &mut SaveData,
+) {
+    run.lives_left = config.hardcore_config.lives;
+    shard_counter.collected.clear();
+    save_data.collected_shards.clear();
+}
+
+/// [`Resource`] that tracks kills that happened while an animation was playing, so they can be
+/// applied once the player is back in control. Otherwise a kill during, for example, the shard
+/// animation would be lost, leaving the player standing in a hazard that already killed them.
+#[derive(Resource, Default, Debug)]
+pub struct QueuedKill {
+    /// The player is in the death transition and hasn't respawned yet
+    dying: bool,
+    queued: bool,
+}
+
+impl QueuedKill {
+    /// Called when the death transition starts.
+    fn start_dying(&mut self) {
+        self.dying = true;
+        self.queued = false;
+    }
+
+    /// Called when the player respawns at the end of the slide to black.
+    fn finish_dying(&mut self) {
+        self.dying = false;
+    }
+
+    /// Called for a kill that happened while animating. Kills from before the player respawned are
+    /// always dropped, since the player is already dying.
+    fn kill_while_animating(&mut self, behavior: KillDuringAnimation) {
+        if !self.dying && behavior == KillDuringAnimation::Queue {
+            self.queued = true;
+        }
+    }
+
+    /// Returns whether a kill was queued, clearing it.
+    fn take(&mut self) -> bool {
+        std::mem::take(&mut self.queued)
+    }
+}
+
+pub fn finish_dying_on_respawn(
+    mut ev_reset_level: EventReader<ResetLevel>,
+    mut queued_kill: ResMut<QueuedKill>,
+) {
+    if ev_reset_level.read().any(|ev| *ev == ResetLevel::Respawn) {
+        queued_kill.finish_dying();
+    }
+}
+
+/// [`System`] that kills the player once control returns if they were killed during an animation.
+pub fn send_queued_kill(
+    mut queued_kill: ResMut<QueuedKill>,
+    mut ev_kill_player: EventWriter<KillPlayerEvent>,
+) {
+    if queued_kill.take() {
+        ev_kill_player.send(KillPlayerEvent::default());
+    }
+}
+
+/// Starts the death transition configured for the [`CurrentLevel`]. Levels with an instant death
+/// transition, or any level while transitions are disabled, respawn the player immediately. The
+/// transition's timing comes from the [`KillTransitionConfig`], and is shortened for repeated
+/// deaths in the same spot, see [`RepeatDeath`].
+#[allow(clippy::too_many_arguments)]
 pub fn start_kill_animation(
     mut ev_transition_camera: EventWriter<CameraTransitionEvent>,
+    mut ev_reset_level: EventWriter<ResetLevel>,
     callbacks: Res<KillAnimationCallbacks>,
+    current_level: Res<CurrentLevel>,
     cur_game_state: Res<State<GameState>>,
     mut next_game_state: ResMut<NextState<GameState>>,
     mut next_anim_state: ResMut<NextState<AnimationState>>,
+    mut hardcore_run: ResMut<HardcoreRun>,
+    mut next_ui_state: ResMut<NextState<UiState>>,
+    (mut shard_counter, mut save_data): (ResMut<ShardCounter>, ResMut<SaveData>),
+    mut queued_kill: ResMut<QueuedKill>,
+    mut last_respawn: ResMut<LastRespawn>,
+    repeat_death: Res<RepeatDeath>,
+    time: Res<Time<Real>>,
+    config: Res<Config>,
+    (mut death_counter, mut ev_death_recorded): (ResMut<DeathCounter>, EventWriter<DeathRecorded>),
 ) {
-    if *cur_game_state.get() == GameState::Animating {
+    // also counts animations that start this tick, like the shard animation when the shard is
+    // collected before the kill, see HurtShardOverlap
+    let starting_animation = matches!(*next_game_state, NextState::Pending(GameState::Animating));
+    if *cur_game_state.get() == GameState::Animating || starting_animation {
+        queued_kill.kill_while_animating(config.level_config.kill_during_animation);
+        return;
+    }
+    // counted once per run, even if several kills happened in the same tick
+    ev_death_recorded.send(DeathRecorded {
+        total: death_counter.record(&current_level.level_iid),
+    });
+    if current_level.death_transition == DeathTransition::Instant
+        || config.camera_config.disable_transitions
+        || repeat_death.is_instant(&config.kill_transition_config)
+    {
+        ev_reset_level.send(ResetLevel::Respawn);
+        last_respawn.0 = Some(time.elapsed());
+        if hardcore_run.lose_life(&config) {
+            end_hardcore_run(
+                &mut hardcore_run,
+                &config,
+                &mut shard_counter,
+                &mut save_data,
+                &mut next_game_state,
+                &mut next_ui_state,
+            );
+        }
         return;
     }
     ev_transition_camera.send(CameraTransitionEvent {
-        duration: Duration::from_millis(400),
-        ease_fn: EaseFunction::SineInOut,
+        duration: repeat_death.slide_to_black(&config.kill_transition_config),
+        ease_fn: config.kill_transition_config.ease.into(),
         callback: Some(callbacks.cb1),
         effect: CameraTransition::SlideToBlack,
+        color: death_transition_color(current_level.death_transition),
     });
     next_game_state.set(GameState::Animating);
     next_anim_state.set(AnimationState::Respawn);
+    queued_kill.start_dying();
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn after_slide_to_black(
     mut ev_transition_camera: EventWriter<CameraTransitionEvent>,
     mut ev_reset_level: EventWriter<ResetLevel>,
     callbacks: Res<KillAnimationCallbacks>,
+    current_level: Res<CurrentLevel>,
+    mut hardcore_run: ResMut<HardcoreRun>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut next_ui_state: ResMut<NextState<UiState>>,
+    mut shard_counter: ResMut<ShardCounter>,
+    mut save_data: ResMut<SaveData>,
+    repeat_death: Res<RepeatDeath>,
+    config: Res<Config>,
+    mut ev_zoom_camera: EventWriter<CameraZoomEvent>,
 ) {
+    let game_over = hardcore_run.lose_life(&config);
+    if config.camera_config.death_zoom {
+        // cut back from the death zoom while the screen is covered
+        ev_zoom_camera.send(CameraZoomEvent {
+            scale: 1.,
+            variant: CameraControlType::Instant,
+        });
+    }
     ev_transition_camera.send(CameraTransitionEvent {
-        duration: Duration::from_millis(400),
-        ease_fn: EaseFunction::SineInOut,
-        callback: Some(callbacks.cb2),
+        duration: repeat_death.slide_from_black(&config.kill_transition_config),
+        ease_fn: config.kill_transition_config.ease.into(),
+        // going back to playing would leave the start menu
+        callback: (!game_over).then_some(callbacks.cb2),
         effect: CameraTransition::SlideFromBlack,
+        color: death_transition_color(current_level.death_transition),
     });
     ev_reset_level.send(ResetLevel::Respawn);
+    if game_over {
+        end_hardcore_run(
+            &mut hardcore_run,
+            &config,
+            &mut shard_counter,
+            &mut save_data,
+            &mut next_game_state,
+            &mut next_ui_state,
+        );
+    }
 }
 
-pub fn after_slide_from_black(mut next_game_state: ResMut<NextState<GameState>>) {
+pub fn after_slide_from_black(
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut last_respawn: ResMut<LastRespawn>,
+    mut spawn_in: ResMut<SpawnIn>,
+    mut q_player: Query<&mut PlayerMovement, With<PlayerMarker>>,
+    config: Res<Config>,
+    time: Res<Time<Real>>,
+) {
+    if let Ok(mut movement) = q_player.get_single_mut() {
+        movement.lock_input(config.player_config.respawn_input_lockout_ticks);
+    }
+    // the spawn-in animation returns control once it finishes
+    if spawn_in.finish_fade() {
+        return;
+    }
     next_game_state.set(GameState::Playing);
+    last_respawn.0 = Some(time.elapsed());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::LightColor;
+
+    #[test]
+    fn immune_color_survives_hurt_box() {
+        let immunity = HurtImmunity {
+            immune_color: Some(LightColor::Blue),
+        };
+        assert!(immunity.hurts(None));
+        assert!(!immunity.hurts(Some(LightColor::Blue)));
+        assert!(immunity.hurts(Some(LightColor::Green)));
+        // without an immune color it hurts like any other hurt box
+        assert!(HurtImmunity::default().hurts(Some(LightColor::Blue)));
+    }
+
+    #[test]
+    fn kill_during_slide_to_black_is_dropped() {
+        let mut queued_kill = QueuedKill::default();
+        queued_kill.start_dying();
+        queued_kill.kill_while_animating(KillDuringAnimation::Queue);
+        queued_kill.finish_dying();
+        assert!(!queued_kill.take());
+    }
+
+    #[test]
+    fn dying_immediately_on_respawn() {
+        let mut queued_kill = QueuedKill::default();
+        queued_kill.start_dying();
+        queued_kill.finish_dying();
+        // the spawn point is in a hazard, so the player is killed while the screen fades back in
+        queued_kill.kill_while_animating(KillDuringAnimation::Queue);
+        assert!(queued_kill.take());
+        // the kill is only applied once
+        assert!(!queued_kill.take());
+
+        queued_kill.start_dying();
+        queued_kill.finish_dying();
+        queued_kill.kill_while_animating(KillDuringAnimation::Ignore);
+        assert!(!queued_kill.take());
+    }
+
+    #[test]
+    fn hardcore_wipe_forgets_saved_shards() {
+        let mut config = Config::default();
+        config.hardcore_config.lives = 3;
+        let mut run = HardcoreRun { lives_left: 0 };
+        let mut shard_counter = ShardCounter::default();
+        shard_counter.collected.insert(EntityIid::new("shard"));
+        let mut save_data = SaveData::default();
+        save_data.collected_shards.insert("shard".to_string());
+
+        reset_hardcore_progress(&mut run, &config, &mut shard_counter, &mut save_data);
+        assert_eq!(run.lives_left, 3);
+        assert!(shard_counter.collected.is_empty());
+        assert!(save_data.collected_shards.is_empty());
+
+        // loading the save again doesn't bring the shards back
+        shard_counter.restore_collected(&save_data);
+        assert!(shard_counter.collected.is_empty());
+    }
+
+    #[test]
+    fn death_counter_counts_per_level() {
+        let mut death_counter = DeathCounter::default();
+        let (first, second) = (LevelIid::new("first"), LevelIid::new("second"));
+        assert_eq!(death_counter.record(&first), 1);
+        assert_eq!(death_counter.record(&first), 2);
+        assert_eq!(death_counter.record(&second), 3);
+        assert_eq!(death_counter.total(), 3);
+        assert_eq!(death_counter.level(&first), 2);
+        assert_eq!(death_counter.level(&second), 1);
+        assert_eq!(death_counter.level(&LevelIid::new("third")), 0);
+    }
+
+    #[test]
+    fn repeated_deaths_shorten_transition() {
+        let config = RepeatDeathConfig {
+            enabled: true,
+            ..default()
+        };
+        let transition = KillTransitionConfig::default();
+        let normal = Duration::from_millis(transition.slide_to_black_ms);
+        let mut repeat_death = RepeatDeath::default();
+        repeat_death.record(Vec2::ZERO, Duration::from_secs(1), &config);
+        assert_eq!(repeat_death.slide_to_black(&transition), normal);
+
+        repeat_death.record(Vec2::new(4.0, 0.0), Duration::from_secs(3), &config);
+        assert_eq!(
+            repeat_death.slide_from_black(&transition),
+            Duration::from_millis(config.transition_millis)
+        );
+
+        // dying somewhere else brings the normal transition back
+        repeat_death.record(Vec2::new(200.0, 0.0), Duration::from_secs(4), &config);
+        assert_eq!(repeat_death.slide_to_black(&transition), normal);
+
+        // and so does waiting too long
+        let later = Duration::from_secs_f32(4.0 + config.window_secs + 1.0);
+        repeat_death.record(Vec2::new(200.0, 0.0), later, &config);
+        assert_eq!(repeat_death.slide_to_black(&transition), normal);
+    }
+
+    #[test]
+    fn zero_duration_halves_keep_the_transition() {
+        let repeat_death = RepeatDeath::default();
+        let mut transition = KillTransitionConfig {
+            slide_to_black_ms: 0,
+            ..default()
+        };
+        assert!(!repeat_death.is_instant(&transition));
+        transition.slide_from_black_ms = 0;
+        assert!(repeat_death.is_instant(&transition));
+    }
+
+    /// The hurt box check from before it used the broad phase, kept to compare against.
+    fn check_every_hurt_box(
+        rapier_context: Query<&RapierContext>,
+        q_player: Query<Entity, With<PlayerHurtMarker>>,
+        q_hurt: Query<Entity, With<HurtMarker>>,
+        mut ev_kill_player: EventWriter<KillPlayerEvent>,
+    ) {
+        let rapier = rapier_context.single();
+        let player = q_player.single();
+        for hurt in q_hurt.iter() {
+            if rapier.intersection_pair(player, hurt) == Some(true) {
+                ev_kill_player.send(KillPlayerEvent::default());
+                return;
+            }
+        }
+    }
+
+    /// Average time it takes to run `system` once.
+    fn time_system<M>(
+        app: &mut App,
+        system: impl IntoSystem<(), (), M> + 'static,
+        runs: u32,
+    ) -> Duration {
+        let system = app.world_mut().register_system(system);
+        let start = std::time::Instant::now();
+        for _ in 0..runs {
+            app.world_mut().run_system(system).unwrap();
+        }
+        start.elapsed() / runs
+    }
+
+    /// Times the hurt box check in a level with far too many hazards, none of them near the player.
+    /// Run with `cargo test bench_hurt_box_check -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_hurt_box_check() {
+        const HURT_BOXES: usize = 10_000;
+        const RUNS: u32 = 1_000;
+
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            HierarchyPlugin,
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(8.0),
+        ))
+        .insert_resource(Config::default())
+        .init_resource::<CurrentLevel>()
+        .init_resource::<HazardRegistry>()
+        .init_resource::<HazardSpeed>()
+        .init_resource::<HurtContacts>()
+        .add_event::<KillPlayerEvent>();
+
+        app.world_mut().spawn((
+            Collider::cuboid(4.0, 5.0),
+            Sensor,
+            RigidBody::Dynamic,
+            GravityScale(0.0),
+            PlayerHurtMarker,
+            Transform::default(),
+        ));
+        for i in 0..HURT_BOXES {
+            let pos = Vec2::new((i % 100) as f32, (i / 100) as f32) * 16.0 + 100.0;
+            app.world_mut().spawn((
+                Collider::cuboid(4.0, 4.0),
+                RigidBody::Fixed,
+                HurtMarker,
+                HazardType::Spike,
+                Transform::from_translation(pos.extend(0.0)),
+            ));
+        }
+        // lets rapier pick up the colliders and run its broad phase
+        app.update();
+        app.update();
+
+        let every_hurt_box = time_system(&mut app, check_every_hurt_box, RUNS);
+        let broad_phase = time_system(&mut app, kill_player_on_hurt_intersection, RUNS);
+        println!(
+            "{HURT_BOXES} hurt boxes: {every_hurt_box:?} checking every hurt box, \
+            {broad_phase:?} with the broad phase"
+        );
+        assert!(app.world().resource::<Events<KillPlayerEvent>>().is_empty());
+    }
+
+    #[test]
+    fn kill_during_other_animation_is_queued() {
+        let mut queued_kill = QueuedKill::default();
+        queued_kill.kill_while_animating(KillDuringAnimation::Queue);
+        assert!(queued_kill.take());
+    }
 }
@@ -1,7 +1,12 @@
 use bevy::prelude::*;
 use enum_map::{enum_map, EnumMap};
 
-use crate::{camera::HIGHRES_LAYER, level::LevelSystems, light::LightColor, player::PlayerMarker};
+use crate::{
+    camera::HIGHRES_LAYER,
+    level::LevelSystems,
+    light::{palette::ColorPalette, LightColor},
+    player::PlayerMarker,
+};
 
 use super::PlayerLightInventory;
 
@@ -14,7 +19,11 @@ impl Plugin for LightIndicatorPlugin {
                 PreUpdate,
                 add_light_indicator.in_set(LevelSystems::Processing),
             )
-            .add_systems(FixedUpdate, update_light_indicator);
+            .add_systems(FixedUpdate, update_light_indicator)
+            .add_systems(
+                Update,
+                update_light_indicator_palette.run_if(resource_changed::<ColorPalette>),
+            );
     }
 }
 
@@ -49,6 +58,24 @@ impl FromWorld for LightIndicatorData {
     }
 }
 
+/// [`System`] that recolors the light indicator materials when the palette changes.
+pub fn update_light_indicator_palette(
+    palette: Res<ColorPalette>,
+    indicator_data: Res<LightIndicatorData>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (color, material) in indicator_data.material_map.iter() {
+        if let Some(material) = materials.get_mut(&material.0) {
+            material.color = palette.indicator_color(color);
+        }
+    }
+    for (color, material) in indicator_data.dimmed_material_map.iter() {
+        if let Some(material) = materials.get_mut(&material.0) {
+            material.color = palette.indicator_color(color).with_alpha(0.15);
+        }
+    }
+}
+
 /// [`System`] that spawns the player's hurtbox [`Collider`] as a child entity.
 // mut commands: Commands - needed for safely creating/removing data in the ECS World
 pub fn add_light_indicator(
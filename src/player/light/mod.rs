@@ -1,6 +1,6 @@
 use bevy::{
     input::{
-        common_conditions::{input_just_pressed, input_just_released, input_pressed},
+        common_conditions::{input_just_pressed, input_just_released},
         mouse::MouseWheel,
     },
     prelude::*,
@@ -11,21 +11,28 @@ use itertools::Itertools;
 use ui::LightUiPlugin;
 
 use bevy::prelude::ops::{cos, sin};
-use std::f32::consts::PI;
+use std::{collections::VecDeque, f32::consts::PI};
 
 use crate::{
     camera::{HIGHRES_LAYER, TERRAIN_LAYER},
-    input::{update_cursor_world_coords, CursorWorldCoords},
-    level::{mirror::Mirror, CurrentLevel, LevelSystems},
+    config::{Config, DisallowedColorBehavior},
+    input::{
+        aim_with_right_stick, cancel_shoot_just_pressed, shoot_just_pressed, shoot_just_released,
+        shoot_pressed, CursorWorldCoords,
+    },
+    level::{lens::Lens, mirror::Mirror, CurrentLevel, LevelSystems},
     light::{
-        segments::{play_light_beam, PrevLightBeamPlayback},
+        segments::{play_light_beam, PrevLightBeamPlayback, MAX_LENS_CHAIN},
         BlackRayComponent, LightBeamSource, LightColor, LightSourceZMarker,
     },
     lighting::LineLight2d,
+    shared::GameState,
 };
 use indicator::LightIndicatorPlugin;
+use reticle::AimReticlePlugin;
 
 mod indicator;
+mod reticle;
 mod ui;
 
 const NUMINCREMENTS: i32 = 16; // The number of angle increments for light beam alignment
@@ -38,42 +45,44 @@ impl Plugin for PlayerLightPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(LightIndicatorPlugin)
             .add_plugins(LightUiPlugin)
+            .add_plugins(AimReticlePlugin)
+            .init_resource::<AimTimeScale>()
+            .add_systems(Update, update_aim_time_scale)
             .add_systems(
                 Update,
                 (
                     handle_color_switch,
-                    should_shoot_light::<true>.run_if(input_just_pressed(MouseButton::Left)),
-                    should_shoot_light::<false>.run_if(input_just_pressed(MouseButton::Right)),
+                    update_disallowed_color,
+                    should_shoot_light::<true>.run_if(shoot_just_pressed),
+                    should_shoot_light::<false>.run_if(cancel_shoot_just_pressed),
                     preview_light_path,
                     spawn_angle_indicator.run_if(
-                        input_just_pressed(MouseButton::Left).or((input_just_released(
-                            KeyCode::ShiftLeft,
-                        )
-                        .or(input_just_released(KeyCode::ShiftRight)))
-                        .and(input_pressed(MouseButton::Left))),
+                        shoot_just_pressed.or((input_just_released(KeyCode::ShiftLeft)
+                            .or(input_just_released(KeyCode::ShiftRight)))
+                        .and(shoot_pressed)),
                     ),
                     despawn_angle_indicator.run_if(
-                        input_just_released(MouseButton::Left)
-                            .or(input_just_pressed(MouseButton::Right))
+                        shoot_just_released
+                            .or(cancel_shoot_just_pressed)
                             .or(input_just_pressed(KeyCode::ShiftLeft))
                             .or(input_just_pressed(KeyCode::ShiftRight)),
                     ),
                     spawn_angle_increments_indicators.run_if(
                         input_just_pressed(KeyCode::ShiftLeft)
                             .or(input_just_pressed(KeyCode::ShiftRight))
-                            .and(input_pressed(MouseButton::Left)),
+                            .and(shoot_pressed),
                     ),
                     despawn_angle_increments_indicators.run_if(
                         input_just_released(KeyCode::ShiftLeft)
                             .or(input_just_released(KeyCode::ShiftRight))
-                            .or(input_just_pressed(MouseButton::Right)),
+                            .or(cancel_shoot_just_pressed),
                     ),
-                    shoot_light.run_if(input_just_released(MouseButton::Left)),
+                    shoot_light.run_if(shoot_just_released),
                 )
                     .chain()
                     .run_if(not_input_locked)
                     .in_set(LevelSystems::Simulation)
-                    .after(update_cursor_world_coords),
+                    .after(aim_with_right_stick),
             );
     }
 }
@@ -85,8 +94,12 @@ pub struct PlayerLightInventory {
     /// set to true when LMB is clicked, set to false when RMB is clicked/LMB is released
     should_shoot: bool,
     pub current_color: Option<LightColor>,
+    /// The color held before the last color switch, used to undo it
+    pub previous_color: Option<LightColor>,
     /// Is true if the color is available
     pub sources: EnumMap<LightColor, bool>,
+    /// The beams shot by the player that are still active, oldest first
+    pub active_beams: VecDeque<(Entity, LightColor)>,
 }
 
 impl PlayerLightInventory {
@@ -94,6 +107,7 @@ impl PlayerLightInventory {
         PlayerLightInventory {
             should_shoot: false,
             current_color: None,
+            previous_color: None,
             sources: enum_map! {
                 LightColor::Green =>true,
                 LightColor::Blue => true,
@@ -101,12 +115,40 @@ impl PlayerLightInventory {
                 LightColor::White =>true,
                 LightColor::Black => true,
             },
+            active_beams: VecDeque::new(),
+        }
+    }
+
+    /// Records a beam that was just shot. If that puts the player over `max_beams`, the oldest
+    /// beam is dropped from the inventory and returned so it can be despawned, and its color can be
+    /// shot again. A `max_beams` of 0 means there is no limit.
+    pub fn push_beam(
+        &mut self,
+        entity: Entity,
+        color: LightColor,
+        max_beams: usize,
+    ) -> Option<Entity> {
+        self.active_beams.push_back((entity, color));
+        if max_beams == 0 || self.active_beams.len() <= max_beams {
+            return None;
         }
+        let (oldest, oldest_color) = self.active_beams.pop_front()?;
+        self.sources[oldest_color] = true;
+        Some(oldest)
     }
 
     pub fn can_shoot(&self) -> bool {
         self.should_shoot && self.current_color.is_some_and(|color| self.sources[color])
     }
+
+    /// How many colors the player is holding, which are the colors allowed in the level that
+    /// haven't been shot yet.
+    pub fn held_color_count(&self, allowed_colors: &EnumMap<LightColor, bool>) -> usize {
+        self.sources
+            .iter()
+            .filter(|(color, has_source)| **has_source && allowed_colors[*color])
+            .count()
+    }
 }
 
 #[derive(Component)]
@@ -137,6 +179,33 @@ pub fn spawn_angle_indicator(
     ));
 }
 
+/// [`Resource`] for the setting that slows down the world while the player is aiming. How much it
+/// is slowed down is set by `aim_time_scale` in the [`Config`].
+#[derive(Default, Resource)]
+pub struct AimTimeScale {
+    pub enabled: bool,
+}
+
+/// [`System`] that scales [`Virtual`] time while the [`AngleMarker`] is shown. Since the
+/// [`FixedUpdate`] schedule is driven by virtual time, this slows down (or freezes) the simulation,
+/// while aiming itself runs in [`Update`] and stays responsive.
+pub fn update_aim_time_scale(
+    aim_time_scale: Res<AimTimeScale>,
+    q_angle: Query<(), With<AngleMarker>>,
+    game_state: Res<State<GameState>>,
+    config: Res<Config>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    let aiming = aim_time_scale.enabled && !q_angle.is_empty() && *game_state == GameState::Playing;
+    let speed = match aiming {
+        true => config.assist_config.aim_time_scale.clamp(0.0, 1.0),
+        false => 1.0,
+    };
+    if time.relative_speed() != speed {
+        time.set_relative_speed(speed);
+    }
+}
+
 #[derive(Component)]
 pub struct AngleIncrementMarker;
 
@@ -187,9 +256,18 @@ pub fn despawn_angle_increments_indicators(
     }
 }
 
+static COLOR_BINDS: [(KeyCode, LightColor); 5] = [
+    (KeyCode::Digit1, LightColor::Green),
+    (KeyCode::Digit2, LightColor::Purple),
+    (KeyCode::Digit3, LightColor::White),
+    (KeyCode::Digit4, LightColor::Blue),
+    (KeyCode::Digit5, LightColor::Black),
+];
+
 /// [`System`] to handle the keyboard presses corresponding to color switches.
 pub fn handle_color_switch(
     keys: Res<ButtonInput<KeyCode>>,
+    config: Res<Config>,
     mut ev_scroll: EventReader<MouseWheel>,
     mut q_inventory: Query<&mut PlayerLightInventory, With<PlayerMarker>>,
     current_level: Res<CurrentLevel>,
@@ -197,14 +275,7 @@ pub fn handle_color_switch(
     let Ok(mut inventory) = q_inventory.get_single_mut() else {
         return;
     };
-
-    static COLOR_BINDS: [(KeyCode, LightColor); 5] = [
-        (KeyCode::Digit1, LightColor::Green),
-        (KeyCode::Digit2, LightColor::Purple),
-        (KeyCode::Digit3, LightColor::White),
-        (KeyCode::Digit4, LightColor::Blue),
-        (KeyCode::Digit5, LightColor::Black),
-    ];
+    let old_color = inventory.current_color;
 
     let mut cur_index = match inventory.current_color {
         None => -1,
@@ -239,6 +310,57 @@ pub fn handle_color_switch(
             inventory.current_color = Some(color);
         }
     }
+
+    if keys.just_pressed(config.controls_config.key_undo_color) {
+        if let Some(previous) = inventory.previous_color {
+            // the previous color may have been disallowed since, e.g. after a shard was reset
+            if current_level.allowed_colors[previous] {
+                inventory.current_color = Some(previous);
+            }
+        }
+    }
+
+    if inventory.current_color != old_color {
+        inventory.previous_color = old_color;
+    }
+}
+
+/// Returns the color the player should be holding, given the colors that are currently allowed.
+/// Returns [`None`] if no colors are allowed, so the player simply moves around without light.
+pub fn resolve_current_color(
+    current_color: Option<LightColor>,
+    allowed_colors: &EnumMap<LightColor, bool>,
+    behavior: DisallowedColorBehavior,
+) -> Option<LightColor> {
+    match current_color {
+        Some(color) if allowed_colors[color] => Some(color),
+        Some(_) if behavior == DisallowedColorBehavior::Fallback => COLOR_BINDS
+            .iter()
+            .map(|(_, color)| *color)
+            .find(|color| allowed_colors[*color]),
+        _ => None,
+    }
+}
+
+/// [`System`] that makes sure the player never holds a color that isn't allowed, e.g. after a
+/// temporary shard was reset on death or every color was disabled.
+pub fn update_disallowed_color(
+    mut q_inventory: Query<&mut PlayerLightInventory, With<PlayerMarker>>,
+    current_level: Res<CurrentLevel>,
+    config: Res<Config>,
+) {
+    let Ok(mut inventory) = q_inventory.get_single_mut() else {
+        return;
+    };
+    let new_color = resolve_current_color(
+        inventory.current_color,
+        &current_level.allowed_colors,
+        config.player_config.disallowed_color,
+    );
+    if new_color != inventory.current_color {
+        inventory.previous_color = inventory.current_color;
+        inventory.current_color = new_color;
+    }
 }
 
 pub fn should_shoot_light<const V: bool>(
@@ -257,6 +379,7 @@ pub fn shoot_light(
     q_cursor: Query<&CursorWorldCoords>,
     keys: Res<ButtonInput<KeyCode>>,
     asset_server: Res<AssetServer>,
+    config: Res<Config>,
 ) {
     let Ok((player_transform, mut player_inventory)) = q_player.get_single_mut() else {
         return;
@@ -272,11 +395,7 @@ pub fn shoot_light(
     }
 
     let ray_pos = player_transform.translation.truncate();
-    let mut ray_dir = (cursor_pos.pos - ray_pos).normalize_or_zero();
-
-    if keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight) {
-        ray_dir = snap_ray(ray_dir);
-    }
+    let ray_dir = aim_direction(ray_pos, cursor_pos, &keys);
 
     if ray_dir == Vec2::ZERO {
         return;
@@ -292,7 +411,7 @@ pub fn shoot_light(
     let mut outer_source_sprite = Sprite::from_image(asset_server.load("light/compass-gold.png"));
     outer_source_sprite.color = shoot_color.light_beam_color().mix(&Color::BLACK, 0.4);
 
-    commands
+    let beam = commands
         .spawn(LightBeamSource {
             start_pos: ray_pos,
             start_dir: ray_dir,
@@ -307,13 +426,21 @@ pub fn shoot_light(
         .with_child((
             LineLight2d::point(shoot_color.lighting_color().extend(1.0), 30.0, 0.0),
             TERRAIN_LAYER,
-        ));
+        ))
+        .id();
 
     // Bevy's Mut or ResMut doesn't let you borrow multiple fields of a struct, so sometimes you
     // need to "reborrow" it to turn it into &mut. See https://bevy-cheatbook.github.io/pitfalls/split-borrows.html
     let player_inventory = &mut *player_inventory;
     player_inventory.sources[shoot_color] = false;
     player_inventory.should_shoot = false;
+    let max_beams = config.player_config.max_active_beams;
+    if let Some(oldest) = player_inventory.push_beam(beam, shoot_color, max_beams) {
+        // it may already be gone if a reset despawned it
+        if let Some(oldest) = commands.get_entity(oldest) {
+            oldest.despawn_recursive();
+        }
+    }
 }
 
 #[derive(Default, Reflect, GizmoConfigGroup)]
@@ -323,14 +450,17 @@ pub struct LightPreviewGizmos;
 /// down. This system needs some work, namely:
 ///
 /// - Not using [`Gizmos`] to render the light segments
+#[allow(clippy::too_many_arguments)]
 pub fn preview_light_path(
     mut q_rapier: Query<&mut RapierContext>,
     q_player: Query<(&Transform, &PlayerLightInventory), With<PlayerMarker>>,
     q_cursor: Query<&CursorWorldCoords>,
     keys: Res<ButtonInput<KeyCode>>,
     q_mirror: Query<&Mirror>,
+    q_lenses: Query<&Lens>,
     mut gizmos: Gizmos,
     q_black_ray: Query<(Entity, &BlackRayComponent)>,
+    config: Res<Config>,
 ) {
     let Ok(rapier_context) = q_rapier.get_single_mut() else {
         return;
@@ -348,27 +478,53 @@ pub fn preview_light_path(
     let shoot_color = inventory.current_color.unwrap();
 
     let ray_pos = transform.translation.truncate();
-    let mut ray_dir = (cursor_pos.pos - ray_pos).normalize_or_zero();
+    let ray_dir = aim_direction(ray_pos, cursor_pos, &keys);
 
-    if keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight) {
-        ray_dir = snap_ray(ray_dir);
-    }
-
-    let dummy_source = LightBeamSource {
+    let rapier_context = rapier_context.into_inner();
+    let mut dummy_source = LightBeamSource {
         start_pos: ray_pos,
         start_dir: ray_dir,
         time_traveled: 10000.0, // LOL
         color: shoot_color,
     };
-    let playback = play_light_beam(
-        rapier_context.into_inner(),
-        &dummy_source,
-        &q_black_ray,
-        &q_mirror,
-    );
+    // follows the light out of any lenses it goes through, like the beam will
+    for _ in 0..=MAX_LENS_CHAIN {
+        let playback = play_light_beam(
+            rapier_context,
+            &dummy_source,
+            &q_black_ray,
+            &q_mirror,
+            &q_lenses,
+            config.light_config.max_reflection_depth,
+        );
+
+        for (a, b) in playback.iter_points(&dummy_source).tuple_windows() {
+            gizmos.line_2d(a, b, dummy_source.color.light_beam_color().darker(0.3));
+        }
+        let Some(refraction) = playback.refraction else {
+            break;
+        };
+        dummy_source = LightBeamSource {
+            start_pos: refraction.exit,
+            start_dir: refraction.dir,
+            color: refraction.color,
+            ..dummy_source
+        };
+    }
+}
 
-    for (a, b) in playback.iter_points(&dummy_source).tuple_windows() {
-        gizmos.line_2d(a, b, shoot_color.light_beam_color().darker(0.3));
+/// The direction a beam shot from `ray_pos` right now would go: towards the cursor, which the right
+/// stick also moves, snapped to the angle increments while shift is held.
+fn aim_direction(
+    ray_pos: Vec2,
+    cursor_pos: &CursorWorldCoords,
+    keys: &ButtonInput<KeyCode>,
+) -> Vec2 {
+    let ray_dir = (cursor_pos.pos - ray_pos).normalize_or_zero();
+    if keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight) {
+        snap_ray(ray_dir)
+    } else {
+        ray_dir
     }
 }
 
@@ -379,3 +535,100 @@ fn snap_ray(ray_vec: Vec2) -> Vec2 {
 
     Vec2::new(cos(snapped_angle), sin(snapped_angle))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowed(colors: &[LightColor]) -> EnumMap<LightColor, bool> {
+        EnumMap::from_fn(|color| colors.contains(&color))
+    }
+
+    #[test]
+    fn shot_colors_are_not_held() {
+        let allowed = allowed(&[LightColor::Green, LightColor::Blue, LightColor::White]);
+        let mut inventory = PlayerLightInventory::new();
+        assert_eq!(inventory.held_color_count(&allowed), 3);
+        inventory.sources[LightColor::Blue] = false;
+        // purple has a source, but isn't allowed in the level
+        assert_eq!(inventory.held_color_count(&allowed), 2);
+    }
+
+    #[test]
+    fn oldest_beam_dropped_past_limit() {
+        let mut inventory = PlayerLightInventory::new();
+        let beams = [
+            (Entity::from_raw(1), LightColor::Green),
+            (Entity::from_raw(2), LightColor::Purple),
+            (Entity::from_raw(3), LightColor::Blue),
+        ];
+        for (entity, color) in beams {
+            inventory.sources[color] = false;
+            let dropped = inventory.push_beam(entity, color, 2);
+            if entity == beams[2].0 {
+                assert_eq!(dropped, Some(beams[0].0));
+            } else {
+                assert_eq!(dropped, None);
+            }
+        }
+        // the dropped beam's color can be shot again
+        assert!(inventory.sources[LightColor::Green]);
+        assert!(!inventory.sources[LightColor::Purple]);
+        assert_eq!(inventory.active_beams, VecDeque::from(beams[1..].to_vec()));
+
+        // no limit
+        for (entity, color) in beams {
+            assert_eq!(inventory.push_beam(entity, color, 0), None);
+        }
+    }
+
+    #[test]
+    fn keeps_allowed_color() {
+        let allowed = allowed(&[LightColor::Green, LightColor::Purple]);
+        for behavior in [
+            DisallowedColorBehavior::Fallback,
+            DisallowedColorBehavior::Deselect,
+        ] {
+            assert_eq!(
+                resolve_current_color(Some(LightColor::Purple), &allowed, behavior),
+                Some(LightColor::Purple)
+            );
+        }
+    }
+
+    #[test]
+    fn handles_disallowed_color() {
+        let allowed = allowed(&[LightColor::Blue]);
+        assert_eq!(
+            resolve_current_color(
+                Some(LightColor::Green),
+                &allowed,
+                DisallowedColorBehavior::Fallback
+            ),
+            Some(LightColor::Blue)
+        );
+        assert_eq!(
+            resolve_current_color(
+                Some(LightColor::Green),
+                &allowed,
+                DisallowedColorBehavior::Deselect
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn all_colors_disabled() {
+        let allowed = allowed(&[]);
+        for behavior in [
+            DisallowedColorBehavior::Fallback,
+            DisallowedColorBehavior::Deselect,
+        ] {
+            assert_eq!(
+                resolve_current_color(Some(LightColor::White), &allowed, behavior),
+                None
+            );
+            assert_eq!(resolve_current_color(None, &allowed, behavior), None);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    camera::HIGHRES_LAYER,
+    input::{aim_with_right_stick, CursorWorldCoords},
+    light::segments::light_ray_collision_groups,
+    player::PlayerMarker,
+    shared::GameState,
+};
+
+use super::{aim_direction, AngleIncrementMarker, AngleMarker, PlayerLightInventory};
+
+/// How far ahead of the player the aim preview reaches when the beam wouldn't hit anything
+const AIM_PREVIEW_RANGE: f32 = 240.0;
+/// How far apart the dots of the trajectory line are
+const TRAJECTORY_DOT_SPACING: f32 = 6.0;
+const TRAJECTORY_DOTS: usize = (AIM_PREVIEW_RANGE / TRAJECTORY_DOT_SPACING) as usize;
+
+/// [`Plugin`] for the reticle shown where the player's light beam will first hit something while
+/// they aim, and the dotted line leading to it.
+pub struct AimReticlePlugin;
+
+impl Plugin for AimReticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_aim_reticle)
+            .add_systems(Update, update_aim_reticle.after(aim_with_right_stick));
+    }
+}
+
+/// Marker [`Component`] for the reticle at the first hit point of the beam being aimed.
+#[derive(Component)]
+pub struct AimReticle;
+
+/// [`Component`] for one of the dots of the trajectory line, the `index`th from the player.
+#[derive(Component)]
+pub struct TrajectoryDot {
+    index: usize,
+}
+
+pub fn spawn_aim_reticle(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Sprite::from_image(asset_server.load("reticle.png")),
+        Visibility::Hidden,
+        AimReticle,
+        HIGHRES_LAYER,
+    ));
+    for index in 0..TRAJECTORY_DOTS {
+        commands.spawn((
+            Sprite::from_color(Color::WHITE, Vec2::ONE),
+            Visibility::Hidden,
+            TrajectoryDot { index },
+            HIGHRES_LAYER,
+        ));
+    }
+}
+
+/// The positions of the trajectory line's dots on the way from `start` to `end`, leaving out the
+/// ones too close to either end.
+fn trajectory_dots(start: Vec2, end: Vec2) -> impl Iterator<Item = Vec2> {
+    let dir = (end - start).normalize_or_zero();
+    let length = start.distance(end);
+    (1..TRAJECTORY_DOTS)
+        .map(move |i| i as f32 * TRAJECTORY_DOT_SPACING)
+        .take_while(move |distance| *distance < length - TRAJECTORY_DOT_SPACING / 2.0)
+        .map(move |distance| start + dir * distance)
+}
+
+pub type AimReticleQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Transform,
+        &'static mut Sprite,
+        &'static mut Visibility,
+    ),
+    (With<AimReticle>, Without<PlayerMarker>),
+>;
+
+pub type TrajectoryDotQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static TrajectoryDot,
+        &'static mut Transform,
+        &'static mut Sprite,
+        &'static mut Visibility,
+    ),
+    (Without<AimReticle>, Without<PlayerMarker>),
+>;
+
+/// Query for the indicators shown around the player while they aim.
+pub type AimIndicatorQuery<'w, 's> =
+    Query<'w, 's, (), Or<(With<AngleMarker>, With<AngleIncrementMarker>)>>;
+
+/// [`System`] that moves the [`AimReticle`] to where a beam shot right now would first hit
+/// something, and lines the [`TrajectoryDot`]s up along the way. The ray stops at the same things
+/// the beam does, like walls and gates. Both are only shown while the player has a color to shoot
+/// and is aiming, which is while the [`AngleMarker`], or the [`AngleIncrementMarker`]s when
+/// snapping, are shown.
+#[allow(clippy::too_many_arguments)]
+pub fn update_aim_reticle(
+    rapier_context: Query<&RapierContext>,
+    q_player: Query<(&Transform, &PlayerLightInventory), With<PlayerMarker>>,
+    q_cursor: Query<&CursorWorldCoords>,
+    q_angle: AimIndicatorQuery,
+    mut q_reticle: AimReticleQuery,
+    mut q_dots: TrajectoryDotQuery,
+    keys: Res<ButtonInput<KeyCode>>,
+    game_state: Res<State<GameState>>,
+) {
+    let mut hide = || {
+        for (_, _, mut visibility) in q_reticle.iter_mut() {
+            *visibility = Visibility::Hidden;
+        }
+        for (_, _, _, mut visibility) in q_dots.iter_mut() {
+            *visibility = Visibility::Hidden;
+        }
+    };
+    let (Ok(rapier_context), Ok((player_transform, inventory)), Ok(cursor_pos)) = (
+        rapier_context.get_single(),
+        q_player.get_single(),
+        q_cursor.get_single(),
+    ) else {
+        hide();
+        return;
+    };
+    let Some(color) = inventory
+        .current_color
+        .filter(|_| inventory.can_shoot() && !q_angle.is_empty())
+    else {
+        hide();
+        return;
+    };
+    let start = player_transform.translation.truncate();
+    let dir = aim_direction(start, cursor_pos, &keys);
+    if dir == Vec2::ZERO || *game_state.get() != GameState::Playing {
+        hide();
+        return;
+    }
+
+    let filter = QueryFilter::new().groups(light_ray_collision_groups(color));
+    let hit_distance = rapier_context
+        .cast_ray(start, dir, AIM_PREVIEW_RANGE, true, filter)
+        .map_or(AIM_PREVIEW_RANGE, |(_, toi)| toi);
+    let end = start + dir * hit_distance;
+    let z = player_transform.translation.z + 1.0;
+    let beam_color = color.light_beam_color();
+
+    for (mut transform, mut sprite, mut visibility) in q_reticle.iter_mut() {
+        transform.translation = end.extend(z);
+        sprite.color = beam_color;
+        *visibility = Visibility::Visible;
+    }
+    let dots: Vec<Vec2> = trajectory_dots(start, end).collect();
+    for (dot, mut transform, mut sprite, mut visibility) in q_dots.iter_mut() {
+        let Some(pos) = dots.get(dot.index) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        transform.translation = pos.extend(z);
+        sprite.color = beam_color.with_alpha(0.6);
+        *visibility = Visibility::Visible;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trajectory_stops_before_hit_point() {
+        let dots: Vec<Vec2> = trajectory_dots(Vec2::ZERO, Vec2::new(20.0, 0.0)).collect();
+        assert_eq!(dots, vec![Vec2::new(6.0, 0.0), Vec2::new(12.0, 0.0)]);
+        assert_eq!(trajectory_dots(Vec2::ZERO, Vec2::ZERO).count(), 0);
+        assert!(
+            trajectory_dots(Vec2::ZERO, Vec2::new(0.0, AIM_PREVIEW_RANGE)).count()
+                < TRAJECTORY_DOTS
+        );
+    }
+}
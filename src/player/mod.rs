@@ -5,6 +5,7 @@ use bevy_rapier2d::prelude::*;
 use match_player::{
     post_update_match_player_pixel, pre_update_match_player_pixel, update_match_player_z,
 };
+use spawn_in::SpawnInPlugin;
 use strand::PlayerStrandPlugin;
 
 use crate::{animation::AnimationConfig, level::LevelSystems};
@@ -12,14 +13,17 @@ use crate::{animation::AnimationConfig, level::LevelSystems};
 use kill::PlayerKillPlugin;
 use light::{PlayerLightInventory, PlayerLightPlugin};
 use movement::{PlayerMovement, PlayerMovementPlugin};
+use skin::PlayerSkin;
 use spawn::{add_player_sensors, init_player_bundle};
 
-mod animation;
+pub mod animation;
 pub mod kill;
 pub mod light;
 pub mod match_player;
 pub mod movement;
+pub mod skin;
 mod spawn;
+pub mod spawn_in;
 mod strand;
 
 /// [`Plugin`] for anything player based.
@@ -31,6 +35,8 @@ impl Plugin for PlayerManagementPlugin {
             .add_plugins(PlayerMovementPlugin)
             .add_plugins(PlayerKillPlugin)
             .add_plugins(PlayerStrandPlugin)
+            .add_plugins(SpawnInPlugin)
+            .init_resource::<PlayerSkin>()
             .add_systems(
                 PreUpdate,
                 add_player_sensors.in_set(LevelSystems::Processing),
@@ -48,6 +54,15 @@ impl Plugin for PlayerManagementPlugin {
     }
 }
 
+/// Half the size of the player's collider.
+pub const PLAYER_HALF_EXTENT: Vec2 = Vec2::new(6.0, 7.0);
+/// Offset from the player's position to the center of their collider, which sits below it.
+pub const PLAYER_COLLIDER_OFFSET: Vec2 = Vec2::new(0.0, -2.0);
+/// Offset from the player's position to the bottom of their collider.
+pub const PLAYER_FEET_OFFSET: f32 = PLAYER_COLLIDER_OFFSET.y - PLAYER_HALF_EXTENT.y;
+/// Offset from the player's position to the top of their collider.
+pub const PLAYER_HEAD_OFFSET: f32 = PLAYER_COLLIDER_OFFSET.y + PLAYER_HALF_EXTENT.y;
+
 /// Add to player to prevent movement/other inputs
 #[derive(Component)]
 pub struct InputLocked;
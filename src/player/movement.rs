@@ -1,54 +1,70 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
+use enum_map::EnumMap;
 
-use crate::config::Config;
-use crate::level::LevelSystems;
+use crate::config::{Config, MovementModifier};
+use crate::debug::not_debug_flying;
+use crate::input::{gamepad_just_pressed, gamepad_movement, gamepad_pressed};
+use crate::level::{platform::cast_player_ray_shape, LevelSystems};
+use crate::light::LightColor;
+use crate::shared::GroupLabel;
 
-use super::{not_input_locked, InputLocked, PlayerMarker};
+use super::{
+    light::PlayerLightInventory, not_input_locked, InputLocked, PlayerMarker, PLAYER_FEET_OFFSET,
+    PLAYER_HALF_EXTENT, PLAYER_HEAD_OFFSET,
+};
 
-/// The number of [`FixedUpdate`] steps the player can jump for after pressing the spacebar.
-const SHOULD_JUMP_TICKS: isize = 8;
-/// The number of [`FixedUpdate`] steps the player can jump for after falling off an edge.
-const COYOTE_TIME_TICKS: isize = 5;
+/// The default number of [`FixedUpdate`] steps the player can jump for after pressing the
+/// spacebar, so a jump pressed just before landing still goes through.
+pub const SHOULD_JUMP_TICKS: u32 = 8;
+/// The default number of [`FixedUpdate`] steps the player can jump for after falling off an edge.
+pub const COYOTE_TIME_TICKS: u32 = 5;
 /// The number of [`FixedUpdate`] steps the player should receive upward velocity for.
 const JUMP_BOOST_TICKS: isize = 2;
 
 /// Max player horizontal velocity.
-const PLAYER_MAX_H_VEL: f32 = 1.5;
-/// Max player vertical velocity.
-const PLAYER_MAX_Y_VEL: f32 = 5.;
-/// The positive y velocity added to the player every jump boost tick.
-const PLAYER_JUMP_VEL: f32 = 2.2;
-/// The x velocity added to the player when A/D is held.
-const PLAYER_MOVE_VEL: f32 = 0.6;
-/// The y velocity subtracted from the player due to gravity.
-const PLAYER_GRAVITY: f32 = 0.15;
+pub const PLAYER_MAX_H_VEL: f32 = 1.5;
+/// Max player vertical velocity, and the default of `max_fall_speed` in the
+/// [`MovementConfig`](crate::config::MovementConfig).
+pub const PLAYER_MAX_Y_VEL: f32 = 5.;
+/// The default positive y velocity given to the player every jump boost tick.
+pub const PLAYER_JUMP_VEL: f32 = 2.2;
+/// The default x velocity added to the player when A/D is held.
+pub const PLAYER_MOVE_VEL: f32 = 0.6;
+/// The y velocity subtracted from the player due to gravity, before the configured gravity scale.
+pub const PLAYER_GRAVITY: f32 = 0.15;
+/// The slowest the player can rebound off a wall or floor, so that resting against one (and the
+/// gravity pulling the player into the floor every tick) doesn't jitter.
+const PLAYER_MIN_REBOUND_VEL: f32 = 0.5;
 
 pub struct PlayerMovementPlugin;
 
 impl Plugin for PlayerMovementPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            FixedUpdate,
-            move_player
-                .before(PhysicsSet::SyncBackend)
-                .in_set(LevelSystems::Simulation),
-        )
-        .add_systems(
-            Update,
-            queue_jump
-                .run_if(not_input_locked)
-                .run_if(jump_key_pressed)
-                .before(move_player)
-                .in_set(LevelSystems::Simulation),
-        )
-        .add_systems(
-            Update,
-            crouch_player
-                .run_if(not_input_locked)
-                .before(move_player)
-                .in_set(LevelSystems::Simulation),
-        );
+        app.init_resource::<ColorMovementModifiers>()
+            .add_systems(
+                FixedUpdate,
+                (move_player, assist_ledge_climb, assist_landing)
+                    .chain()
+                    .run_if(not_debug_flying)
+                    .before(PhysicsSet::SyncBackend)
+                    .in_set(LevelSystems::Simulation),
+            )
+            .add_systems(
+                Update,
+                queue_jump
+                    .run_if(not_input_locked)
+                    .run_if(jump_key_pressed)
+                    .before(move_player)
+                    .in_set(LevelSystems::Simulation),
+            )
+            .add_systems(
+                Update,
+                crouch_player
+                    .run_if(not_input_locked)
+                    .before(move_player)
+                    .in_set(LevelSystems::Simulation),
+            );
     }
 }
 
@@ -62,15 +78,90 @@ pub struct PlayerMovement {
     should_jump_ticks_remaining: isize,
     coyote_time_ticks_remaining: isize,
     jump_boost_ticks_remaining: isize,
+    /// Whether the player is rising from a bounce off the floor, which releasing jump shouldn't cut
+    bouncing: bool,
+    /// Which way the player runs in one button mode
+    auto_run_left: bool,
+    /// Ticks left in which input is ignored after respawning, see [`PlayerMovement::lock_input`]
+    input_lockout_ticks_remaining: u32,
+    /// Velocity added every tick by the [`ForceZone`](crate::level::force_zone::ForceZone)s the
+    /// player is inside
+    pub zone_force: Vec2,
+}
+
+impl PlayerMovement {
+    /// Ignores movement input for the next `ticks` ticks, so a direction still held from before a
+    /// death doesn't walk the player straight back into the hazard. Doesn't lock anything if
+    /// `ticks` is 0.
+    pub fn lock_input(&mut self, ticks: u32) {
+        self.input_lockout_ticks_remaining = ticks;
+    }
+
+    fn input_locked_out(&self) -> bool {
+        self.input_lockout_ticks_remaining > 0
+    }
+
+    /// Forgets a buffered jump and any coyote time left, so neither carries over into a respawn.
+    pub fn reset_jump_windows(&mut self) {
+        self.should_jump_ticks_remaining = 0;
+        self.coyote_time_ticks_remaining = 0;
+        self.jump_boost_ticks_remaining = 0;
+    }
+
+    fn tick_input_lockout(&mut self) {
+        self.input_lockout_ticks_remaining = self.input_lockout_ticks_remaining.saturating_sub(1);
+    }
+}
+
+/// [`Resource`] holding the [`MovementModifier`] applied while the player holds each
+/// [`LightColor`]. Nothing is modified while the player isn't holding a color.
+#[derive(Resource)]
+pub struct ColorMovementModifiers(pub EnumMap<LightColor, MovementModifier>);
+
+impl FromWorld for ColorMovementModifiers {
+    fn from_world(world: &mut World) -> Self {
+        let config = world.resource::<Config>();
+        ColorMovementModifiers(EnumMap::from_fn(|color| {
+            config.player_config.movement_modifier(color)
+        }))
+    }
+}
+
+/// What pressing the button does in one button mode.
+#[derive(Debug, PartialEq, Eq)]
+enum OneButtonAction {
+    Jump,
+    TurnAround,
+}
+
+/// Jumps whenever a jump would go through, including during coyote time, and turns around
+/// otherwise, since buffering the jump until landing would take away the only way to turn.
+fn one_button_action(player: &PlayerMovement) -> OneButtonAction {
+    if player.coyote_time_ticks_remaining > 0 {
+        OneButtonAction::Jump
+    } else {
+        OneButtonAction::TurnAround
+    }
 }
 
 /// [`System`] that is run the frame the space bar is pressed. Allows the player to jump for the
 /// next couple of frames.
-pub fn queue_jump(mut q_player: Query<&mut PlayerMovement, With<PlayerMarker>>) {
+pub fn queue_jump(
+    mut q_player: Query<&mut PlayerMovement, With<PlayerMarker>>,
+    config: Res<Config>,
+) {
     let Ok(mut player) = q_player.get_single_mut() else {
         return;
     };
-    player.should_jump_ticks_remaining = SHOULD_JUMP_TICKS;
+    if player.input_locked_out() {
+        return;
+    }
+    if config.assist_config.one_button && one_button_action(&player) == OneButtonAction::TurnAround
+    {
+        player.auto_run_left = !player.auto_run_left;
+        return;
+    }
+    player.should_jump_ticks_remaining = config.movement_config.jump_buffer_ticks();
 }
 
 /// [`System`] that is run on [`Update`] to crouch player
@@ -96,97 +187,378 @@ pub fn crouch_player(
     }
 }
 
+pub type MovePlayerQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut KinematicCharacterController,
+        &'static KinematicCharacterControllerOutput,
+        &'static mut PlayerMovement,
+        Option<&'static InputLocked>,
+        Option<&'static PlayerLightInventory>,
+    ),
+    With<PlayerMarker>,
+>;
+
 /// [`System`] that is run on [`Update`] to move the player around.
 pub fn move_player(
-    mut q_player: Query<
-        (
-            &mut KinematicCharacterController,
-            &KinematicCharacterControllerOutput,
-            &mut PlayerMovement,
-            Option<&InputLocked>,
-        ),
-        With<PlayerMarker>,
-    >,
+    mut q_player: MovePlayerQuery,
     keys: Res<ButtonInput<KeyCode>>,
+    q_gamepads: Query<&Gamepad>,
     config: Res<Config>,
+    modifiers: Res<ColorMovementModifiers>,
 ) {
-    let Ok((mut controller, output, mut player, movement_locked)) = q_player.get_single_mut()
+    let Ok((mut controller, output, mut player, movement_locked, inventory)) =
+        q_player.get_single_mut()
     else {
         return;
     };
+    let modifier = inventory
+        .and_then(|inventory| inventory.current_color)
+        .map(|color| modifiers.0[color])
+        .unwrap_or_default();
 
+    let locked_out = player.input_locked_out();
     let check_pressed = |key: KeyCode| {
-        if movement_locked.is_some() {
+        if movement_locked.is_some() || locked_out {
             return false;
         }
         keys.pressed(key)
     };
+    let (gamepad_movement, gamepad_jump) = match movement_locked.is_some() || locked_out {
+        true => (Vec2::ZERO, false),
+        false => (
+            gamepad_movement(&q_gamepads, &config),
+            gamepad_pressed(&q_gamepads, config.controls_config.gamepad_jump),
+        ),
+    };
 
     if output.grounded {
-        player.coyote_time_ticks_remaining = COYOTE_TIME_TICKS;
+        player.coyote_time_ticks_remaining = config.movement_config.coyote_time_ticks();
     }
 
-    // Can only jump if they've pressed space within the past SHOULD_JUMP_TICKS, and they have been
-    // grounded in the past COYOTE_TIME_TICKS
+    // the character controller stops the player at walls and floors, so hitting one shows up as
+    // the last translation not (fully) happening
+    let player_config = &config.player_config;
+    let movement_config = &config.movement_config;
+    let blocked =
+        |desired: f32, effective: f32| desired != 0. && effective.abs() < desired.abs() / 2.;
+    let one_button = config.assist_config.one_button && movement_locked.is_none() && !locked_out;
+    if blocked(output.desired_translation.x, output.effective_translation.x) {
+        if one_button {
+            player.auto_run_left = output.desired_translation.x > 0.;
+        }
+        if let Some(rebound) =
+            rebound_velocity(output.desired_translation.x, player_config.wall_restitution)
+        {
+            player.velocity.x = rebound;
+        }
+    }
+    let floor_rebound = if output.grounded && output.desired_translation.y < 0. {
+        rebound_velocity(
+            output.desired_translation.y,
+            player_config.floor_restitution,
+        )
+    } else {
+        None
+    };
+
+    // Can only jump if they've pressed space within the jump buffer, and they have been grounded
+    // within the coyote time
     if player.should_jump_ticks_remaining > 0 && player.coyote_time_ticks_remaining > 0 {
         player.jump_boost_ticks_remaining = JUMP_BOOST_TICKS;
+        player.bouncing = false;
     } else if !check_pressed(config.controls_config.key_jump)
         && !check_pressed(config.controls_config.key_up)
+        && !gamepad_jump
         && player.velocity.y > 0.
+        && !player.bouncing
+        // a tap is always a full jump in one button mode
+        && !one_button
     {
         // Jump was cut
-        player.velocity.y = PLAYER_GRAVITY;
+        player.velocity.y = movement_config.gravity();
         player.jump_boost_ticks_remaining = 0;
     } else if output.desired_translation.y > 0. && output.effective_translation.y < 0.05 {
         // Bonked head onto wall
         player.velocity.y = 0.;
         player.jump_boost_ticks_remaining = 0;
+    } else if let Some(rebound) = floor_rebound {
+        player.velocity.y = rebound;
+        player.bouncing = true;
     } else if output.grounded {
         player.velocity.y = 0.;
     }
 
     if player.jump_boost_ticks_remaining > 0 {
-        player.velocity.y = PLAYER_JUMP_VEL;
+        player.velocity.y = movement_config.jump_impulse() * modifier.jump;
+    } else if player.velocity.y < 0. {
+        player.velocity.y -= movement_config.gravity() * modifier.fall;
     } else {
-        player.velocity.y -= PLAYER_GRAVITY;
+        player.velocity.y -= movement_config.gravity();
     }
+    player.velocity.y += player.zone_force.y;
 
-    player.velocity.y = player.velocity.y.clamp(-PLAYER_MAX_Y_VEL, PLAYER_MAX_Y_VEL);
+    player.velocity.y = player
+        .velocity
+        .y
+        .clamp(-movement_config.max_fall_speed(), PLAYER_MAX_Y_VEL);
+    if player.velocity.y <= 0. {
+        player.bouncing = false;
+    }
 
+    let (move_left, move_right) = if one_button {
+        (player.auto_run_left, !player.auto_run_left)
+    } else {
+        (
+            check_pressed(config.controls_config.key_left) || gamepad_movement.x < 0.,
+            check_pressed(config.controls_config.key_right) || gamepad_movement.x > 0.,
+        )
+    };
     let mut moved = false;
-    if check_pressed(config.controls_config.key_left) {
-        player.velocity.x -= PLAYER_MOVE_VEL;
+    if move_left {
+        player.velocity.x -= movement_config.horizontal_acceleration() * modifier.run;
         moved = true;
     }
-    if check_pressed(config.controls_config.key_right) {
-        player.velocity.x += PLAYER_MOVE_VEL;
+    if move_right {
+        player.velocity.x += movement_config.horizontal_acceleration() * modifier.run;
         moved = true;
     }
 
     player.sneaking = keys.pressed(KeyCode::ControlLeft);
     let temp_max_h_vel = if player.sneaking {
-        PLAYER_MAX_H_VEL / 2.
+        PLAYER_MAX_H_VEL * modifier.run / 2.
     } else {
-        PLAYER_MAX_H_VEL
+        PLAYER_MAX_H_VEL * modifier.run
     };
-    player.velocity.x = player.velocity.x.clamp(-temp_max_h_vel, temp_max_h_vel);
     if !moved {
-        // slow player down when not moving horizontally
-        // NOTE: why not using rapier friction?
-        player.velocity.x *= 0.6;
+        // slow player down when not moving horizontally. rapier friction doesn't apply, since the
+        // player is moved by a kinematic character controller
+        player.velocity.x *= 1. - player_config.friction.clamp(0., 1.);
         if player.velocity.x.abs() < 0.1 {
             player.velocity.x = 0.;
         }
     }
+    // after friction, so even gentle zones move a player standing still
+    player.velocity.x += player.zone_force.x;
+    player.velocity.x = player.velocity.x.clamp(-temp_max_h_vel, temp_max_h_vel);
 
     player.should_jump_ticks_remaining -= 1;
     player.jump_boost_ticks_remaining -= 1;
     player.coyote_time_ticks_remaining -= 1;
+    player.tick_input_lockout();
 
     controller.translation = Some(player.velocity);
 }
 
-fn jump_key_pressed(keys: Res<ButtonInput<KeyCode>>, config: Res<Config>) -> bool {
+/// Returns the velocity the player rebounds with after hitting a surface at `velocity`, or `None`
+/// if the hit is too soft to bounce.
+fn rebound_velocity(velocity: f32, restitution: f32) -> Option<f32> {
+    let rebound = -velocity * restitution;
+    (rebound.abs() >= PLAYER_MIN_REBOUND_VEL).then_some(rebound)
+}
+
+/// [`System`] that nudges the player up onto a ledge when they rise into its corner, instead of
+/// bonking it. Only ledges whose top is within the configured tolerance of the player's feet count,
+/// so walls taller than that block the player as usual.
+pub fn assist_ledge_climb(
+    mut q_player: Query<(&mut Transform, &PlayerMovement), With<PlayerMarker>>,
+    rapier_context: ReadDefaultRapierContext,
+    config: Res<Config>,
+) {
+    let assist = &config.assist_config;
+    if !assist.ledge_assist {
+        return;
+    }
+    let Ok((mut transform, player)) = q_player.get_single_mut() else {
+        return;
+    };
+    if player.velocity.y <= 0. || player.velocity.x == 0. {
+        return;
+    }
+
+    let tolerance = assist.ledge_assist_tolerance;
+    let x_offset = player.velocity.x.signum() * (PLAYER_HALF_EXTENT.x + 1.);
+    let cast = |y_offset: f32, height: f32| {
+        cast_player_ray_shape(
+            &rapier_context,
+            &transform,
+            x_offset,
+            y_offset,
+            2.,
+            height,
+            Vec2::new(player.velocity.x.signum(), 0.),
+            GroupLabel::TERRAIN | GroupLabel::PLATFORM,
+        )
+    };
+
+    // there should be something in front of the player's feet, but nothing above the ledge
+    let below_ledge_height = tolerance;
+    let above_ledge_height = PLAYER_HEAD_OFFSET - PLAYER_FEET_OFFSET - tolerance;
+    let ledge_in_front = cast(
+        PLAYER_FEET_OFFSET + below_ledge_height / 2.,
+        below_ledge_height,
+    );
+    let clear_above = cast(
+        PLAYER_FEET_OFFSET + tolerance + above_ledge_height / 2.,
+        above_ledge_height,
+    )
+    .is_none();
+
+    if ledge_in_front.is_some() && clear_above {
+        transform.translation.y += tolerance;
+    }
+}
+
+/// [`System`] that nudges the player sideways onto a platform when they are about to fall past its
+/// edge by no more than the configured tolerance, instead of clipping past it. The player is only
+/// nudged towards platforms they aren't moving away from, and only if there is room for them above
+/// the platform, so falling down right beside a platform on purpose still works.
+pub fn assist_landing(
+    mut q_player: Query<
+        (
+            &mut Transform,
+            &PlayerMovement,
+            &KinematicCharacterControllerOutput,
+        ),
+        With<PlayerMarker>,
+    >,
+    rapier_context: ReadDefaultRapierContext,
+    config: Res<Config>,
+) {
+    let assist = &config.assist_config;
+    if !assist.landing_assist {
+        return;
+    }
+    let Ok((mut transform, player, output)) = q_player.get_single_mut() else {
+        return;
+    };
+    if output.grounded || player.velocity.y >= 0. {
+        return;
+    }
+
+    let tolerance = assist.landing_assist_tolerance;
+    // how far the player falls by the next tick, where a platform top has to be to be landed on
+    let fall = -player.velocity.y;
+    let cast = |x_offset: f32, y_offset: f32, width: f32, height: f32| {
+        cast_player_ray_shape(
+            &rapier_context,
+            &transform,
+            x_offset,
+            y_offset,
+            width,
+            height,
+            Vec2::NEG_Y,
+            GroupLabel::TERRAIN | GroupLabel::PLATFORM,
+        )
+    };
+
+    // the player lands on their own if there is something below them
+    if cast(
+        0.,
+        PLAYER_FEET_OFFSET - fall / 2.,
+        PLAYER_HALF_EXTENT.x * 2.,
+        fall,
+    )
+    .is_some()
+    {
+        return;
+    }
+    let body_height = PLAYER_HEAD_OFFSET - PLAYER_FEET_OFFSET;
+    for side in [-1., 1.] {
+        if player.velocity.x * side < 0. {
+            continue;
+        }
+        let x_offset = side * (PLAYER_HALF_EXTENT.x + tolerance / 2.);
+        let platform_beside = cast(x_offset, PLAYER_FEET_OFFSET - fall / 2., tolerance, fall);
+        let clear_beside = cast(
+            x_offset,
+            PLAYER_FEET_OFFSET + body_height / 2.,
+            tolerance,
+            body_height,
+        )
+        .is_none();
+        if platform_beside.is_some() && clear_beside {
+            transform.translation.x += side * tolerance;
+            return;
+        }
+    }
+}
+
+fn jump_key_pressed(
+    keys: Res<ButtonInput<KeyCode>>,
+    q_gamepads: Query<&Gamepad>,
+    config: Res<Config>,
+) -> bool {
     return keys.just_pressed(config.controls_config.key_jump)
-        || keys.just_pressed(config.controls_config.key_up);
+        || keys.just_pressed(config.controls_config.key_up)
+        || gamepad_just_pressed(&q_gamepads, config.controls_config.gamepad_jump);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MovementConfig;
+
+    #[test]
+    fn bounce_rebounds_with_restitution() {
+        assert_eq!(rebound_velocity(PLAYER_MAX_H_VEL, 0.5), Some(-0.75));
+        assert_eq!(rebound_velocity(-PLAYER_MAX_Y_VEL, 0.8), Some(4.));
+    }
+
+    #[test]
+    fn one_button_jumps_only_when_it_can() {
+        let mut player = PlayerMovement {
+            coyote_time_ticks_remaining: COYOTE_TIME_TICKS as isize,
+            ..default()
+        };
+        assert_eq!(one_button_action(&player), OneButtonAction::Jump);
+        player.coyote_time_ticks_remaining = 0;
+        assert_eq!(one_button_action(&player), OneButtonAction::TurnAround);
+    }
+
+    #[test]
+    fn input_lockout_runs_out() {
+        let mut player = PlayerMovement::default();
+        player.lock_input(0);
+        assert!(!player.input_locked_out());
+        player.lock_input(2);
+        assert!(player.input_locked_out());
+        player.tick_input_lockout();
+        assert!(player.input_locked_out());
+        player.tick_input_lockout();
+        assert!(!player.input_locked_out());
+    }
+
+    #[test]
+    fn movement_config_is_clamped() {
+        let movement_config = MovementConfig {
+            gravity_scale: 0.0,
+            jump_impulse: 100.0,
+            ..default()
+        };
+        assert!(movement_config.gravity() > 0.0);
+        assert_eq!(movement_config.jump_impulse(), PLAYER_MAX_Y_VEL);
+        assert_eq!(MovementConfig::default().gravity(), PLAYER_GRAVITY);
+    }
+
+    #[test]
+    fn respawn_forgets_jump_windows() {
+        let mut player = PlayerMovement {
+            should_jump_ticks_remaining: SHOULD_JUMP_TICKS as isize,
+            coyote_time_ticks_remaining: COYOTE_TIME_TICKS as isize,
+            ..default()
+        };
+        player.reset_jump_windows();
+        let jumps =
+            player.should_jump_ticks_remaining > 0 && player.coyote_time_ticks_remaining > 0;
+        assert!(!jumps);
+    }
+
+    #[test]
+    fn no_bounce_by_default_or_at_rest() {
+        assert_eq!(rebound_velocity(PLAYER_MAX_H_VEL, 0.0), None);
+        // standing on the floor is a hit at the speed of one tick of gravity
+        assert_eq!(rebound_velocity(-PLAYER_GRAVITY, 1.0), None);
+    }
 }
@@ -0,0 +1,79 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+
+use crate::config::{Config, PlayerSkinConfig};
+
+use super::animation::ANIMATION_FRAMES;
+
+/// [`Resource`] holding the sprite sheet the player is drawn with, loaded from the `skin` in the
+/// `player_config`. Skins that can't be used fall back to the default sheet with a warning.
+#[derive(Resource)]
+pub struct PlayerSkin {
+    pub image: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+}
+
+impl FromWorld for PlayerSkin {
+    fn from_world(world: &mut World) -> Self {
+        let skin = world.resource::<Config>().player_config.skin.clone();
+        let skin = match validate_skin(&skin, |path| Path::new("assets").join(path).exists()) {
+            Ok(()) => skin,
+            Err(reason) => {
+                warn!(
+                    "Can't use player skin {}: {reason}, using the default",
+                    skin.path
+                );
+                PlayerSkinConfig::default()
+            }
+        };
+
+        let layout =
+            world
+                .resource_mut::<Assets<TextureAtlasLayout>>()
+                .add(TextureAtlasLayout::from_grid(
+                    UVec2::new(skin.frame_width, skin.frame_height),
+                    skin.frames,
+                    1,
+                    None,
+                    None,
+                ));
+        PlayerSkin {
+            image: world.resource::<AssetServer>().load(skin.path),
+            layout,
+        }
+    }
+}
+
+/// Checks that `skin` can be drawn with the player's animations, where `exists` tells whether an
+/// asset path exists.
+fn validate_skin(skin: &PlayerSkinConfig, exists: impl Fn(&str) -> bool) -> Result<(), String> {
+    if skin.frames as usize != ANIMATION_FRAMES {
+        return Err(format!(
+            "it has {} frames instead of {ANIMATION_FRAMES}",
+            skin.frames
+        ));
+    }
+    if skin.frame_width == 0 || skin.frame_height == 0 {
+        return Err("its frames are empty".into());
+    }
+    if !exists(&skin.path) {
+        return Err("the file doesn't exist".into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skins_need_matching_frames() {
+        let skin = PlayerSkinConfig::default();
+        assert_eq!(validate_skin(&skin, |_| true), Ok(()));
+        assert!(validate_skin(&skin, |_| false).is_err());
+
+        let short = PlayerSkinConfig { frames: 10, ..skin };
+        assert!(validate_skin(&short, |_| true).is_err());
+    }
+}
@@ -7,10 +7,9 @@ use crate::{
 };
 
 use super::{
-    animation::{PlayerAnimationType, ANIMATION_FRAMES},
-    light::PlayerLightInventory,
-    movement::PlayerMovement,
-    PlayerBundle, PlayerHurtMarker, PlayerMarker,
+    animation::PlayerAnimationType, light::PlayerLightInventory, movement::PlayerMovement,
+    skin::PlayerSkin, PlayerBundle, PlayerHurtMarker, PlayerMarker, PLAYER_COLLIDER_OFFSET,
+    PLAYER_HALF_EXTENT,
 };
 
 /// Used by Ldtk to spawn the player correctly with all of the correct [`Component`]s.
@@ -27,9 +26,9 @@ pub fn init_player_bundle(_: &EntityInstance) -> PlayerBundle {
         },
         controller_output: KinematicCharacterControllerOutput::default(),
         collider: Collider::compound(vec![(
-            Vect::new(0.0, -2.0),
+            PLAYER_COLLIDER_OFFSET,
             Rot::default(),
-            Collider::cuboid(6.0, 7.0),
+            Collider::cuboid(PLAYER_HALF_EXTENT.x, PLAYER_HALF_EXTENT.y),
         )]),
         collision_groups: CollisionGroups::new(
             GroupLabel::PLAYER_COLLIDER,
@@ -54,27 +53,18 @@ pub fn init_player_bundle(_: &EntityInstance) -> PlayerBundle {
 pub fn add_player_sensors(
     mut commands: Commands,
     q_player: Query<Entity, Added<PlayerMarker>>,
-    asset_server: Res<AssetServer>,
-    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    skin: Res<PlayerSkin>,
 ) {
     let Ok(player) = q_player.get_single() else {
         return;
     };
 
-    let texture_atlas_layout = texture_atlas_layouts.add(TextureAtlasLayout::from_grid(
-        UVec2::new(15, 20),
-        ANIMATION_FRAMES as u32,
-        1,
-        None,
-        None,
-    ));
-
     // insert sprite here because it depends on texture atlas which needs a resource
     commands.entity(player).insert((
         Sprite {
-            image: asset_server.load("lyra_sheet.png"),
+            image: skin.image.clone(),
             texture_atlas: Some(TextureAtlas {
-                layout: texture_atlas_layout,
+                layout: skin.layout.clone(),
                 index: 0,
             }),
             ..default()
@@ -0,0 +1,179 @@
+use std::f32::consts::TAU;
+
+use bevy::{prelude::*, time::Real};
+
+use crate::{
+    config::Config,
+    input::gamepad_just_pressed,
+    level::LevelSystems,
+    shared::{AnimationState, GameState, ResetLevel},
+};
+
+use super::{
+    kill::{reset_player_on_kill, LastRespawn},
+    light::PlayerLightInventory,
+    PlayerMarker,
+};
+
+/// How many particles converge on Lyra while she materializes
+const SPAWN_IN_PARTICLES: usize = 8;
+/// How far from Lyra the particles start
+const SPAWN_IN_RADIUS: f32 = 20.0;
+
+/// [`Plugin`] for the optional spawn-in animation, where Lyra materializes at the spawn point after
+/// respawning while particles converge on her. It starts as soon as the player is moved back, so
+/// the fade from black reveals it, and control only returns once both are done.
+pub struct SpawnInPlugin;
+
+impl Plugin for SpawnInPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpawnIn>()
+            .add_systems(
+                Update,
+                start_spawn_in
+                    .after(reset_player_on_kill)
+                    .in_set(LevelSystems::Reset)
+                    .run_if(|config: Res<Config>| config.player_config.spawn_in_secs > 0.0),
+            )
+            .add_systems(
+                Update,
+                play_spawn_in.run_if(in_state(AnimationState::SpawnIn)),
+            );
+    }
+}
+
+/// [`Resource`] tracking the spawn-in animation.
+#[derive(Resource, Default)]
+pub struct SpawnIn {
+    /// Set while Lyra is materializing
+    timer: Option<Timer>,
+    /// Whether the fade from black has finished, or there was none
+    fade_done: bool,
+}
+
+impl SpawnIn {
+    pub fn is_playing(&self) -> bool {
+        self.timer.is_some()
+    }
+
+    /// Called once the fade from black finishes. Returns true if the spawn-in animation is still
+    /// playing, in which case it returns control to the player instead.
+    pub fn finish_fade(&mut self) -> bool {
+        self.fade_done = true;
+        self.is_playing()
+    }
+}
+
+/// [`Component`] for the particles converging on Lyra, which start at `from`, relative to her.
+#[derive(Component)]
+pub struct SpawnInParticle {
+    from: Vec2,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn start_spawn_in(
+    mut commands: Commands,
+    mut ev_reset_level: EventReader<ResetLevel>,
+    mut spawn_in: ResMut<SpawnIn>,
+    mut q_player: Query<(Entity, &mut Sprite, &PlayerLightInventory), With<PlayerMarker>>,
+    cur_game_state: Res<State<GameState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut next_anim_state: ResMut<NextState<AnimationState>>,
+    config: Res<Config>,
+) {
+    if !ev_reset_level.read().any(|ev| *ev == ResetLevel::Respawn) {
+        return;
+    }
+    // the hardcore run ended, so the player isn't coming back
+    if *cur_game_state.get() == GameState::Ui
+        || matches!(*next_game_state, NextState::Pending(GameState::Ui))
+    {
+        return;
+    }
+    let Ok((player_entity, mut player_sprite, inventory)) = q_player.get_single_mut() else {
+        return;
+    };
+    if spawn_in.is_playing() {
+        return;
+    }
+
+    *spawn_in = SpawnIn {
+        timer: Some(Timer::from_seconds(
+            config.player_config.spawn_in_secs,
+            TimerMode::Once,
+        )),
+        // instant death transitions respawn without a fade
+        fade_done: *cur_game_state.get() != GameState::Animating,
+    };
+    player_sprite.color.set_alpha(0.0);
+
+    let particle_color = match inventory.current_color {
+        Some(color) => color.light_beam_color(),
+        None => Color::WHITE,
+    };
+    commands.entity(player_entity).with_children(|player| {
+        for i in 0..SPAWN_IN_PARTICLES {
+            let from =
+                Vec2::from_angle(TAU * i as f32 / SPAWN_IN_PARTICLES as f32) * SPAWN_IN_RADIUS;
+            player.spawn((
+                Sprite::from_color(particle_color.with_alpha(0.0), Vec2::splat(2.0)),
+                Transform::from_translation(from.extend(1.0)),
+                SpawnInParticle { from },
+            ));
+        }
+    });
+    next_game_state.set(GameState::Animating);
+    next_anim_state.set(AnimationState::SpawnIn);
+}
+
+/// [`System`] that fades Lyra in while the particles converge on her. Jumping skips the rest.
+#[allow(clippy::too_many_arguments)]
+pub fn play_spawn_in(
+    mut commands: Commands,
+    mut spawn_in: ResMut<SpawnIn>,
+    mut q_player: Query<&mut Sprite, With<PlayerMarker>>,
+    mut q_particles: Query<
+        (Entity, &SpawnInParticle, &mut Transform, &mut Sprite),
+        Without<PlayerMarker>,
+    >,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut last_respawn: ResMut<LastRespawn>,
+    keys: Res<ButtonInput<KeyCode>>,
+    q_gamepads: Query<&Gamepad>,
+    config: Res<Config>,
+    time: Res<Time<Real>>,
+) {
+    let Some(timer) = spawn_in.timer.as_mut() else {
+        return;
+    };
+    if keys.just_pressed(config.controls_config.key_jump)
+        || gamepad_just_pressed(&q_gamepads, config.controls_config.gamepad_jump)
+    {
+        timer.set_elapsed(timer.duration());
+    }
+    timer.tick(time.delta());
+    let progress =
+        EasingCurve::new(0.0, 1.0, EaseFunction::QuadraticOut).sample_clamped(timer.fraction());
+
+    if let Ok(mut player_sprite) = q_player.get_single_mut() {
+        player_sprite.color.set_alpha(progress);
+    }
+    for (entity, particle, mut transform, mut sprite) in q_particles.iter_mut() {
+        if timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        transform.translation = (particle.from * (1.0 - progress)).extend(1.0);
+        // the particles fade in as they start moving, and out as they reach Lyra
+        sprite.color.set_alpha((1.0 - progress) * progress * 4.0);
+    }
+
+    if !timer.finished() {
+        return;
+    }
+    spawn_in.timer = None;
+    if spawn_in.fade_done {
+        next_game_state.set(GameState::Playing);
+        last_respawn.0 = Some(time.elapsed());
+    }
+}
@@ -0,0 +1,184 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use bevy::{prelude::*, time::Real};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    level::{snapshot::LevelSnapshot, stats::LevelBestStats, CurrentLevel, LevelSystems},
+    light::LightColor,
+    shared::ResetLevel,
+};
+
+/// The file of the first save slot, which is also where saves from before there were slots live.
+pub const SAVE_PATH: &str = "lightborne_save.toml";
+pub const SAVE_SLOT_COUNT: usize = 3;
+/// How long the [`SaveData`] has to go without changing before it is written, so that rapid level
+/// transitions don't write the file over and over.
+const SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// [`Plugin`] that loads the [`SaveData`] of the first save slot on startup and writes it back to
+/// disk shortly after it changes. The save slot menu can switch to another slot before playing.
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_save_slot(0).unwrap_or_default())
+            .insert_resource(ActiveSaveSlot(0))
+            .init_resource::<PendingSave>()
+            .add_systems(Update, record_level_progress.in_set(LevelSystems::Reset))
+            .add_systems(Last, write_save_data);
+    }
+}
+
+/// [`Resource`] holding when the [`SaveData`] last changed without being written yet.
+#[derive(Resource, Default)]
+pub struct PendingSave(Option<Duration>);
+
+impl PendingSave {
+    /// Drops the pending write, for when the save it would write no longer exists.
+    pub fn cancel(&mut self) {
+        self.0 = None;
+    }
+
+    /// Writes `save_data` to `slot` right away if a write is pending.
+    pub fn flush(&mut self, slot: usize, save_data: &SaveData) {
+        if self.0.take().is_some() {
+            write_save_slot(slot, save_data);
+        }
+    }
+}
+
+/// [`Resource`] holding the index of the save slot the [`SaveData`] is written to.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ActiveSaveSlot(pub usize);
+
+pub fn save_slot_path(slot: usize) -> String {
+    match slot {
+        0 => SAVE_PATH.to_string(),
+        _ => format!("lightborne_save_{}.toml", slot + 1),
+    }
+}
+
+/// Reads the [`SaveData`] of a save slot, or `None` if the slot is empty. A slot that can't be
+/// parsed is moved aside to a `.corrupt` file instead of being overwritten, and counts as empty.
+pub fn load_save_slot(slot: usize) -> Option<SaveData> {
+    let path = save_slot_path(slot);
+    let contents = std::fs::read_to_string(&path).ok()?;
+    match toml::from_str(&contents) {
+        Ok(save_data) => Some(save_data),
+        Err(err) => {
+            warn!("Failed to parse {path}, treating the slot as empty: {err}");
+            if let Err(err) = std::fs::rename(&path, format!("{path}.corrupt")) {
+                warn!("Failed to move {path} aside: {err}");
+            }
+            None
+        }
+    }
+}
+
+fn write_save_slot(slot: usize, save_data: &SaveData) {
+    let path = save_slot_path(slot);
+    let contents = match toml::to_string(save_data) {
+        Ok(contents) => contents,
+        Err(err) => {
+            warn!("Failed to serialize save data: {err}");
+            return;
+        }
+    };
+    // this will fail on wasm, where there is no filesystem to write to
+    if let Err(err) = std::fs::write(&path, contents) {
+        warn!("Failed to write {path}: {err}");
+    }
+}
+
+pub fn delete_save_slot(slot: usize) {
+    let path = save_slot_path(slot);
+    if let Err(err) = std::fs::remove_file(&path) {
+        warn!("Failed to delete {path}: {err}");
+    }
+}
+
+/// [`Resource`] holding everything that should persist between sessions.
+#[derive(Resource, Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct SaveData {
+    /// Iids of every level the player has entered at least once
+    pub visited_levels: HashSet<String>,
+    /// Best stats per level, keyed by the level's iid
+    pub level_stats: HashMap<String, LevelBestStats>,
+    /// The last quicksave, which is restored on quickload
+    pub quicksave: Option<LevelSnapshot>,
+    /// Ids of every tutorial the player has already been shown
+    pub seen_tutorials: HashSet<String>,
+    /// Ids of every cutscene the player has already watched
+    pub seen_cutscenes: HashSet<String>,
+    /// Iids of every shard the player has collected at least once
+    pub collected_shards: HashSet<String>,
+    /// Iid of the level the player was in last, which the game starts in when the save is loaded
+    pub last_level: Option<String>,
+    /// Every color allowed in a level the player has entered
+    pub unlocked_colors: HashSet<LightColor>,
+    /// Colors granted by permanent shards, which every level allows from then on
+    pub permanent_colors: HashSet<LightColor>,
+    /// Ids of every achievement the player has unlocked, see
+    /// [`Achievement`](crate::level::achievement::Achievement)
+    pub achievements: HashSet<String>,
+}
+
+impl SaveData {
+    /// A one line description of the progress in this save, for the save slot menu.
+    pub fn summary(&self) -> String {
+        format!(
+            "{} levels visited, {} completed, {} shards",
+            self.visited_levels.len(),
+            self.level_stats.len(),
+            self.collected_shards.len(),
+        )
+    }
+}
+
+/// [`System`] that records the level the player entered and the colors it allows in the
+/// [`SaveData`], so the game can pick up where it left off.
+pub fn record_level_progress(
+    mut ev_reset_level: EventReader<ResetLevel>,
+    current_level: Res<CurrentLevel>,
+    mut save_data: ResMut<SaveData>,
+) {
+    if !ev_reset_level.read().any(|x| *x == ResetLevel::Switching) {
+        return;
+    }
+    // only touched when something is new, since every change schedules a write
+    let level_iid = current_level.level_iid.as_str();
+    if save_data.last_level.as_deref() != Some(level_iid) {
+        save_data.last_level = Some(level_iid.to_string());
+    }
+    for (color, allowed) in current_level.allowed_colors.iter() {
+        if *allowed && !save_data.unlocked_colors.contains(&color) {
+            save_data.unlocked_colors.insert(color);
+        }
+    }
+}
+
+/// [`System`] that writes the [`SaveData`] to the active slot once it has gone [`SAVE_DEBOUNCE`]
+/// without changing, or right away when the game exits.
+fn write_save_data(
+    save_data: Res<SaveData>,
+    slot: Res<ActiveSaveSlot>,
+    mut pending: ResMut<PendingSave>,
+    mut ev_app_exit: EventReader<AppExit>,
+    time: Res<Time<Real>>,
+) {
+    if save_data.is_changed() {
+        pending.0 = Some(time.elapsed());
+    }
+    let Some(changed_at) = pending.0 else {
+        return;
+    };
+    if ev_app_exit.read().next().is_none() && time.elapsed() - changed_at < SAVE_DEBOUNCE {
+        return;
+    }
+    pending.flush(slot.0, &save_data);
+}
@@ -28,6 +28,36 @@ pub enum GameState {
     Animating,
     Paused,
     Ui,
+    /// The level complete summary is on screen, see
+    /// [`LevelStatsPlugin`](crate::level::stats::LevelStatsPlugin)
+    LevelComplete,
+}
+
+/// [`Event`] sent whenever [`GameState`] changes, so systems can react to any transition without
+/// polling the state.
+///
+/// This is sent in [`PreUpdate`] of the frame after the transition is applied, so `OnEnter` and
+/// `OnExit` systems have already run by the time it can be read, and [`State<GameState>`] already
+/// holds `to`.
+#[derive(Event, Debug, Clone)]
+pub struct GameStateChanged {
+    pub from: Option<GameState>,
+    pub to: Option<GameState>,
+}
+
+pub fn send_game_state_changed(
+    mut ev_transition: EventReader<StateTransitionEvent<GameState>>,
+    mut ev_game_state_changed: EventWriter<GameStateChanged>,
+) {
+    for transition in ev_transition.read() {
+        if transition.exited == transition.entered {
+            continue;
+        }
+        ev_game_state_changed.send(GameStateChanged {
+            from: transition.exited.clone(),
+            to: transition.entered.clone(),
+        });
+    }
 }
 
 #[derive(SubStates, Default, Debug, Clone, PartialEq, Eq, Hash)]
@@ -36,10 +66,14 @@ pub enum AnimationState {
     #[default]
     Switch,
     Respawn,
+    /// Lyra materializing at the spawn point, see
+    /// [`SpawnInPlugin`](crate::player::spawn_in::SpawnInPlugin)
+    SpawnIn,
     Shard,
     ShardDialogue, // FIXME: copied to shit LOL
     Cruciera,
     CrucieraDialogue,
+    Cutscene,
 }
 
 #[derive(SubStates, Default, Debug, Clone, PartialEq, Eq, Hash)]
@@ -49,6 +83,7 @@ pub enum UiState {
     LevelSelect,
     Settings,
     StartMenu,
+    SaveSlots,
 }
 
 #[derive(Event, PartialEq, Eq)]
@@ -58,4 +93,7 @@ pub enum ResetLevel {
     Respawn,
     /// Sent to run systems that reset the level state on level switch
     Switching,
+    /// Sent to reset the level in place before a quickload restores its snapshot. Level entities
+    /// are reset like on a level switch, but it isn't a death, so it isn't counted or penalized
+    Quickload,
 }
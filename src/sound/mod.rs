@@ -5,16 +5,71 @@ use bevy::{
     prelude::*,
 };
 
+use crate::config::Config;
+
 pub struct SoundPlugin;
 
 impl Plugin for SoundPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<BgmTracks>()
+            .init_resource::<AudioSettings>()
             .add_event::<ChangeBgmEvent>()
-            .add_systems(Update, (handle_change_bgm_event, fade_bgm));
+            .add_systems(
+                Update,
+                (
+                    apply_master_volume.run_if(resource_changed::<AudioSettings>),
+                    handle_change_bgm_event,
+                    fade_bgm,
+                ),
+            );
     }
 }
 
+/// [`Resource`] for the volume of each audio bus, from 0 (muted) to 1. It starts out as the
+/// `audio_config`, and the master volume is changed by the volume setting.
+#[derive(Resource, Debug)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub sfx: f32,
+    pub music: f32,
+}
+
+impl FromWorld for AudioSettings {
+    fn from_world(world: &mut World) -> Self {
+        let audio_config = &world.resource::<Config>().audio_config;
+        AudioSettings {
+            master: audio_config.master,
+            sfx: audio_config.sfx,
+            music: audio_config.music,
+        }
+    }
+}
+
+impl AudioSettings {
+    /// The volume of the music bus, not counting the master volume, which bevy applies through
+    /// [`GlobalVolume`].
+    pub fn music_volume(&self) -> f32 {
+        self.music.max(0.0)
+    }
+
+    /// A one-shot sound effect playing `source` at the sfx volume, which despawns once it is done.
+    /// Returns [`None`] if the sfx bus or the master volume is muted, so nothing is spawned.
+    pub fn sfx(&self, source: Handle<AudioSource>) -> Option<(AudioPlayer, PlaybackSettings)> {
+        if self.master <= 0.0 || self.sfx <= 0.0 {
+            return None;
+        }
+        Some((
+            AudioPlayer::new(source),
+            PlaybackSettings::DESPAWN.with_volume(Volume::new(self.sfx)),
+        ))
+    }
+}
+
+/// [`System`] that applies the master volume to every sound through bevy's [`GlobalVolume`].
+fn apply_master_volume(settings: Res<AudioSettings>, mut global_volume: ResMut<GlobalVolume>) {
+    global_volume.volume = Volume::new(settings.master.max(0.0));
+}
+
 #[derive(Component, Default)]
 pub struct BgmMarker;
 
@@ -139,17 +194,19 @@ fn fade_bgm(
     mut audio_sink: Query<(&mut AudioSink, Entity, &mut Fade, &FadeSettings)>,
     time: Res<Time>,
     global_volume: Res<GlobalVolume>,
+    audio_settings: Res<AudioSettings>,
 ) {
+    let volume = global_volume.volume.get() * audio_settings.music_volume();
     for (audio, entity, mut fade, fade_settings) in audio_sink.iter_mut() {
         fade.timer.tick(time.delta());
         let progress = fade.timer.elapsed_secs() / fade.timer.duration().as_secs_f32();
-        audio.set_volume(fade.from.lerp(fade.to, progress) * global_volume.volume.get());
+        audio.set_volume(fade.from.lerp(fade.to, progress) * volume);
         if !fade.timer.just_finished() {
             continue;
         }
 
         // make sure its actually the end vol
-        audio.set_volume(fade.to * global_volume.volume.get());
+        audio.set_volume(fade.to * volume);
 
         match fade_settings {
             FadeSettings::Continue => {
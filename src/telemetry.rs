@@ -0,0 +1,237 @@
+use std::{fs::OpenOptions, io::Write};
+
+use bevy::prelude::*;
+
+use crate::{
+    config::Config,
+    level::{
+        level_completion::{GameCompleteEvent, LevelCompleteEvent},
+        shard::{CrystalShard, ShardCollectedEvent},
+        stats::{record_level_stats, LevelAttempt},
+        CurrentLevel,
+    },
+    player::{kill::KillPlayerEvent, PlayerMarker},
+    shared::{GameState, GameStateChanged, ResetLevel},
+};
+
+const CSV_HEADER: &str = "session_secs,event,level_iid,x,y,detail";
+
+/// [`Plugin`] for the opt-in playtesting telemetry, which appends a CSV row for every death,
+/// collected shard, level entered or completed, the game being completed, and [`GameState`]
+/// change. Rows are buffered and written to disk every few seconds and on exit, so logging costs
+/// next to nothing.
+pub struct TelemetryPlugin;
+
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TelemetryLog>()
+            .add_systems(
+                Update,
+                (
+                    log_deaths,
+                    log_collected_shards,
+                    log_entered_levels,
+                    log_completed_levels.before(record_level_stats),
+                    log_completed_game,
+                    log_game_state_changes,
+                    flush_telemetry,
+                )
+                    .chain()
+                    .run_if(|config: Res<Config>| config.telemetry_config.enabled),
+            )
+            .add_systems(
+                Last,
+                flush_telemetry_on_exit
+                    .run_if(on_event::<AppExit>)
+                    .run_if(|config: Res<Config>| config.telemetry_config.enabled),
+            );
+    }
+}
+
+/// [`Resource`] buffering the rows that haven't been written to disk yet.
+#[derive(Resource)]
+pub struct TelemetryLog {
+    rows: Vec<String>,
+    flush_timer: Timer,
+}
+
+impl FromWorld for TelemetryLog {
+    fn from_world(world: &mut World) -> Self {
+        let config = world.resource::<Config>();
+        TelemetryLog {
+            // marks where each session starts, since every session appends to the same file
+            rows: vec![csv_row(0.0, "session_start", "", None, "")],
+            flush_timer: Timer::from_seconds(
+                config.telemetry_config.flush_secs,
+                TimerMode::Repeating,
+            ),
+        }
+    }
+}
+
+impl TelemetryLog {
+    fn push(
+        &mut self,
+        time: &Time<Real>,
+        event: &str,
+        current_level: &CurrentLevel,
+        pos: Option<Vec2>,
+        detail: &str,
+    ) {
+        self.rows.push(csv_row(
+            time.elapsed_secs(),
+            event,
+            current_level.level_iid.as_str(),
+            pos,
+            detail,
+        ));
+    }
+
+    /// Appends the buffered rows to the file at `path`, writing the header first if the file is
+    /// new.
+    fn flush(&mut self, path: &str) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path);
+        // this will fail on wasm, where there is no filesystem to write to
+        let mut file = match file {
+            Ok(file) => file,
+            Err(err) => {
+                warn!("Failed to open {path}: {err}");
+                self.rows.clear();
+                return;
+            }
+        };
+        let mut contents = String::new();
+        if file.metadata().is_ok_and(|metadata| metadata.len() == 0) {
+            contents.push_str(CSV_HEADER);
+            contents.push('\n');
+        }
+        for row in self.rows.drain(..) {
+            contents.push_str(&row);
+            contents.push('\n');
+        }
+        if let Err(err) = file.write_all(contents.as_bytes()) {
+            warn!("Failed to write {path}: {err}");
+        }
+    }
+}
+
+fn csv_row(secs: f32, event: &str, level_iid: &str, pos: Option<Vec2>, detail: &str) -> String {
+    let (x, y) = match pos {
+        Some(pos) => (format!("{:.1}", pos.x), format!("{:.1}", pos.y)),
+        None => (String::new(), String::new()),
+    };
+    // none of the fields contain commas or quotes, except maybe hand written level iids
+    let level_iid = level_iid.replace(',', ";");
+    format!("{secs:.3},{event},{level_iid},{x},{y},{detail}")
+}
+
+fn log_deaths(
+    mut log: ResMut<TelemetryLog>,
+    mut ev_kill_player: EventReader<KillPlayerEvent>,
+    game_state: Res<State<GameState>>,
+    current_level: Res<CurrentLevel>,
+    q_player: Query<&Transform, With<PlayerMarker>>,
+    time: Res<Time<Real>>,
+) {
+    for event in ev_kill_player.read() {
+        // kills while animating don't start another death
+        if *game_state != GameState::Playing {
+            continue;
+        }
+        let pos = q_player
+            .get_single()
+            .ok()
+            .map(|transform| transform.translation.xy());
+        let cause = format!("{:?}", event.cause);
+        log.push(&time, "death", &current_level, pos, &cause);
+    }
+}
+
+fn log_collected_shards(
+    mut log: ResMut<TelemetryLog>,
+    mut ev_shard_collected: EventReader<ShardCollectedEvent>,
+    q_shards: Query<&GlobalTransform, With<CrystalShard>>,
+    current_level: Res<CurrentLevel>,
+    time: Res<Time<Real>>,
+) {
+    for ev in ev_shard_collected.read() {
+        let color = format!("{:?}", ev.light_color);
+        let pos = q_shards
+            .get(ev.shard)
+            .ok()
+            .map(|transform| transform.translation().xy());
+        log.push(&time, "shard_collected", &current_level, pos, &color);
+    }
+}
+
+fn log_entered_levels(
+    mut log: ResMut<TelemetryLog>,
+    mut ev_reset_level: EventReader<ResetLevel>,
+    current_level: Res<CurrentLevel>,
+    time: Res<Time<Real>>,
+) {
+    if ev_reset_level.read().any(|ev| *ev == ResetLevel::Switching) {
+        log.push(&time, "level_entered", &current_level, None, "");
+    }
+}
+
+/// Runs before the stats are recorded, since that clears the [`LevelAttempt`].
+fn log_completed_levels(
+    mut log: ResMut<TelemetryLog>,
+    mut ev_level_complete: EventReader<LevelCompleteEvent>,
+    attempt: Res<LevelAttempt>,
+    current_level: Res<CurrentLevel>,
+    time: Res<Time<Real>>,
+) {
+    for event in ev_level_complete.read() {
+        if attempt.level_iid.as_ref() != Some(&event.level_iid) {
+            continue;
+        }
+        let detail = format!(
+            "{:.3}s {} deaths",
+            attempt.timer.elapsed_secs(),
+            attempt.deaths
+        );
+        log.push(&time, "level_completed", &current_level, None, &detail);
+    }
+}
+
+fn log_completed_game(
+    mut log: ResMut<TelemetryLog>,
+    mut ev_game_complete: EventReader<GameCompleteEvent>,
+    current_level: Res<CurrentLevel>,
+    time: Res<Time<Real>>,
+) {
+    for event in ev_game_complete.read() {
+        let detail = format!("last level {}", event.level_iid);
+        log.push(&time, "game_completed", &current_level, None, &detail);
+    }
+}
+
+fn log_game_state_changes(
+    mut log: ResMut<TelemetryLog>,
+    mut ev_game_state_changed: EventReader<GameStateChanged>,
+    current_level: Res<CurrentLevel>,
+    time: Res<Time<Real>>,
+) {
+    for event in ev_game_state_changed.read() {
+        let (Some(from), Some(to)) = (&event.from, &event.to) else {
+            continue;
+        };
+        let detail = format!("{from:?}->{to:?}");
+        log.push(&time, "game_state", &current_level, None, &detail);
+    }
+}
+
+fn flush_telemetry(mut log: ResMut<TelemetryLog>, config: Res<Config>, time: Res<Time<Real>>) {
+    if log.flush_timer.tick(time.delta()).just_finished() {
+        log.flush(&config.telemetry_config.path);
+    }
+}
+
+fn flush_telemetry_on_exit(mut log: ResMut<TelemetryLog>, config: Res<Config>) {
+    log.flush(&config.telemetry_config.path);
+}
@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+
+use crate::{
+    config::Config,
+    level::CurrentLevel,
+    light::palette::ColorPalette,
+    player::{
+        kill::{DeathCounter, DeathRecorded},
+        light::PlayerLightInventory,
+        PlayerMarker,
+    },
+    shared::GameState,
+};
+
+/// [`Plugin`] for the optional HUD in the top left corner, showing the player's current color and
+/// how many times they've died in the current level.
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HudVisible>()
+            .add_systems(Update, (toggle_hud, update_hud).chain());
+    }
+}
+
+/// [`Resource`] for whether the HUD is shown, which starts out as configured and is flipped by the
+/// toggle key.
+#[derive(Resource)]
+pub struct HudVisible(pub bool);
+
+impl FromWorld for HudVisible {
+    fn from_world(world: &mut World) -> Self {
+        HudVisible(world.resource::<Config>().hud_config.hud)
+    }
+}
+
+#[derive(Component)]
+pub struct HudMarker;
+
+#[derive(Component)]
+pub struct HudColorSwatch;
+
+#[derive(Component)]
+pub struct HudDeathsText;
+
+fn toggle_hud(
+    keys: Res<ButtonInput<KeyCode>>,
+    config: Res<Config>,
+    mut hud_visible: ResMut<HudVisible>,
+) {
+    if keys.just_pressed(config.hud_config.key_toggle) {
+        hud_visible.0 = !hud_visible.0;
+    }
+}
+
+/// [`System`] that spawns the HUD the first time it is shown, and keeps it in sync with the player.
+/// The death count is only rebuilt when a death is recorded or the level changes. The HUD is hidden
+/// in menus.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn update_hud(
+    mut commands: Commands,
+    hud_visible: Res<HudVisible>,
+    game_state: Res<State<GameState>>,
+    q_player: Query<&PlayerLightInventory, With<PlayerMarker>>,
+    death_counter: Res<DeathCounter>,
+    mut ev_death_recorded: EventReader<DeathRecorded>,
+    current_level: Res<CurrentLevel>,
+    mut q_hud: Query<&mut Visibility, With<HudMarker>>,
+    mut q_swatch: Query<&mut BackgroundColor, With<HudColorSwatch>>,
+    mut q_deaths: Query<&mut Text, With<HudDeathsText>>,
+    asset_server: Res<AssetServer>,
+    palette: Res<ColorPalette>,
+) {
+    let show = hud_visible.0 && *game_state != GameState::Ui;
+
+    let Ok(mut visibility) = q_hud.get_single_mut() else {
+        if show {
+            spawn_hud(&mut commands, &asset_server);
+        }
+        return;
+    };
+    visibility.set_if_neq(if show {
+        Visibility::Visible
+    } else {
+        Visibility::Hidden
+    });
+    // kept up to date while hidden, since deaths recorded in the meantime would be missed
+    if let Ok(mut text) = q_deaths.get_single_mut() {
+        let total = match ev_death_recorded.read().last() {
+            Some(death_recorded) => Some(death_recorded.total),
+            // the counter also changes without a death when a new game resets it
+            None if current_level.is_changed()
+                || death_counter.is_changed()
+                || text.0.is_empty() =>
+            {
+                Some(death_counter.total())
+            }
+            None => None,
+        };
+        if let Some(total) = total {
+            text.0 = format!(
+                "Deaths: {} ({total} total)",
+                death_counter.level(&current_level.level_iid)
+            );
+        }
+    }
+    if !show {
+        return;
+    }
+
+    let swatch_color = match q_player.get_single().ok().and_then(|i| i.current_color) {
+        Some(color) => palette.indicator_color(color),
+        None => Color::srgba(1.0, 1.0, 1.0, 0.15),
+    };
+    if let Ok(mut swatch) = q_swatch.get_single_mut() {
+        swatch.set_if_neq(BackgroundColor(swatch_color));
+    }
+}
+
+fn spawn_hud(commands: &mut Commands, asset_server: &AssetServer) {
+    let font = TextFont {
+        font: asset_server.load("fonts/Outfit-Medium.ttf"),
+        ..default()
+    };
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(32.),
+                top: Val::Px(32.),
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(12.),
+                ..default()
+            },
+            HudMarker,
+        ))
+        .with_children(|hud| {
+            hud.spawn((
+                Node {
+                    width: Val::Px(16.),
+                    height: Val::Px(16.),
+                    ..default()
+                },
+                BackgroundColor(Color::NONE),
+                HudColorSwatch,
+            ));
+            hud.spawn((
+                Text::new(""),
+                font.with_font_size(20.),
+                TextColor(Color::srgba(1.0, 1.0, 1.0, 0.7)),
+                HudDeathsText,
+            ));
+        });
+}
@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    config::Config,
+    level::CurrentLevel,
+    shared::{GameState, ResetLevel},
+};
+
+/// How much of the intro is spent fading in, and again fading out
+const INTRO_FADE_FRACTION: f32 = 0.25;
+
+/// [`Plugin`] for the title shown over the top of the screen when entering a level that has one,
+/// set by the level's `IntroTitle` and `IntroSubtitle` fields. The title only fades in and out, so
+/// the player can keep moving while it's shown.
+pub struct LevelIntroPlugin;
+
+impl Plugin for LevelIntroPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (spawn_level_intro, update_level_intro).chain());
+    }
+}
+
+/// [`Component`] for the node holding a level's intro text, which is despawned once `timer`
+/// finishes.
+#[derive(Component)]
+pub struct LevelIntro {
+    timer: Timer,
+}
+
+/// How opaque the intro text is `elapsed` into an intro lasting `duration`, easing in at the start
+/// and out at the end like the camera transitions.
+fn intro_alpha(elapsed: Duration, duration: Duration) -> f32 {
+    let t = elapsed.as_secs_f32() / duration.as_secs_f32().max(f32::EPSILON);
+    let fade = t.min(1.0 - t) / INTRO_FADE_FRACTION;
+    EasingCurve::new(0.0, 1.0, EaseFunction::SineInOut).sample_clamped(fade)
+}
+
+/// [`System`] that shows the intro of the level the player just switched to, replacing the last
+/// one if it was still showing.
+pub fn spawn_level_intro(
+    mut commands: Commands,
+    mut ev_reset_level: EventReader<ResetLevel>,
+    current_level: Res<CurrentLevel>,
+    q_intro: Query<Entity, With<LevelIntro>>,
+    asset_server: Res<AssetServer>,
+    config: Res<Config>,
+) {
+    if !ev_reset_level.read().any(|ev| *ev == ResetLevel::Switching) {
+        return;
+    }
+    for entity in q_intro.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some(intro) = current_level.intro.as_ref() else {
+        return;
+    };
+    if config.hud_config.intro_secs <= 0.0 {
+        return;
+    }
+
+    let font = TextFont {
+        font: asset_server.load("fonts/Outfit-Medium.ttf"),
+        ..default()
+    };
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                top: Val::Percent(18.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(8.),
+                ..default()
+            },
+            LevelIntro {
+                timer: Timer::from_seconds(config.hud_config.intro_secs, TimerMode::Once),
+            },
+        ))
+        .with_children(|container| {
+            container.spawn((
+                Text::new(intro.title.clone()),
+                font.clone().with_font_size(48.),
+                TextColor(Color::WHITE.with_alpha(0.0)),
+            ));
+            if let Some(subtitle) = &intro.subtitle {
+                container.spawn((
+                    Text::new(subtitle.clone()),
+                    font.with_font_size(24.),
+                    TextColor(Color::WHITE.with_alpha(0.0)),
+                ));
+            }
+        });
+}
+
+/// [`System`] that fades the level intro in and out, and despawns it once it's done, the skip key
+/// is pressed, or a menu is opened.
+pub fn update_level_intro(
+    mut commands: Commands,
+    mut q_intro: Query<(Entity, &mut LevelIntro, &Children)>,
+    mut q_text: Query<&mut TextColor>,
+    keys: Res<ButtonInput<KeyCode>>,
+    game_state: Res<State<GameState>>,
+    config: Res<Config>,
+    time: Res<Time<Real>>,
+) {
+    for (entity, mut intro, children) in q_intro.iter_mut() {
+        intro.timer.tick(time.delta());
+        if intro.timer.finished()
+            || keys.just_pressed(config.hud_config.key_skip_intro)
+            || *game_state == GameState::Ui
+        {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let alpha = intro_alpha(intro.timer.elapsed(), intro.timer.duration());
+        for child in children.iter() {
+            if let Ok(mut text_color) = q_text.get_mut(*child) {
+                text_color.0.set_alpha(alpha);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intro_fades_in_and_out() {
+        let duration = Duration::from_secs(4);
+        assert_eq!(intro_alpha(Duration::ZERO, duration), 0.0);
+        assert_eq!(intro_alpha(Duration::from_secs(2), duration), 1.0);
+        assert_eq!(intro_alpha(duration, duration), 0.0);
+        let fading_in = intro_alpha(Duration::from_millis(500), duration);
+        assert!(fading_in > 0.0 && fading_in < 1.0);
+    }
+}
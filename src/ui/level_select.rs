@@ -97,6 +97,21 @@ impl PartialOrd for LevelSaveData {
 #[derive(Resource)]
 pub struct Levels(pub Vec<LevelSaveData>);
 
+impl Levels {
+    /// Marks the level with `level_iid` complete and unlocks the level after it, returning the
+    /// `level_iid` of that level, or [`None`] if it was the last one.
+    pub fn complete(&mut self, level_iid: &LevelIid) -> Option<LevelIid> {
+        let i = self
+            .0
+            .iter()
+            .position(|level| level.level_iid == *level_iid)?;
+        self.0[i].complete = true;
+        let next = self.0.get_mut(i + 1)?;
+        next.locked = false;
+        Some(next.level_iid.clone())
+    }
+}
+
 fn init_levels(
     mut res_levels: ResMut<Levels>,
     query_ldtk: Query<&LdtkProjectHandle>,
@@ -1,4 +1,7 @@
+pub mod hud;
+pub mod level_intro;
 pub mod level_select;
 pub mod pause;
+pub mod save_slots;
 pub mod settings;
 pub mod start_menu;
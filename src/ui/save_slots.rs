@@ -0,0 +1,186 @@
+use bevy::prelude::*;
+
+use crate::{
+    level::shard_counter::ShardCounter,
+    player::kill::DeathCounter,
+    save::{
+        delete_save_slot, load_save_slot, ActiveSaveSlot, PendingSave, SaveData, SAVE_SLOT_COUNT,
+    },
+    shared::{GameState, UiState},
+    sound::{BgmTrack, ChangeBgmEvent},
+};
+
+/// [`Plugin`] for the menu opened by Play on the start menu, where the player picks which save slot
+/// to play, or deletes one.
+pub struct SaveSlotsPlugin;
+
+impl Plugin for SaveSlotsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                spawn_save_slots.run_if(in_state(UiState::SaveSlots)),
+                despawn_save_slots.run_if(not(in_state(UiState::SaveSlots))),
+                handle_save_slot_buttons.run_if(in_state(UiState::SaveSlots)),
+            ),
+        );
+    }
+}
+
+#[derive(Component)]
+pub struct SaveSlotsMarker;
+
+#[derive(Component)]
+pub enum SaveSlotButton {
+    Play(usize),
+    Delete(usize),
+    Back,
+}
+
+fn spawn_save_slots(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    q_save_slots: Query<Entity, With<SaveSlotsMarker>>,
+    mut ev_change_bgm: EventWriter<ChangeBgmEvent>,
+) {
+    if q_save_slots.get_single().is_ok() {
+        return;
+    }
+
+    let font = TextFont {
+        font: asset_server.load("fonts/Outfit-Medium.ttf"),
+        ..default()
+    };
+
+    ev_change_bgm.send(ChangeBgmEvent(BgmTrack::LevelSelect));
+
+    // read every time the menu opens, so deleted and newly created slots show up
+    let slots = (0..SAVE_SLOT_COUNT)
+        .map(load_save_slot)
+        .collect::<Vec<Option<SaveData>>>();
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(32.0),
+                ..default()
+            },
+            BackgroundColor(Color::BLACK),
+            SaveSlotsMarker,
+        ))
+        .with_children(|container| {
+            container.spawn((Text::new("Save Slots"), font.clone().with_font_size(48.)));
+            for (slot, save_data) in slots.iter().enumerate() {
+                container
+                    .spawn(Node {
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(32.0),
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        let summary = match save_data {
+                            Some(save_data) => save_data.summary(),
+                            None => "Empty".to_string(),
+                        };
+                        row.spawn((
+                            Text::new(format!("Slot {}: {summary}", slot + 1)),
+                            font.clone().with_font_size(24.),
+                        ));
+                        row.spawn((
+                            Text::new(if save_data.is_some() {
+                                "Continue"
+                            } else {
+                                "New Game"
+                            }),
+                            font.clone().with_font_size(32.),
+                            Button,
+                            SaveSlotButton::Play(slot),
+                        ));
+                        if save_data.is_some() {
+                            row.spawn((
+                                Text::new("Delete"),
+                                font.clone().with_font_size(32.),
+                                Button,
+                                SaveSlotButton::Delete(slot),
+                            ));
+                        }
+                    });
+            }
+            container.spawn((
+                Text::new("Back"),
+                font.clone().with_font_size(48.),
+                Button,
+                SaveSlotButton::Back,
+            ));
+        });
+}
+
+fn despawn_save_slots(mut commands: Commands, query: Query<Entity, With<SaveSlotsMarker>>) {
+    let Ok(entity) = query.get_single() else {
+        return;
+    };
+    commands.entity(entity).despawn_recursive();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_save_slot_buttons(
+    mut commands: Commands,
+    q_button: Query<(&Interaction, &SaveSlotButton), Changed<Interaction>>,
+    q_save_slots: Query<Entity, With<SaveSlotsMarker>>,
+    mut save_data: ResMut<SaveData>,
+    mut active_slot: ResMut<ActiveSaveSlot>,
+    mut pending_save: ResMut<PendingSave>,
+    mut shard_counter: ResMut<ShardCounter>,
+    mut death_counter: ResMut<DeathCounter>,
+    mut next_ui_state: ResMut<NextState<UiState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    asset_server: Res<AssetServer>,
+) {
+    for (interaction, button) in q_button.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        commands.spawn((
+            AudioPlayer::new(asset_server.load("sfx/click.wav")),
+            PlaybackSettings::DESPAWN,
+        ));
+
+        next_game_state.set(GameState::Ui);
+        match button {
+            SaveSlotButton::Play(slot) => {
+                if *slot != active_slot.0 {
+                    // the old slot might still have a write pending
+                    pending_save.flush(active_slot.0, &save_data);
+                    // an empty slot starts a new save, which is written shortly after it changes
+                    *save_data = load_save_slot(*slot).unwrap_or_default();
+                    active_slot.0 = *slot;
+                }
+                shard_counter.restore_collected(&save_data);
+                // deaths count from the start of every game
+                *death_counter = DeathCounter::default();
+                next_ui_state.set(UiState::LevelSelect);
+            }
+            SaveSlotButton::Delete(slot) => {
+                delete_save_slot(*slot);
+                if *slot == active_slot.0 {
+                    // bypassed so the deleted save isn't written right back to disk
+                    *save_data.bypass_change_detection() = SaveData::default();
+                    pending_save.cancel();
+                    shard_counter.restore_collected(&save_data);
+                }
+                // respawned next frame with the slot now empty
+                for entity in q_save_slots.iter() {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+            SaveSlotButton::Back => {
+                next_ui_state.set(UiState::StartMenu);
+            }
+        }
+    }
+}
@@ -1,13 +1,16 @@
 use std::ops::RangeInclusive;
 
-use bevy::audio::Volume;
 use bevy::prelude::*;
 use enum_map::{enum_map, Enum, EnumMap};
 
 use crate::camera::handle_move_camera;
+use crate::config::Config;
+use crate::level::shard_counter::ShardCounter;
 use crate::level::speedrun::SpeedrunTimer;
+use crate::light::palette::{ColorPalette, PaletteMode};
+use crate::player::light::AimTimeScale;
 use crate::shared::{GameState, UiState};
-use crate::sound::{BgmTrack, ChangeBgmEvent};
+use crate::sound::{AudioSettings, BgmTrack, ChangeBgmEvent};
 
 pub struct SettingsPlugin;
 
@@ -97,9 +100,12 @@ pub struct UpdateSetting(SettingName);
 pub enum SettingName {
     Volume,
     SpeedrunTimer,
+    ShardCounter,
+    AimSlowdown,
+    ColorblindPalette,
 }
 
-fn init_settings() -> Settings {
+fn init_settings(config: &Config) -> Settings {
     // Settings(vec![Setting::new_slider(
     //     "Volume".to_owned(),
     //     100.0,
@@ -109,7 +115,7 @@ fn init_settings() -> Settings {
     Settings(enum_map! {
         SettingName::Volume => Setting::new_slider(
             "Volume".to_owned(),
-            100.0,
+            (config.audio_config.master * 100.0).clamp(0.0, 100.0),
             0.0..=100.0,
             "%".to_owned(),
         ),
@@ -117,12 +123,25 @@ fn init_settings() -> Settings {
             "Speedrun Timer".to_owned(),
             false,
         ),
+        SettingName::ShardCounter => Setting::new_toggle(
+            "Shard Counter".to_owned(),
+            false,
+        ),
+        SettingName::AimSlowdown => Setting::new_toggle(
+            "Slow Time While Aiming".to_owned(),
+            false,
+        ),
+        SettingName::ColorblindPalette => Setting::new_toggle(
+            "Colorblind Palette".to_owned(),
+            config.assist_config.color_palette == PaletteMode::Colorblind,
+        ),
     })
 }
 
 impl Plugin for SettingsPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(init_settings())
+        let settings = init_settings(app.world().resource::<Config>());
+        app.insert_resource(settings)
             .add_event::<RedrawSetting>()
             .add_event::<UpdateSetting>()
             .add_systems(
@@ -452,8 +471,11 @@ fn redraw_setting(
 fn update_setting(
     mut ev: EventReader<UpdateSetting>,
     settings: Res<Settings>,
-    mut global_volume: ResMut<GlobalVolume>,
+    mut audio_settings: ResMut<AudioSettings>,
     mut speedrun_timer: ResMut<SpeedrunTimer>,
+    mut shard_counter: ResMut<ShardCounter>,
+    mut aim_time_scale: ResMut<AimTimeScale>,
+    mut palette: ResMut<ColorPalette>,
 ) {
     for UpdateSetting(setting_name) in ev.read() {
         let setting = &settings.0[*setting_name];
@@ -462,7 +484,7 @@ fn update_setting(
                 let SettingVariant::Slider { ref value, .. } = setting.variant else {
                     continue;
                 };
-                global_volume.volume = Volume::new(value.value / 100.0);
+                audio_settings.master = value.value / 100.0;
             }
             SettingName::SpeedrunTimer => {
                 let SettingVariant::Toggle { ref value, .. } = setting.variant else {
@@ -470,6 +492,28 @@ fn update_setting(
                 };
                 speedrun_timer.enabled = value.value;
             }
+            SettingName::ShardCounter => {
+                let SettingVariant::Toggle { ref value, .. } = setting.variant else {
+                    continue;
+                };
+                shard_counter.enabled = value.value;
+            }
+            SettingName::AimSlowdown => {
+                let SettingVariant::Toggle { ref value, .. } = setting.variant else {
+                    continue;
+                };
+                aim_time_scale.enabled = value.value;
+            }
+            SettingName::ColorblindPalette => {
+                let SettingVariant::Toggle { ref value, .. } = setting.variant else {
+                    continue;
+                };
+                palette.mode = if value.value {
+                    PaletteMode::Colorblind
+                } else {
+                    PaletteMode::Standard
+                };
+            }
         }
     }
 }
@@ -129,7 +129,7 @@ fn start_game(
                 next_game_state.set(GameState::Ui);
                 match button_marker {
                     StartMenuButtonMarker::Play => {
-                        next_ui_state.set(UiState::LevelSelect);
+                        next_ui_state.set(UiState::SaveSlots);
                     }
                     StartMenuButtonMarker::Settings => {
                         next_ui_state.set(UiState::Settings);